@@ -20,6 +20,8 @@ pub struct CliStart {
     pub prev_kbd_bright: bool,
     #[options(meta = "", help = "Set your battery charge limit <20-100>")]
     pub chg_limit: Option<u8>,
+    #[options(help = "Charge to 100% once, the limit is restored when AC is unplugged")]
+    pub one_shot_chg: bool,
     #[options(command)]
     pub command: Option<CliCommand>,
 }
@@ -28,6 +30,10 @@ pub struct CliStart {
 pub enum CliCommand {
     #[options(help = "Set the keyboard lighting from built-in modes")]
     LedMode(LedModeCommand),
+    #[options(help = "Apply an exported Aura effect or full Aura config from a file")]
+    LedLoad(LedLoadCommand),
+    #[options(name = "aura", help = "Keyboard lighting recovery")]
+    Aura(AuraCommand),
     #[options(help = "Set the LED power states")]
     LedPow1(LedPowerCommand1),
     #[options(help = "Set the LED power states")]
@@ -56,6 +62,39 @@ pub struct LedModeCommand {
     pub command: Option<SetAuraBuiltin>,
 }
 
+#[derive(Options)]
+pub struct LedLoadCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        free,
+        help = "path to a single effect, an exported Aura config, or aura.ron"
+    )]
+    pub file: Option<String>,
+}
+
+#[derive(Options)]
+pub struct AuraCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<AuraSubCommand>,
+}
+
+#[derive(Options)]
+pub enum AuraSubCommand {
+    #[options(help = "Reset the keyboard to solid white at full brightness, all LEDs on")]
+    Safe(LedSafeCommand),
+}
+
+#[derive(Options)]
+pub struct LedSafeCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "store this as the lighting config, otherwise it is reverted on reboot")]
+    pub save: bool,
+}
+
 #[derive(Options)]
 pub struct GraphicsCommand {
     #[options(help = "print help message")]
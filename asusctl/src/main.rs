@@ -5,7 +5,9 @@ use std::process::Command;
 use std::thread::sleep;
 
 use anime_cli::{AnimeActions, AnimeCommand};
+use asusd::ctrl_aura::config::AuraConfig;
 use aura_cli::{LedPowerCommand1, LedPowerCommand2};
+use config_traits::ron;
 use dmi_id::DMIID;
 use gumdrop::{Opt, Options};
 use profiles_cli::{FanCurveCommand, ProfileCommand};
@@ -110,6 +112,8 @@ fn do_parsed(
 ) -> Result<(), Box<dyn std::error::Error>> {
     match &parsed.command {
         Some(CliCommand::LedMode(mode)) => handle_led_mode(dbus, &supported.keyboard_led, mode)?,
+        Some(CliCommand::LedLoad(load)) => handle_led_load(dbus, load)?,
+        Some(CliCommand::Aura(cmd)) => handle_aura(dbus, cmd)?,
         Some(CliCommand::LedPow1(pow)) => handle_led_power1(dbus, &supported.keyboard_led, pow)?,
         Some(CliCommand::LedPow2(pow)) => handle_led_power2(dbus, &supported.keyboard_led, pow)?,
         Some(CliCommand::Profile(cmd)) => handle_profile(dbus, &supported.platform_profile, cmd)?,
@@ -123,6 +127,7 @@ fn do_parsed(
             if (!parsed.show_supported
                 && parsed.kbd_bright.is_none()
                 && parsed.chg_limit.is_none()
+                && !parsed.one_shot_chg
                 && !parsed.next_kbd_bright
                 && !parsed.prev_kbd_bright)
                 || parsed.help
@@ -187,6 +192,10 @@ fn do_parsed(
             .set_charge_control_end_threshold(chg_limit)?;
     }
 
+    if parsed.one_shot_chg {
+        dbus.proxies().charge().charge_to_full_once()?;
+    }
+
     Ok(())
 }
 
@@ -453,6 +462,82 @@ fn handle_led_mode(
     Ok(())
 }
 
+fn handle_led_load(
+    dbus: &RogDbusClientBlocking<'_>,
+    load: &LedLoadCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match &load.file {
+        Some(path) if !load.help => path,
+        _ => {
+            println!("{}", load.self_usage());
+            return Ok(());
+        }
+    };
+    let data = std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+
+    // Validation of the effects is left to asusd
+    let effect = ron::from_str::<AuraEffect>(&data)
+        .or_else(|_| serde_json::from_str::<AuraEffect>(&data))
+        .ok();
+    if let Some(effect) = effect {
+        dbus.proxies().led().set_led_mode(&effect)?;
+        println!("Applied {} effect from {path}", <&str>::from(&effect.mode));
+        return Ok(());
+    }
+
+    // A full config is handed to asusd in one go so the keyboard is written
+    // once, power states included. Accepts `export_config` JSON or aura.ron.
+    let json = if serde_json::from_str::<serde_json::Value>(&data).is_ok() {
+        data
+    } else {
+        let config = ron::from_str::<AuraConfig>(&data)
+            .map_err(|e| format!("{path} is not an Aura effect or an Aura config: {e}"))?;
+        serde_json::to_string(&config)?
+    };
+    dbus.proxies()
+        .led()
+        .import_config(&json)
+        .map_err(|e| format!("Could not apply the Aura config from {path}: {e}"))?;
+    println!("Applied Aura config from {path}");
+    Ok(())
+}
+
+fn handle_aura(
+    dbus: &RogDbusClientBlocking<'_>,
+    cmd: &AuraCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match &cmd.command {
+        Some(AuraSubCommand::Safe(safe)) => handle_led_safe(dbus, safe),
+        None => {
+            println!("Missing command\n\n{}", cmd.self_usage());
+            if let Some(lst) = cmd.self_command_list() {
+                println!("\n{}", lst);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn handle_led_safe(
+    dbus: &RogDbusClientBlocking<'_>,
+    safe: &LedSafeCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if safe.help {
+        println!("{}", safe.self_usage());
+        return Ok(());
+    }
+    dbus.proxies()
+        .led()
+        .safe_state(safe.save)
+        .map_err(|e| format!("Could not reset the keyboard lighting: {e}"))?;
+    if safe.save {
+        println!("Keyboard lighting reset to white and saved");
+    } else {
+        println!("Keyboard lighting reset to white, the saved config is restored on reboot");
+    }
+    Ok(())
+}
+
 fn handle_led_power1(
     dbus: &RogDbusClientBlocking<'_>,
     supported: &LedSupportedFunctions,
@@ -643,7 +728,8 @@ fn handle_profile(
     }
 
     if cmd.next {
-        dbus.proxies().profile().next_profile()?;
+        let profile = dbus.proxies().profile().next_profile()?;
+        println!("Profile set to {profile}");
     } else if let Some(profile) = cmd.profile_set {
         dbus.proxies().profile().set_active_profile(profile)?;
     }
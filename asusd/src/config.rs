@@ -1,4 +1,5 @@
 use config_traits::{StdConfig, StdConfigLoad2};
+use rog_platform::platform::MiniLedLevel;
 use serde_derive::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "asusd.ron";
@@ -20,6 +21,59 @@ pub struct Config {
     pub ppt_platform_sppt: Option<u8>,
     pub nv_dynamic_boost: Option<u8>,
     pub nv_temp_target: Option<u8>,
+    /// Delay in milliseconds before applying the stored state on boot, for
+    /// systems where the kernel modules need time to settle
+    #[serde(default)]
+    pub startup_apply_delay_ms: u64,
+    /// Time in milliseconds the AC or lid state must be stable before actions
+    /// are taken on it, to avoid thrashing on a flaky charger connection
+    #[serde(default = "default_event_debounce_ms")]
+    pub event_debounce_ms: u64,
+    /// Turn panel overdrive off while on battery. `panel_od` is kept as the
+    /// preferred setting and restored on AC.
+    #[serde(default)]
+    pub panel_od_off_on_battery: bool,
+    /// Seconds `ac_command` or `bat_command` may run before it is killed
+    #[serde(default = "default_command_timeout_secs")]
+    pub command_timeout_secs: u64,
+    /// The mini-LED level set with `set_mini_led_level`, restored on boot.
+    /// `None` if only the on/off `mini_led_mode` has been used.
+    #[serde(default)]
+    pub mini_led_level: Option<MiniLedLevel>,
+}
+
+impl Config {
+    /// Reset the settings owned by the platform controller to their defaults.
+    /// The charge limit, AC/battery commands and daemon timing settings are
+    /// kept.
+    pub fn reset_platform(&mut self) {
+        let defaults = Config::new();
+        self.panel_od = defaults.panel_od;
+        self.panel_od_off_on_battery = defaults.panel_od_off_on_battery;
+        self.mini_led_mode = defaults.mini_led_mode;
+        self.mini_led_level = defaults.mini_led_level;
+        self.post_animation_sound = defaults.post_animation_sound;
+        self.ppt_pl1_spl = defaults.ppt_pl1_spl;
+        self.ppt_pl2_sppt = defaults.ppt_pl2_sppt;
+        self.ppt_fppt = defaults.ppt_fppt;
+        self.ppt_apu_sppt = defaults.ppt_apu_sppt;
+        self.ppt_platform_sppt = defaults.ppt_platform_sppt;
+        self.nv_dynamic_boost = defaults.nv_dynamic_boost;
+        self.nv_temp_target = defaults.nv_temp_target;
+    }
+
+    /// The `panel_od` state to use for the power source
+    pub fn panel_od_for_power(&self, power_plugged: bool) -> bool {
+        self.panel_od && (power_plugged || !self.panel_od_off_on_battery)
+    }
+}
+
+fn default_event_debounce_ms() -> u64 {
+    1000
+}
+
+fn default_command_timeout_secs() -> u64 {
+    10
 }
 
 impl StdConfig for Config {
@@ -29,6 +83,8 @@ impl StdConfig for Config {
             disable_nvidia_powerd_on_battery: true,
             ac_command: String::new(),
             bat_command: String::new(),
+            event_debounce_ms: default_event_debounce_ms(),
+            command_timeout_secs: default_command_timeout_secs(),
             ..Default::default()
         }
     }
@@ -42,7 +98,7 @@ impl StdConfig for Config {
     }
 }
 
-impl StdConfigLoad2<Config462, Config472> for Config {}
+impl StdConfigLoad2<Config472, Config462> for Config {}
 
 #[derive(Deserialize, Serialize, Default, Debug)]
 pub struct Config472 {
@@ -61,9 +117,13 @@ impl From<Config472> for Config {
         Self {
             bat_charge_limit: c.bat_charge_limit,
             panel_od: c.panel_od,
-            disable_nvidia_powerd_on_battery: true,
+            mini_led_mode: c.mini_led_mode,
+            disable_nvidia_powerd_on_battery: c.disable_nvidia_powerd_on_battery,
             ac_command: c.ac_command,
             bat_command: c.bat_command,
+            post_animation_sound: c.post_animation_sound,
+            event_debounce_ms: default_event_debounce_ms(),
+            command_timeout_secs: default_command_timeout_secs(),
             ..Default::default()
         }
     }
@@ -84,10 +144,126 @@ impl From<Config462> for Config {
         Self {
             bat_charge_limit: c.bat_charge_limit,
             panel_od: c.panel_od,
-            disable_nvidia_powerd_on_battery: true,
-            ac_command: String::new(),
-            bat_command: String::new(),
+            disable_nvidia_powerd_on_battery: c.disable_nvidia_powerd_on_battery,
+            ac_command: c.ac_command,
+            bat_command: c.bat_command,
+            event_debounce_ms: default_event_debounce_ms(),
+            command_timeout_secs: default_command_timeout_secs(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config_traits::{StdConfig, StdConfigLoad2};
+
+    use super::Config;
+
+    /// Parse `buf` the same way as `load`
+    fn migrate(buf: &str) -> Config {
+        Config::new().parse_or_new(buf)
+    }
+
+    #[test]
+    fn panel_od_off_on_battery() {
+        let mut config = Config {
+            panel_od: true,
+            ..Default::default()
+        };
+        assert!(config.panel_od_for_power(true));
+        assert!(config.panel_od_for_power(false));
+
+        config.panel_od_off_on_battery = true;
+        assert!(config.panel_od_for_power(true));
+        assert!(!config.panel_od_for_power(false));
+
+        config.panel_od = false;
+        assert!(!config.panel_od_for_power(true));
+    }
+
+    #[test]
+    fn reset_platform_keeps_other_settings() {
+        let mut config = Config {
+            bat_charge_limit: 60,
+            ac_command: "ac".to_owned(),
+            panel_od: true,
+            panel_od_off_on_battery: true,
+            mini_led_mode: true,
+            post_animation_sound: true,
+            ppt_pl1_spl: Some(40),
+            nv_temp_target: Some(80),
+            command_timeout_secs: 3,
             ..Default::default()
+        };
+        config.reset_platform();
+
+        assert!(!config.panel_od);
+        assert!(!config.panel_od_off_on_battery);
+        assert!(!config.mini_led_mode);
+        assert!(!config.post_animation_sound);
+        assert_eq!(config.ppt_pl1_spl, None);
+        assert_eq!(config.nv_temp_target, None);
+
+        assert_eq!(config.bat_charge_limit, 60);
+        assert_eq!(config.ac_command, "ac");
+        assert_eq!(config.command_timeout_secs, 3);
+    }
+
+    #[test]
+    fn migrate_historical_configs() {
+        let v462 = r#"(
+            bat_charge_limit: 80,
+            panel_od: true,
+            disable_nvidia_powerd_on_battery: false,
+            ac_command: "ac.sh",
+            bat_command: "bat.sh",
+        )"#;
+        let v472 = r#"(
+            bat_charge_limit: 80,
+            panel_od: true,
+            mini_led_mode: true,
+            disable_nvidia_powerd_on_battery: false,
+            ac_command: "ac.sh",
+            bat_command: "bat.sh",
+            post_animation_sound: true,
+        )"#;
+        // Written before the fields with serde defaults were added
+        let v480 = r#"(
+            bat_charge_limit: 80,
+            panel_od: true,
+            mini_led_mode: true,
+            disable_nvidia_powerd_on_battery: false,
+            ac_command: "ac.sh",
+            bat_command: "bat.sh",
+            post_animation_sound: true,
+            ppt_pl1_spl: Some(45),
+            ppt_pl2_sppt: None,
+            ppt_fppt: None,
+            ppt_apu_sppt: None,
+            ppt_platform_sppt: None,
+            nv_dynamic_boost: Some(15),
+            nv_temp_target: None,
+        )"#;
+
+        for buf in [v462, v472, v480] {
+            let config = migrate(buf);
+            assert_eq!(config.bat_charge_limit, 80);
+            assert_eq!(config.ac_command, "ac.sh");
+            assert_eq!(config.bat_command, "bat.sh");
+            assert!(config.panel_od);
+            assert!(!config.disable_nvidia_powerd_on_battery);
+            assert_eq!(config.event_debounce_ms, 1000);
+            assert_eq!(config.command_timeout_secs, 10);
         }
+
+        let config = migrate(v472);
+        assert!(config.mini_led_mode);
+        assert!(config.post_animation_sound);
+
+        let config = migrate(v480);
+        assert_eq!(config.ppt_pl1_spl, Some(45));
+        assert_eq!(config.nv_dynamic_boost, Some(15));
+        assert_eq!(config.ppt_platform_sppt, None);
     }
 }
@@ -6,6 +6,7 @@ use rog_anime::usb::Brightness;
 use rog_anime::{
     ActionData, ActionLoader, AnimTime, Animations, AnimeType, DeviceState, Fade, Vec2,
 };
+use rog_aura::LedBrightness;
 use serde_derive::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "anime.ron";
@@ -122,6 +123,12 @@ pub struct AnimeConfig {
     pub off_when_lid_closed: bool,
     pub brightness_on_battery: Brightness,
     pub builtin_anims: Animations,
+    /// Scale the display brightness with the keyboard brightness
+    #[serde(default)]
+    pub anime_follows_kbd_brightness: bool,
+    /// Rotate everything written to the display by 180 degrees
+    #[serde(default)]
+    pub anime_flip: bool,
 }
 
 impl Default for AnimeConfig {
@@ -141,6 +148,8 @@ impl Default for AnimeConfig {
             off_when_lid_closed: true,
             brightness_on_battery: Brightness::Low,
             builtin_anims: Animations::default(),
+            anime_follows_kbd_brightness: false,
+            anime_flip: false,
         }
     }
 }
@@ -177,6 +186,50 @@ impl From<&AnimeConfig> for DeviceState {
 }
 
 impl AnimeConfig {
+    /// The brightness to apply on reload or when the power source changes.
+    /// `display_brightness` is the user setting and is used while on AC.
+    pub fn brightness_for_power(&self, power_plugged: bool) -> Brightness {
+        if power_plugged {
+            self.display_brightness
+        } else {
+            self.brightness_on_battery
+        }
+    }
+
+    /// The brightness for the power source, scaled by the keyboard brightness
+    /// if `anime_follows_kbd_brightness` is set. The keyboard at high gives the
+    /// unscaled brightness, and the display is only off if the keyboard is.
+    pub fn brightness_for_kbd(&self, power_plugged: bool, kbd: LedBrightness) -> Brightness {
+        self.scale_for_kbd(self.brightness_for_power(power_plugged), kbd)
+    }
+
+    /// Scale `bright` by the keyboard brightness if
+    /// `anime_follows_kbd_brightness` is set, otherwise it is returned as is
+    pub fn scale_for_kbd(&self, bright: Brightness, kbd: LedBrightness) -> Brightness {
+        if !self.anime_follows_kbd_brightness {
+            return bright;
+        }
+        match (bright as u32 * kbd as u32).div_ceil(3) {
+            0 => Brightness::Off,
+            1 => Brightness::Low,
+            2 => Brightness::Med,
+            _ => Brightness::High,
+        }
+    }
+
+    /// If the display should be on for the power source. The display is turned
+    /// off on battery if `off_when_unplugged` is set, otherwise it follows the
+    /// user setting in `display_enabled`.
+    pub fn display_on_for_power(&self, power_plugged: bool) -> bool {
+        self.display_enabled && (power_plugged || !self.off_when_unplugged)
+    }
+
+    /// As `display_on_for_power`, and also off while the lid is closed if
+    /// `off_when_lid_closed` is set
+    pub fn display_on_for_state(&self, power_plugged: bool, lid_closed: bool) -> bool {
+        self.display_on_for_power(power_plugged) && !(lid_closed && self.off_when_lid_closed)
+    }
+
     // fn clamp_config_brightness(mut config: &mut AnimeConfig) {
     //     if config.brightness < 0.0 || config.brightness > 1.0 {
     //         warn!(
@@ -227,3 +280,97 @@ impl AnimeConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rog_anime::usb::Brightness;
+    use rog_aura::LedBrightness;
+
+    use super::AnimeConfig;
+
+    #[test]
+    fn display_off_on_battery() {
+        let mut config = AnimeConfig {
+            display_enabled: true,
+            off_when_unplugged: false,
+            ..Default::default()
+        };
+        // No-op when not enabled
+        assert!(config.display_on_for_power(true));
+        assert!(config.display_on_for_power(false));
+
+        config.off_when_unplugged = true;
+        assert!(config.display_on_for_power(true));
+        assert!(!config.display_on_for_power(false));
+
+        // A display the user turned off stays off when AC returns
+        config.display_enabled = false;
+        assert!(!config.display_on_for_power(true));
+        assert!(!config.display_on_for_power(false));
+    }
+
+    #[test]
+    fn display_off_with_lid_closed() {
+        let mut config = AnimeConfig {
+            display_enabled: true,
+            off_when_unplugged: false,
+            off_when_lid_closed: true,
+            ..Default::default()
+        };
+        // On battery without `off_when_unplugged` the display stays on
+        assert!(config.display_on_for_state(false, false));
+        assert!(config.display_on_for_state(true, false));
+        assert!(!config.display_on_for_state(true, true));
+
+        config.off_when_lid_closed = false;
+        assert!(config.display_on_for_state(true, true));
+
+        config.display_enabled = false;
+        assert!(!config.display_on_for_state(true, false));
+    }
+
+    #[test]
+    fn brightness_follows_kbd() {
+        let mut config = AnimeConfig {
+            display_brightness: Brightness::High,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::Low),
+            Brightness::High
+        );
+
+        config.anime_follows_kbd_brightness = true;
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::High),
+            Brightness::High
+        );
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::Low),
+            Brightness::Low
+        );
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::Off),
+            Brightness::Off
+        );
+
+        config.display_brightness = Brightness::Med;
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::High),
+            Brightness::Med
+        );
+        assert_eq!(
+            config.brightness_for_kbd(true, LedBrightness::Low),
+            Brightness::Low
+        );
+        // Battery brightness is scaled instead of the user setting
+        assert_eq!(
+            config.brightness_for_kbd(false, LedBrightness::Med),
+            Brightness::Low
+        );
+        assert_eq!(
+            config.scale_for_kbd(Brightness::High, LedBrightness::Low),
+            Brightness::Low
+        );
+    }
+}
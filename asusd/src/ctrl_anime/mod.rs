@@ -6,33 +6,48 @@ use std::convert::TryFrom;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 use ::zbus::export::futures_util::lock::Mutex;
+use config_traits::StdConfig;
 use log::{error, info, warn};
 use rog_anime::error::AnimeError;
 use rog_anime::usb::{
-    get_anime_type, pkt_flush, pkt_set_brightness, pkt_set_enable_display,
-    pkt_set_enable_powersave_anim, pkts_for_init, Brightness,
+    get_anime_type, pkt_flush, pkt_set_brightness, pkt_set_builtin_animations,
+    pkt_set_enable_display, pkt_set_enable_powersave_anim, pkts_for_init, Brightness,
 };
 use rog_anime::{ActionData, AnimeDataBuffer, AnimePacketType, AnimeType};
+use rog_aura::LedBrightness;
 use rog_platform::hid_raw::HidRaw;
-use rog_platform::supported::AnimeSupportedFunctions;
+use rog_platform::supported::{AnimeSupportedFunctions, UnsupportedReason};
 use rog_platform::usb_raw::USBRaw;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 use self::config::{AnimeConfig, AnimeConfigCached};
 use crate::error::RogError;
-use crate::GetSupported;
+use crate::{Devices, GetSupported};
 
 impl GetSupported for CtrlAnime {
     type A = AnimeSupportedFunctions;
 
-    fn get_supported() -> Self::A {
-        if USBRaw::new(0x193b).is_ok() {
+    fn get_supported(devices: &Devices) -> Self::A {
+        if devices.usb(0x193b).is_ok() {
             AnimeSupportedFunctions(true)
         } else {
-            AnimeSupportedFunctions(HidRaw::new("193b").is_ok())
+            AnimeSupportedFunctions(devices.hidraw("193b").is_ok())
         }
     }
+
+    fn unsupported_reasons(supported: &Self::A, _devices: &Devices) -> Vec<UnsupportedReason> {
+        if supported.0 {
+            return Vec::new();
+        }
+        vec![UnsupportedReason::new(
+            "anime_ctrl",
+            "No AniMe Matrix device (USB 0b05:193b) was found",
+        )]
+    }
 }
 
 enum Node {
@@ -54,6 +69,14 @@ impl Node {
         Ok(())
     }
 
+    /// The device node in use
+    pub fn path(&self) -> String {
+        match self {
+            Node::Usb(u) => u.dev_path().display().to_string(),
+            Node::Hid(h) => h.path().display().to_string(),
+        }
+    }
+
     pub fn set_builtins_enabled(&self, enabled: bool, bright: Brightness) -> Result<(), RogError> {
         self.write_bytes(&pkt_set_enable_powersave_anim(enabled))?;
         self.write_bytes(&pkt_set_enable_display(enabled))?;
@@ -62,6 +85,10 @@ impl Node {
     }
 }
 
+/// The shortest time between frames written with `write_frame`, frames that
+/// come in faster are dropped
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
 pub struct CtrlAnime {
     // node: HidRaw,
     node: Node,
@@ -72,13 +99,25 @@ pub struct CtrlAnime {
     thread_exit: Arc<AtomicBool>,
     // Set to false when the thread exits
     thread_running: Arc<AtomicBool>,
+    /// Keyboard brightness changes sent by the keyboard controller
+    kbd_brightness: watch::Receiver<LedBrightness>,
+    power_plugged: bool,
+    /// When the last streamed frame was written
+    last_frame: Option<Instant>,
+    /// The running `upload_boot_animation` task, aborted when another upload
+    /// is started
+    upload_task: Option<JoinHandle<()>>,
 }
 
 impl CtrlAnime {
     #[inline]
-    pub fn new(config: AnimeConfig) -> Result<CtrlAnime, RogError> {
-        let usb = USBRaw::new(0x193b).ok();
-        let hid = HidRaw::new("193b").ok();
+    pub fn new(
+        config: AnimeConfig,
+        kbd_brightness: watch::Receiver<LedBrightness>,
+        devices: &Devices,
+    ) -> Result<CtrlAnime, RogError> {
+        let usb = devices.usb(0x193b).ok();
+        let hid = devices.hidraw("193b").ok();
         let node = if usb.is_some() {
             unsafe { Node::Usb(usb.unwrap_unchecked()) }
         } else if hid.is_some() {
@@ -106,6 +145,10 @@ impl CtrlAnime {
             config,
             thread_exit: Arc::new(AtomicBool::new(false)),
             thread_running: Arc::new(AtomicBool::new(false)),
+            kbd_brightness,
+            power_plugged: true,
+            last_frame: None,
+            upload_task: None,
         };
         ctrl.do_initialization()?;
 
@@ -114,6 +157,104 @@ impl CtrlAnime {
 
     // let device = CtrlAnime::get_device(0x0b05, 0x193b)?;
 
+    /// The device node of the display
+    pub fn device_path(&self) -> String {
+        self.node.path()
+    }
+
+    /// The brightness to write for the power source, scaled by the keyboard
+    /// brightness if the display follows it
+    pub(super) fn applied_brightness(&self) -> Brightness {
+        self.config
+            .brightness_for_kbd(self.power_plugged, *self.kbd_brightness.borrow())
+    }
+
+    /// Set the global brightness of the display, `0` (off) to `3` (high).
+    /// Values out of range are clamped. The brightness is stored in the config
+    /// as the user setting, and is scaled by the keyboard brightness when
+    /// written if the display follows it.
+    pub fn set_brightness(&mut self, level: u8) -> Result<Brightness, RogError> {
+        let bright = match level.min(3) {
+            0 => Brightness::Off,
+            1 => Brightness::Low,
+            2 => Brightness::Med,
+            _ => Brightness::High,
+        };
+        let scaled = self
+            .config
+            .scale_for_kbd(bright, *self.kbd_brightness.borrow());
+        self.node.write_bytes(&pkt_set_brightness(scaled))?;
+        self.node
+            .write_bytes(&pkt_set_enable_display(bright != Brightness::Off))?;
+
+        self.config.display_enabled = bright != Brightness::Off;
+        self.config.display_brightness = bright;
+        self.config.write();
+        Ok(bright)
+    }
+
+    /// Write the stored brightness to the display. The brightness is not
+    /// retained by the device across boots so this must be done on reload.
+    fn reapply_brightness(&self, power_plugged: bool) -> Result<(), RogError> {
+        let bright = self
+            .config
+            .brightness_for_kbd(power_plugged, *self.kbd_brightness.borrow());
+        info!("AniMe: applying stored brightness {bright:?}");
+        self.node.write_bytes(&pkt_set_brightness(bright))
+    }
+
+    /// Turn the display off if on battery and `off_when_unplugged` is set, or
+    /// the lid is closed and `off_when_lid_closed` is set. Otherwise restore
+    /// the user's display state and brightness.
+    fn apply_power_state(&self, power_plugged: bool, lid_closed: bool) -> Result<(), RogError> {
+        let on = self.config.display_on_for_state(power_plugged, lid_closed);
+        self.node.write_bytes(&pkt_set_enable_display(on))?;
+        if on {
+            self.reapply_brightness(power_plugged)?;
+        }
+        Ok(())
+    }
+
+    /// Write the builtin animations and the display state and brightness for
+    /// the power source and lid, as done on boot. Returns if the display is
+    /// on.
+    fn apply_stored_state(
+        &mut self,
+        power_plugged: bool,
+        lid_closed: bool,
+    ) -> Result<bool, RogError> {
+        self.power_plugged = power_plugged;
+        let anim = &self.config.builtin_anims;
+        if self.config.builtin_anims_enabled {
+            self.node.write_bytes(&pkt_set_builtin_animations(
+                anim.boot,
+                anim.awake,
+                anim.sleep,
+                anim.shutdown,
+            ))?;
+        }
+        self.node
+            .set_builtins_enabled(self.config.builtin_anims_enabled, self.applied_brightness())?;
+
+        self.apply_power_state(power_plugged, lid_closed)
+            .map_err(|err| {
+                warn!("create_sys_event_tasks::reload {}", err);
+            })
+            .ok();
+        Ok(self.config.display_on_for_state(power_plugged, lid_closed))
+    }
+
+    /// Scale the display brightness to a keyboard brightness change. This only
+    /// writes to the display, nothing is sent back to the keyboard.
+    fn follow_kbd_brightness(&self) -> Result<(), RogError> {
+        if self.config.anime_follows_kbd_brightness
+            && self.config.display_on_for_power(self.power_plugged)
+        {
+            self.reapply_brightness(self.power_plugged)?;
+        }
+        Ok(())
+    }
+
     /// Start an action thread. This is classed as a singleton and there should
     /// be only one running - so the thread uses atomics to signal run/exit.
     ///
@@ -261,9 +402,25 @@ impl CtrlAnime {
             .ok();
     }
 
+    /// Write a frame streamed from a client. Returns `false` if the frame was
+    /// dropped because it came in sooner than `FRAME_INTERVAL` after the last.
+    fn write_frame(&mut self, data: Vec<u8>) -> Result<bool, RogError> {
+        if !frame_due(self.last_frame, Instant::now()) {
+            return Ok(false);
+        }
+        let buffer = AnimeDataBuffer::from_vec(self.anime_type, data)?;
+        self.thread_exit.store(true, Ordering::SeqCst);
+        self.write_data_buffer(buffer)?;
+        self.last_frame = Some(Instant::now());
+        Ok(true)
+    }
+
     /// Write only a data packet. This will modify the leds brightness using the
-    /// global brightness set in config.
+    /// global brightness set in config, and rotate it if `anime_flip` is set.
     fn write_data_buffer(&self, mut buffer: AnimeDataBuffer) -> Result<(), RogError> {
+        if self.config.anime_flip {
+            buffer.rotate_180();
+        }
         for led in buffer.data_mut().iter_mut() {
             let mut bright = *led as f32;
             if bright > 254.0 {
@@ -286,3 +443,113 @@ impl CtrlAnime {
         Ok(())
     }
 }
+
+/// Check every frame of an upload is `frame_len` long before any are written
+fn check_frames(frames: &[Vec<u8>], frame_len: usize) -> Result<(), String> {
+    if let Some((i, frame)) = frames
+        .iter()
+        .enumerate()
+        .find(|(_, frame)| frame.len() != frame_len)
+    {
+        return Err(format!(
+            "frame {i} is {} bytes, {frame_len} expected",
+            frame.len()
+        ));
+    }
+    Ok(())
+}
+
+/// If enough time has passed since the last frame to write another
+fn frame_due(last_frame: Option<Instant>, now: Instant) -> bool {
+    match last_frame {
+        Some(last) => now.duration_since(last) >= FRAME_INTERVAL,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use rog_anime::usb::{pkt_set_brightness, pkt_set_enable_display, Brightness};
+    use rog_anime::AnimeType;
+    use rog_aura::LedBrightness;
+    use tokio::sync::watch;
+
+    use super::config::{AnimeConfig, AnimeConfigCached};
+    use super::{check_frames, frame_due, CtrlAnime, Node, FRAME_INTERVAL};
+    use crate::Devices;
+
+    #[test]
+    fn brightness_reapplied_after_reload() {
+        let node = Devices::simulated().hidraw("193b").unwrap();
+        let sim = node.simulation().unwrap().clone();
+        let (_kbd_tx, kbd_brightness) = watch::channel(LedBrightness::High);
+        let mut ctrl = CtrlAnime {
+            node: Node::Hid(node),
+            anime_type: AnimeType::GA402,
+            cache: AnimeConfigCached::default(),
+            config: AnimeConfig {
+                display_enabled: true,
+                display_brightness: Brightness::High,
+                brightness_on_battery: Brightness::Low,
+                off_when_unplugged: false,
+                ..Default::default()
+            },
+            thread_exit: Arc::new(AtomicBool::new(false)),
+            thread_running: Arc::new(AtomicBool::new(false)),
+            kbd_brightness,
+            power_plugged: true,
+            last_frame: None,
+            upload_task: None,
+        };
+
+        // On battery the brightness for battery is the last written
+        assert!(ctrl.apply_stored_state(false, false).unwrap());
+        let writes = sim.written_bytes();
+        assert_eq!(
+            writes.last().unwrap(),
+            &pkt_set_brightness(Brightness::Low).to_vec()
+        );
+
+        assert!(ctrl.apply_stored_state(true, false).unwrap());
+        let writes = sim.written_bytes();
+        assert_eq!(
+            writes.last().unwrap(),
+            &pkt_set_brightness(Brightness::High).to_vec()
+        );
+
+        // The display is turned off and the brightness not written
+        ctrl.config.off_when_unplugged = true;
+        assert!(!ctrl.apply_stored_state(false, false).unwrap());
+        let writes = sim.written_bytes();
+        assert_eq!(
+            writes.last().unwrap(),
+            &pkt_set_enable_display(false).to_vec()
+        );
+    }
+
+    #[test]
+    fn frames_rate_limited() {
+        let now = Instant::now();
+        assert!(frame_due(None, now));
+        assert!(!frame_due(Some(now), now));
+        assert!(!frame_due(
+            Some(now),
+            now + FRAME_INTERVAL - Duration::from_millis(1)
+        ));
+        assert!(frame_due(Some(now), now + FRAME_INTERVAL));
+    }
+
+    #[test]
+    fn upload_frames_checked() {
+        assert!(check_frames(&[], 4).is_ok());
+        assert!(check_frames(&[vec![0; 4], vec![1; 4]], 4).is_ok());
+        assert_eq!(
+            check_frames(&[vec![0; 4], vec![1; 3], vec![1; 5]], 4),
+            Err("frame 1 is 3 bytes, 4 expected".to_owned())
+        );
+    }
+}
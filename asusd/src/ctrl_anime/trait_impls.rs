@@ -3,17 +3,18 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
-use log::warn;
+use log::{info, warn};
 use logind_zbus::manager::ManagerProxy;
 use rog_anime::usb::{
-    pkt_set_brightness, pkt_set_builtin_animations, pkt_set_enable_display,
-    pkt_set_enable_powersave_anim, AnimAwake, AnimBooting, AnimShutdown, AnimSleeping, Brightness,
+    pkt_set_builtin_animations, pkt_set_enable_display, pkt_set_enable_powersave_anim, AnimAwake,
+    AnimBooting, AnimShutdown, AnimSleeping, Brightness,
 };
 use rog_anime::{AnimeDataBuffer, DeviceState};
+use tokio::time::sleep;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::{dbus_interface, CacheProperties, Connection, SignalContext};
 
-use super::CtrlAnime;
+use super::{check_frames, CtrlAnime, FRAME_INTERVAL};
 use crate::error::RogError;
 
 pub(super) const ZBUS_PATH: &str = "/org/asuslinux/Anime";
@@ -58,6 +59,97 @@ impl CtrlAnimeZbus {
         Ok(())
     }
 
+    /// Write a frame to the display straight away. `data` is the brightness of
+    /// each LED in display order and must be `frame_length` long. Frames sent
+    /// faster than the display can refresh, or while the last is still being
+    /// written, are dropped and `false` is returned.
+    async fn write_frame(&self, data: Vec<u8>) -> zbus::fdo::Result<bool> {
+        let mut lock = match self.0.try_lock() {
+            Some(lock) => lock,
+            None => return Ok(false),
+        };
+        let expected = lock.anime_type.data_length();
+        if data.len() != expected {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "frame is {} bytes, {expected} expected",
+                data.len()
+            )));
+        }
+        lock.write_frame(data).map_err(|err| {
+            warn!("ctrl_anime::write_frame {}", err);
+            err.into()
+        })
+    }
+
+    /// Write a boot animation to the display one frame at a time in the
+    /// background. Each frame must be `frame_length` long, all are checked
+    /// before any are written. Progress is sent with `notify_upload_progress`.
+    /// Starting an upload cancels any upload still running, so an empty
+    /// `frames` only cancels.
+    async fn upload_boot_animation(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        frames: Vec<Vec<u8>>,
+    ) -> zbus::fdo::Result<()> {
+        let mut lock = self.0.lock().await;
+        check_frames(&frames, lock.anime_type.data_length()).map_err(|err| {
+            warn!("ctrl_anime::upload_boot_animation {err}");
+            zbus::fdo::Error::InvalidArgs(err)
+        })?;
+        if let Some(task) = lock.upload_task.take() {
+            info!("AniMe: cancelling previous animation upload");
+            task.abort();
+        }
+        if frames.is_empty() {
+            return Ok(());
+        }
+        lock.thread_exit.store(true, Ordering::SeqCst);
+
+        let inner = self.0.clone();
+        let ctxt = ctxt.to_owned();
+        lock.upload_task = Some(tokio::spawn(async move {
+            let total = frames.len() as u32;
+            for (i, frame) in frames.into_iter().enumerate() {
+                {
+                    let lock = inner.lock().await;
+                    match AnimeDataBuffer::from_vec(lock.anime_type, frame) {
+                        Ok(buffer) => {
+                            if let Err(err) = lock.write_data_buffer(buffer) {
+                                warn!("ctrl_anime::upload_boot_animation {err}");
+                                return;
+                            }
+                        }
+                        Err(err) => {
+                            warn!("ctrl_anime::upload_boot_animation {err}");
+                            return;
+                        }
+                    }
+                }
+                Self::notify_upload_progress(&ctxt, i as u32 + 1, total)
+                    .await
+                    .ok();
+                sleep(FRAME_INTERVAL).await;
+            }
+            inner.lock().await.upload_task = None;
+        }));
+        Ok(())
+    }
+
+    /// The length of a frame for `write_frame`
+    async fn frame_length(&self) -> u32 {
+        let lock = self.0.lock().await;
+        lock.anime_type.data_length() as u32
+    }
+
+    /// The width and height of the display as used for diagonal images
+    async fn matrix_dimensions(&self) -> (u32, u32) {
+        let lock = self.0.lock().await;
+        (
+            lock.anime_type.width() as u32,
+            lock.anime_type.height() as u32,
+        )
+    }
+
     /// Set base brightness level
     async fn set_brightness(
         &self,
@@ -65,26 +157,34 @@ impl CtrlAnimeZbus {
         brightness: Brightness,
     ) {
         let mut lock = self.0.lock().await;
-        lock.node
-            .write_bytes(&pkt_set_brightness(brightness))
+        lock.set_brightness(brightness as u8)
             .map_err(|err| {
                 warn!("ctrl_anime::set_brightness {}", err);
             })
             .ok();
-        lock.node
-            .write_bytes(&pkt_set_enable_display(brightness != Brightness::Off))
-            .map_err(|err| {
-                warn!("ctrl_anime::set_brightness {}", err);
-            })
+
+        Self::notify_device_state(&ctxt, DeviceState::from(&lock.config))
+            .await
             .ok();
+    }
 
-        lock.config.display_enabled = brightness != Brightness::Off;
-        lock.config.display_brightness = brightness;
-        lock.config.write();
+    /// Set base brightness level as a number from 0 (off) to 3 (high). Values
+    /// out of range are clamped.
+    async fn set_brightness_level(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        level: u8,
+    ) -> zbus::fdo::Result<()> {
+        let mut lock = self.0.lock().await;
+        lock.set_brightness(level).map_err(|err| {
+            warn!("ctrl_anime::set_brightness_level {}", err);
+            err
+        })?;
 
         Self::notify_device_state(&ctxt, DeviceState::from(&lock.config))
             .await
             .ok();
+        Ok(())
     }
 
     /// Enable the builtin animations or not. This is quivalent to "Powersave
@@ -96,7 +196,7 @@ impl CtrlAnimeZbus {
     ) {
         let mut lock = self.0.lock().await;
         lock.node
-            .set_builtins_enabled(enabled, lock.config.display_brightness)
+            .set_builtins_enabled(enabled, lock.applied_brightness())
             .map_err(|err| {
                 warn!("ctrl_anime::set_builtins_enabled {}", err);
             })
@@ -191,16 +291,15 @@ impl CtrlAnimeZbus {
         let mut lock = self.0.lock().await;
         let manager = get_logind_manager().await;
         let pow = manager.on_external_power().await.unwrap_or_default();
+        let lid = manager.lid_closed().await.unwrap_or_default();
 
-        lock.node
-            .write_bytes(&pkt_set_enable_display(!pow && !enabled))
+        lock.config.off_when_unplugged = enabled;
+        lock.config.write();
+        lock.apply_power_state(pow, lid)
             .map_err(|err| {
-                warn!("create_sys_event_tasks::off_when_lid_closed {}", err);
+                warn!("ctrl_anime::set_off_when_unplugged {}", err);
             })
             .ok();
-
-        lock.config.off_when_unplugged = enabled;
-        lock.config.write();
         Self::notify_device_state(&ctxt, DeviceState::from(&lock.config))
             .await
             .ok();
@@ -244,6 +343,48 @@ impl CtrlAnimeZbus {
             .ok();
     }
 
+    /// Set if the display brightness is scaled with the keyboard brightness.
+    /// Disabling restores the display brightness setting.
+    async fn set_follows_kbd_brightness(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        let mut lock = self.0.lock().await;
+        lock.config.anime_follows_kbd_brightness = enabled;
+        lock.config.write();
+        if lock.config.display_on_for_power(lock.power_plugged) {
+            lock.reapply_brightness(lock.power_plugged).map_err(|err| {
+                warn!("ctrl_anime::set_follows_kbd_brightness {}", err);
+                err
+            })?;
+        }
+        Self::notify_device_state(&ctxt, DeviceState::from(&lock.config))
+            .await
+            .ok();
+        Ok(())
+    }
+
+    /// Get if the display brightness is scaled with the keyboard brightness
+    async fn follows_kbd_brightness(&self) -> bool {
+        let lock = self.0.lock().await;
+        lock.config.anime_follows_kbd_brightness
+    }
+
+    /// Rotate everything written to the display by 180 degrees, for when it is
+    /// seen upside down such as in tent mode
+    async fn set_flip(&self, flip: bool) {
+        let mut lock = self.0.lock().await;
+        lock.config.anime_flip = flip;
+        lock.config.write();
+    }
+
+    /// Get if the display is rotated by 180 degrees
+    async fn flip(&self) -> bool {
+        let lock = self.0.lock().await;
+        lock.config.anime_flip
+    }
+
     /// The main loop is the base system set action if the user isn't running
     /// the user daemon
     async fn run_main_loop(&self, start: bool) {
@@ -265,6 +406,14 @@ impl CtrlAnimeZbus {
     /// system-status animations
     #[dbus_interface(signal)]
     async fn notify_device_state(ctxt: &SignalContext<'_>, data: DeviceState) -> zbus::Result<()>;
+
+    /// Sent after each frame of `upload_boot_animation` is written
+    #[dbus_interface(signal)]
+    async fn notify_upload_progress(
+        ctxt: &SignalContext<'_>,
+        written: u32,
+        total: u32,
+    ) -> zbus::Result<()>;
 }
 
 #[async_trait]
@@ -337,27 +486,39 @@ impl crate::CtrlTask for CtrlAnimeZbus {
                 let inner = inner4.clone();
                 // on power change
                 async move {
-                    let lock = inner.lock().await;
-                    if lock.config.off_when_unplugged {
-                        lock.node
-                            .write_bytes(&pkt_set_enable_display(power_plugged))
-                            .map_err(|err| {
-                                warn!("create_sys_event_tasks::off_when_unplugged {}", err);
-                            })
-                            .ok();
-                    } else {
-                        lock.node
-                            .write_bytes(&pkt_set_brightness(lock.config.brightness_on_battery))
-                            .map_err(|err| {
-                                warn!("create_sys_event_tasks::off_when_unplugged {}", err);
-                            })
-                            .ok();
-                    }
+                    let lid_closed = get_logind_manager()
+                        .await
+                        .lid_closed()
+                        .await
+                        .unwrap_or_default();
+                    let mut lock = inner.lock().await;
+                    lock.power_plugged = power_plugged;
+                    // Changes made by the user while on battery are kept as
+                    // the display state is only read from the config here
+                    lock.apply_power_state(power_plugged, lid_closed)
+                        .map_err(|err| {
+                            warn!("create_sys_event_tasks::off_when_unplugged {}", err);
+                        })
+                        .ok();
                 }
             },
         )
         .await;
 
+        let inner = self.0.clone();
+        let mut kbd_brightness = self.0.lock().await.kbd_brightness.clone();
+        tokio::spawn(async move {
+            // Ends if there is no keyboard controller
+            while kbd_brightness.changed().await.is_ok() {
+                let lock = inner.lock().await;
+                lock.follow_kbd_brightness()
+                    .map_err(|err| {
+                        warn!("ctrl_anime::follow_kbd_brightness {}", err);
+                    })
+                    .ok();
+            }
+        });
+
         Ok(())
     }
 }
@@ -365,36 +526,12 @@ impl crate::CtrlTask for CtrlAnimeZbus {
 #[async_trait]
 impl crate::Reloadable for CtrlAnimeZbus {
     async fn reload(&mut self) -> Result<(), RogError> {
-        if let Some(lock) = self.0.try_lock() {
-            let anim = &lock.config.builtin_anims;
-            // Set builtins
-            if lock.config.builtin_anims_enabled {
-                lock.node.write_bytes(&pkt_set_builtin_animations(
-                    anim.boot,
-                    anim.awake,
-                    anim.sleep,
-                    anim.shutdown,
-                ))?;
-            }
-            // Builtins enabled or na?
-            lock.node.set_builtins_enabled(
-                lock.config.builtin_anims_enabled,
-                lock.config.display_brightness,
-            )?;
-
+        if let Some(mut lock) = self.0.try_lock() {
             let manager = get_logind_manager().await;
             let lid_closed = manager.lid_closed().await.unwrap_or_default();
             let power_plugged = manager.on_external_power().await.unwrap_or_default();
 
-            let on = (lid_closed && lock.config.off_when_lid_closed)
-                || (power_plugged && lock.config.off_when_unplugged);
-            lock.node
-                .write_bytes(&pkt_set_enable_display(on))
-                .map_err(|err| {
-                    warn!("create_sys_event_tasks::reload {}", err);
-                })
-                .ok();
-            if !on {
+            if !lock.apply_stored_state(power_plugged, lid_closed)? {
                 // early return so we don't run animation thread
                 return Ok(());
             }
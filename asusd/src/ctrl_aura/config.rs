@@ -2,11 +2,15 @@ use std::collections::{BTreeMap, HashSet};
 
 use config_traits::{StdConfig, StdConfigLoad};
 use log::{debug, warn};
-use rog_aura::aura_detection::{LaptopLedData, ASUS_KEYBOARD_DEVICES};
-use rog_aura::power::AuraPower;
+use rog_aura::aura_detection::{LaptopLedData, PowerZones, ASUS_KEYBOARD_DEVICES};
+use rog_aura::power::{AuraPower, KbAuraPowerState};
 use rog_aura::usb::{AuraDevRog1, AuraDevTuf, AuraDevice, AuraPowerDev};
-use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Direction, LedBrightness, Speed, GRADIENT};
+use rog_aura::{
+    AuraEffect, AuraModeNum, AuraZone, Colour, Direction, LedBrightness, Speed, GAMMA_NONE,
+    GRADIENT,
+};
 use rog_platform::hid_raw::HidRaw;
+use rog_profiles::Profile;
 use serde_derive::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "aura.ron";
@@ -18,10 +22,82 @@ const CONFIG_FILE: &str = "aura.ron";
 pub enum AuraPowerConfig {
     AuraDevTuf(HashSet<AuraDevTuf>),
     AuraDevRog1(HashSet<AuraDevRog1>),
-    AuraDevRog2(AuraPower),
+    AuraDevRog2(#[serde(deserialize_with = "deserialize_stored_power")] AuraPower),
+}
+
+/// A power state as stored by older configs, which had one state for both
+/// boot and shutdown. Only used for loading the config, `KbAuraPowerState` is
+/// what goes over dbus.
+#[derive(Deserialize)]
+struct StoredPowerState {
+    zone: PowerZones,
+    boot: bool,
+    awake: bool,
+    sleep: bool,
+    shutdown: Option<bool>,
+}
+
+impl From<StoredPowerState> for KbAuraPowerState {
+    fn from(s: StoredPowerState) -> Self {
+        Self {
+            zone: s.zone,
+            boot: s.boot,
+            awake: s.awake,
+            sleep: s.sleep,
+            shutdown: s.shutdown.unwrap_or(s.boot),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StoredAuraPower {
+    keyboard: StoredPowerState,
+    logo: StoredPowerState,
+    lightbar: StoredPowerState,
+    lid: Option<StoredPowerState>,
+    rear_glow: Option<StoredPowerState>,
+}
+
+/// Load `AuraPower` from the config, a missing `shutdown` state takes the
+/// value of `boot` and missing zones get their defaults
+fn deserialize_stored_power<'de, D>(deserializer: D) -> Result<AuraPower, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = <StoredAuraPower as serde::Deserialize>::deserialize(deserializer)?;
+    let defaults = AuraPower::new_all_on();
+    Ok(AuraPower {
+        keyboard: s.keyboard.into(),
+        logo: s.logo.into(),
+        lightbar: s.lightbar.into(),
+        lid: s.lid.map_or(defaults.lid, Into::into),
+        rear_glow: s.rear_glow.map_or(defaults.rear_glow, Into::into),
+    })
 }
 
 impl AuraPowerConfig {
+    /// Every zone enabled in every state, for the device type
+    pub fn all_on(prod_id: AuraDevice) -> Self {
+        if prod_id.is_new_style() {
+            AuraPowerConfig::AuraDevRog2(AuraPower::new_all_on())
+        } else if prod_id.is_tuf_style() {
+            AuraPowerConfig::AuraDevTuf(HashSet::from([
+                AuraDevTuf::Awake,
+                AuraDevTuf::Boot,
+                AuraDevTuf::Sleep,
+                AuraDevTuf::Keyboard,
+            ]))
+        } else {
+            AuraPowerConfig::AuraDevRog1(HashSet::from([
+                AuraDevRog1::Awake,
+                AuraDevRog1::Boot,
+                AuraDevRog1::Sleep,
+                AuraDevRog1::Keyboard,
+                AuraDevRog1::Lightbar,
+            ]))
+        }
+    }
+
     /// Invalid for TUF laptops
     pub fn to_bytes(control: &Self) -> [u8; 4] {
         match control {
@@ -104,6 +180,67 @@ impl From<&AuraPowerConfig> for AuraPowerDev {
     }
 }
 
+/// The order the mode and power states are written in when restoring on boot
+/// or resume. Some keyboards need the power states first or the animation
+/// does not stick.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreOrder {
+    #[default]
+    ModeFirst,
+    PowerFirst,
+}
+
+/// Temperature bounds for the software thermal effect. At or below `min_temp`
+/// the keyboard is blue, at or above `max_temp` it is red.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermalEffect {
+    pub min_temp: u8,
+    pub max_temp: u8,
+}
+
+impl Default for ThermalEffect {
+    fn default() -> Self {
+        Self {
+            min_temp: 40,
+            max_temp: 90,
+        }
+    }
+}
+
+impl ThermalEffect {
+    /// Map a temperature in degrees celsius to a colour on a blue to red
+    /// gradient
+    pub fn colour_for(&self, temp: u8) -> Colour {
+        let min = self.min_temp.min(self.max_temp);
+        let max = self.max_temp.max(self.min_temp);
+        let ratio = if max == min {
+            if temp >= max {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            (temp.clamp(min, max) - min) as f32 / (max - min) as f32
+        };
+        Colour {
+            r: (ratio * 255.0).round() as u8,
+            g: 0,
+            b: ((1.0 - ratio) * 255.0).round() as u8,
+        }
+    }
+}
+
+/// Keyboard brightness and mode to switch to when a platform profile becomes
+/// active
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileLighting {
+    pub brightness: LedBrightness,
+    pub mode: AuraModeNum,
+    /// Use the `multizone` effects stored for `mode`
+    #[serde(default)]
+    pub multizone_on: bool,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 // #[serde(default)]
 pub struct AuraConfig {
@@ -113,6 +250,59 @@ pub struct AuraConfig {
     pub multizone: Option<BTreeMap<AuraModeNum, Vec<AuraEffect>>>,
     pub multizone_on: bool,
     pub enabled: AuraPowerConfig,
+    #[serde(default)]
+    pub thermal_effect: ThermalEffect,
+    /// Change brightness and mode with the platform profile, using
+    /// `profile_lighting`. The power states in `enabled` still decide if the
+    /// LEDs are lit at all and take precedence over this.
+    #[serde(default)]
+    pub follows_profile: bool,
+    #[serde(default)]
+    pub profile_lighting: BTreeMap<Profile, ProfileLighting>,
+    /// The last brightness that was not `Off`, restored when the backlight is
+    /// toggled back on
+    #[serde(default)]
+    pub last_brightness: LedBrightness,
+    /// Dim the backlight to `dim_to` after this many seconds without a key
+    /// press, restoring it on the next. `0` disables this.
+    #[serde(default)]
+    pub backlight_timeout_secs: u64,
+    #[serde(default = "default_dim_to")]
+    pub dim_to: LedBrightness,
+    #[serde(default)]
+    pub restore_order: RestoreOrder,
+    /// Re-write the mode and power states this often, for keyboards whose
+    /// controller resets without notice. `0` disables this.
+    #[serde(default)]
+    pub led_keepalive_secs: u64,
+    /// Turn the backlight off when the lid is closed, restoring the stored
+    /// brightness and mode when it is opened
+    #[serde(default)]
+    pub leds_off_on_lid_close: bool,
+    /// Fade the brightness out and back in when switching mode, instead of a
+    /// hard cut
+    #[serde(default)]
+    pub mode_switch_fade: bool,
+    /// Brightness of single zones, such as the lightbar, below the keyboard
+    /// brightness. Zones not listed are at full brightness.
+    #[serde(default)]
+    pub zone_brightness: BTreeMap<AuraZone, LedBrightness>,
+    /// Per-channel `(r, g, b)` gamma applied to colours before they are
+    /// written, to correct the colour cast of some keyboards
+    #[serde(default = "default_colour_gamma")]
+    pub colour_gamma: (f32, f32, f32),
+    /// The settings of other ROG keyboards, such as an external one, by
+    /// product ID
+    #[serde(default)]
+    pub secondary: BTreeMap<String, AuraConfig>,
+}
+
+fn default_dim_to() -> LedBrightness {
+    LedBrightness::Off
+}
+
+fn default_colour_gamma() -> (f32, f32, f32) {
+    GAMMA_NONE
 }
 
 impl StdConfig for AuraConfig {
@@ -143,24 +333,7 @@ impl StdConfigLoad for AuraConfig {}
 impl AuraConfig {
     pub fn from_default_support(prod_id: AuraDevice, support_data: &LaptopLedData) -> Self {
         // create a default config here
-        let enabled = if prod_id.is_new_style() {
-            AuraPowerConfig::AuraDevRog2(AuraPower::new_all_on())
-        } else if prod_id.is_tuf_style() {
-            AuraPowerConfig::AuraDevTuf(HashSet::from([
-                AuraDevTuf::Awake,
-                AuraDevTuf::Boot,
-                AuraDevTuf::Sleep,
-                AuraDevTuf::Keyboard,
-            ]))
-        } else {
-            AuraPowerConfig::AuraDevRog1(HashSet::from([
-                AuraDevRog1::Awake,
-                AuraDevRog1::Boot,
-                AuraDevRog1::Sleep,
-                AuraDevRog1::Keyboard,
-                AuraDevRog1::Lightbar,
-            ]))
-        };
+        let enabled = AuraPowerConfig::all_on(prod_id);
         let mut config = AuraConfig {
             brightness: LedBrightness::Med,
             current_mode: AuraModeNum::Static,
@@ -168,6 +341,19 @@ impl AuraConfig {
             multizone: None,
             multizone_on: false,
             enabled,
+            thermal_effect: ThermalEffect::default(),
+            follows_profile: false,
+            profile_lighting: BTreeMap::new(),
+            last_brightness: LedBrightness::Med,
+            backlight_timeout_secs: 0,
+            dim_to: default_dim_to(),
+            restore_order: RestoreOrder::default(),
+            led_keepalive_secs: 0,
+            leds_off_on_lid_close: false,
+            mode_switch_fade: false,
+            zone_brightness: BTreeMap::new(),
+            colour_gamma: default_colour_gamma(),
+            secondary: BTreeMap::new(),
         };
 
         for n in &support_data.basic_modes {
@@ -200,6 +386,43 @@ impl AuraConfig {
         config
     }
 
+    /// Make `builtins` match the modes in `support_data`. A default effect is
+    /// added for each supported mode that is missing, such as one added by a
+    /// firmware or LED database update since the config was saved, and modes
+    /// no longer supported are removed.
+    pub fn sync_builtins(&mut self, support_data: &LaptopLedData) {
+        self.builtins
+            .retain(|mode, _| support_data.basic_modes.contains(mode));
+        for mode in &support_data.basic_modes {
+            self.builtins.entry(*mode).or_insert_with(|| {
+                debug!("AuraConfig: adding default for new mode {mode}");
+                AuraEffect::default_with_mode(*mode)
+            });
+        }
+    }
+
+    /// Set the primary colour of every builtin mode that uses one. Modes
+    /// without a colour, such as rainbow, are left as is. Returns the modes
+    /// that were changed.
+    pub fn set_global_colour(&mut self, colour: Colour) -> Vec<AuraModeNum> {
+        let mut changed = Vec::new();
+        for (mode, effect) in &mut self.builtins {
+            if AuraEffect::allowed_parameters(*mode).colour1 {
+                effect.colour1 = colour;
+                changed.push(*mode);
+            }
+        }
+        changed
+    }
+
+    /// The config of a secondary keyboard, created with defaults if it has
+    /// none yet
+    pub fn secondary_mut(&mut self, prod: AuraDevice) -> &mut AuraConfig {
+        self.secondary
+            .entry(<&str>::from(prod).to_owned())
+            .or_insert_with(|| AuraConfig::from_default_support(prod, &LaptopLedData::default()))
+    }
+
     /// Set the mode data, current mode, and if multizone enabled.
     ///
     /// Multipurpose, will accept `AuraEffect` with zones and put in the correct
@@ -231,6 +454,107 @@ impl AuraConfig {
         }
     }
 
+    /// Remove the per-zone effects for a mode so that it reverts to the
+    /// standard builtin. Returns `false` if the mode had no multizone data.
+    pub fn clear_multizone(&mut self, mode: AuraModeNum) -> bool {
+        let mut removed = false;
+        if let Some(multi) = self.multizone.as_mut() {
+            removed = multi.remove(&mode).is_some();
+            if multi.is_empty() {
+                self.multizone = None;
+            }
+        }
+        if removed && mode == self.current_mode {
+            self.multizone_on = false;
+        }
+        removed
+    }
+
+    /// Enable or disable the lighting following the platform profile. If there
+    /// is no mapping yet then a default is created: dimmed static for Quiet,
+    /// and the current mode at medium and high brightness for Balanced and
+    /// Performance.
+    pub fn set_follows_profile(&mut self, enabled: bool) {
+        self.follows_profile = enabled;
+        if enabled && self.profile_lighting.is_empty() {
+            let quiet_mode = if self.builtins.contains_key(&AuraModeNum::Static) {
+                AuraModeNum::Static
+            } else {
+                self.current_mode
+            };
+            for (profile, brightness, mode) in [
+                (Profile::Quiet, LedBrightness::Low, quiet_mode),
+                (Profile::Balanced, LedBrightness::Med, self.current_mode),
+                (Profile::Performance, LedBrightness::High, self.current_mode),
+            ] {
+                let multizone_on = self.multizone_on && mode == self.current_mode;
+                self.profile_lighting.insert(
+                    profile,
+                    ProfileLighting {
+                        brightness,
+                        mode,
+                        multizone_on,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Store the current brightness and mode as the lighting for the profile so
+    /// they are restored when switching back to it. Does nothing unless
+    /// following profiles is enabled.
+    pub fn remember_profile_lighting(&mut self, profile: Profile) -> bool {
+        if !self.follows_profile {
+            return false;
+        }
+        let lighting = ProfileLighting {
+            brightness: self.brightness,
+            mode: self.current_mode,
+            multizone_on: self.multizone_on,
+        };
+        self.profile_lighting.insert(profile, lighting) != Some(lighting)
+    }
+
+    /// The lighting to use for the profile, if following profiles is enabled
+    pub fn lighting_for_profile(&self, profile: Profile) -> Option<ProfileLighting> {
+        if !self.follows_profile {
+            return None;
+        }
+        self.profile_lighting.get(&profile).copied()
+    }
+
+    /// Set the brightness, remembering it if it is not `Off`
+    pub fn set_brightness(&mut self, brightness: LedBrightness) {
+        if self.brightness != LedBrightness::Off {
+            self.last_brightness = self.brightness;
+        }
+        self.brightness = brightness;
+    }
+
+    /// The level to dim to when the keyboard is idle, or `None` if the timeout
+    /// is disabled or the backlight is already at or below `dim_to`
+    pub fn idle_dim_level(&self) -> Option<LedBrightness> {
+        if self.backlight_timeout_secs == 0 || self.brightness as u8 <= self.dim_to as u8 {
+            return None;
+        }
+        Some(self.dim_to)
+    }
+
+    /// Turn the backlight off, or if already off then restore the last level
+    /// that was on. Returns the new brightness.
+    pub fn toggle_brightness(&mut self) -> LedBrightness {
+        if self.brightness == LedBrightness::Off {
+            let last = match self.last_brightness {
+                LedBrightness::Off => LedBrightness::Med,
+                last => last,
+            };
+            self.set_brightness(last);
+        } else {
+            self.set_brightness(LedBrightness::Off);
+        }
+        self.brightness
+    }
+
     pub fn get_multizone(&self, aura_type: AuraModeNum) -> Option<&[AuraEffect]> {
         if let Some(multi) = &self.multizone {
             return multi.get(&aura_type).map(|v| v.as_slice());
@@ -243,9 +567,177 @@ impl AuraConfig {
 mod tests {
     use rog_aura::aura_detection::LaptopLedData;
     use rog_aura::usb::AuraDevice;
-    use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour};
+    use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, LedBrightness};
+    use rog_profiles::Profile;
+
+    use rog_aura::aura_detection::PowerZones;
+    use rog_aura::power::AuraPower;
 
-    use super::AuraConfig;
+    use super::{AuraConfig, AuraPowerConfig, ProfileLighting, ThermalEffect};
+
+    #[test]
+    fn old_config_power_states() {
+        let json = r#"{"AuraDevRog2": {
+            "keyboard": {"zone": "Keyboard", "boot": true, "awake": true, "sleep": false},
+            "logo": {"zone": "Logo", "boot": false, "awake": true, "sleep": true},
+            "lightbar": {"zone": "Lightbar", "boot": true, "awake": false, "sleep": false, "shutdown": false}
+        }}"#;
+        let power = match serde_json::from_str(json).unwrap() {
+            AuraPowerConfig::AuraDevRog2(power) => power,
+            _ => panic!("expected AuraDevRog2"),
+        };
+        assert!(power.keyboard.shutdown);
+        assert!(!power.logo.shutdown);
+        assert!(!power.lightbar.shutdown);
+        assert_eq!(power.lid, AuraPower::new_all_on().lid);
+        assert_eq!(power.rear_glow.zone, PowerZones::RearGlow);
+
+        let bytes = power.to_bytes();
+        // boot_keyb_ | awake_keyb | shut_keyb_ | awake_logo | sleep_logo
+        assert_eq!(bytes[0], 0b1001_1110);
+        // boot_bar__ is set from the second byte
+        assert_eq!(bytes[1], 0b0000_0010);
+    }
+
+    #[test]
+    fn sync_builtins_adds_missing_modes() {
+        let support = LaptopLedData {
+            basic_modes: vec![
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+                AuraModeNum::Pulse,
+            ],
+            ..Default::default()
+        };
+        let mut config = AuraConfig::from_default_support(AuraDevice::X19b6, &support);
+        // A saved config from before Pulse was supported, plus an old mode
+        config.builtins.remove(&AuraModeNum::Pulse);
+        config.builtins.insert(
+            AuraModeNum::Rainbow,
+            AuraEffect::default_with_mode(AuraModeNum::Rainbow),
+        );
+        let mut breathe = AuraEffect::default_with_mode(AuraModeNum::Breathe);
+        breathe.colour1 = Colour { r: 1, g: 2, b: 3 };
+        config.builtins.insert(AuraModeNum::Breathe, breathe);
+
+        config.sync_builtins(&support);
+        assert_eq!(
+            config.builtins.keys().copied().collect::<Vec<_>>(),
+            vec![
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+                AuraModeNum::Pulse
+            ]
+        );
+        assert_eq!(
+            config.builtins[&AuraModeNum::Pulse].mode,
+            AuraModeNum::Pulse
+        );
+        // Saved effects are kept
+        assert_eq!(
+            config.builtins[&AuraModeNum::Breathe].colour1,
+            Colour { r: 1, g: 2, b: 3 }
+        );
+    }
+
+    #[test]
+    fn global_colour_skips_colourless_modes() {
+        let support = LaptopLedData {
+            basic_modes: vec![
+                AuraModeNum::Static,
+                AuraModeNum::Rainbow,
+                AuraModeNum::Breathe,
+            ],
+            ..Default::default()
+        };
+        let mut config = AuraConfig::from_default_support(AuraDevice::X19b6, &support);
+        let rainbow = config.builtins[&AuraModeNum::Rainbow].colour1;
+        let colour = Colour { r: 1, g: 2, b: 3 };
+
+        assert_eq!(
+            config.set_global_colour(colour),
+            vec![AuraModeNum::Static, AuraModeNum::Breathe]
+        );
+        assert_eq!(config.builtins[&AuraModeNum::Static].colour1, colour);
+        assert_eq!(config.builtins[&AuraModeNum::Breathe].colour1, colour);
+        assert_eq!(config.builtins[&AuraModeNum::Rainbow].colour1, rainbow);
+    }
+
+    #[test]
+    fn follows_profile_defaults() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.current_mode = AuraModeNum::Breathe;
+        assert_eq!(config.lighting_for_profile(Profile::Quiet), None);
+
+        config.set_follows_profile(true);
+        assert_eq!(
+            config.lighting_for_profile(Profile::Performance),
+            Some(ProfileLighting {
+                brightness: LedBrightness::High,
+                mode: AuraModeNum::Breathe,
+                multizone_on: false,
+            })
+        );
+        assert_eq!(
+            config
+                .lighting_for_profile(Profile::Quiet)
+                .map(|l| l.brightness),
+            Some(LedBrightness::Low)
+        );
+
+        // User changes are kept when toggling
+        config.profile_lighting.remove(&Profile::Balanced);
+        config.set_follows_profile(false);
+        assert_eq!(config.lighting_for_profile(Profile::Quiet), None);
+        config.set_follows_profile(true);
+        assert_eq!(config.lighting_for_profile(Profile::Balanced), None);
+    }
+
+    #[test]
+    fn remember_profile_lighting() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.current_mode = AuraModeNum::Pulse;
+        assert!(!config.remember_profile_lighting(Profile::Quiet));
+        assert!(config.profile_lighting.is_empty());
+
+        config.set_follows_profile(true);
+        config.brightness = LedBrightness::Off;
+        assert!(config.remember_profile_lighting(Profile::Quiet));
+        // Unchanged state doesn't need a write
+        assert!(!config.remember_profile_lighting(Profile::Quiet));
+        assert_eq!(
+            config.lighting_for_profile(Profile::Quiet),
+            Some(ProfileLighting {
+                brightness: LedBrightness::Off,
+                mode: AuraModeNum::Pulse,
+                multizone_on: false,
+            })
+        );
+        assert_eq!(
+            config
+                .lighting_for_profile(Profile::Performance)
+                .map(|l| l.brightness),
+            Some(LedBrightness::High)
+        );
+
+        // The multizone state is kept per profile
+        config.multizone_on = true;
+        assert!(config.remember_profile_lighting(Profile::Performance));
+        assert_eq!(
+            config
+                .lighting_for_profile(Profile::Performance)
+                .map(|l| l.multizone_on),
+            Some(true)
+        );
+        assert_eq!(
+            config
+                .lighting_for_profile(Profile::Quiet)
+                .map(|l| l.multizone_on),
+            Some(false)
+        );
+    }
 
     #[test]
     fn set_multizone_4key_config() {
@@ -385,4 +877,101 @@ mod tests {
         let sta = res.get(&AuraModeNum::Pulse).unwrap();
         assert_eq!(sta.len(), 1);
     }
+
+    #[test]
+    fn clear_multizone_restores_builtin() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.builtins.insert(
+            AuraModeNum::Static,
+            AuraEffect {
+                mode: AuraModeNum::Static,
+                ..Default::default()
+            },
+        );
+
+        for (mode, zone) in [
+            (AuraModeNum::Breathe, AuraZone::Key1),
+            (AuraModeNum::Static, AuraZone::Key1),
+            (AuraModeNum::Static, AuraZone::Key2),
+        ] {
+            config.set_builtin(AuraEffect {
+                mode,
+                zone,
+                ..Default::default()
+            });
+        }
+        assert!(config.multizone_on);
+        assert_eq!(config.current_mode, AuraModeNum::Static);
+
+        assert!(config.clear_multizone(AuraModeNum::Static));
+        assert!(!config.multizone_on);
+        assert!(config.get_multizone(AuraModeNum::Static).is_none());
+        assert_eq!(config.get_multizone(AuraModeNum::Breathe).unwrap().len(), 1);
+        assert_eq!(
+            config.builtins.get(&AuraModeNum::Static).unwrap().zone,
+            AuraZone::None
+        );
+
+        // No multizone data is a no-op
+        assert!(!config.clear_multizone(AuraModeNum::Static));
+        assert!(config.clear_multizone(AuraModeNum::Breathe));
+        assert!(config.multizone.is_none());
+    }
+
+    #[test]
+    fn thermal_effect_colour_range() {
+        let thermal = ThermalEffect {
+            min_temp: 40,
+            max_temp: 90,
+        };
+        assert_eq!(thermal.colour_for(20), Colour { r: 0, g: 0, b: 255 });
+        assert_eq!(thermal.colour_for(40), Colour { r: 0, g: 0, b: 255 });
+        assert_eq!(
+            thermal.colour_for(65),
+            Colour {
+                r: 128,
+                g: 0,
+                b: 128
+            }
+        );
+        assert_eq!(thermal.colour_for(90), Colour { r: 255, g: 0, b: 0 });
+        assert_eq!(thermal.colour_for(110), Colour { r: 255, g: 0, b: 0 });
+    }
+
+    #[test]
+    fn toggle_brightness_restores_level() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.set_brightness(LedBrightness::High);
+        assert_eq!(config.toggle_brightness(), LedBrightness::Off);
+        assert_eq!(config.toggle_brightness(), LedBrightness::High);
+
+        // Cycling down to off still remembers the last level that was on
+        config.set_brightness(LedBrightness::Low);
+        config.set_brightness(LedBrightness::Off);
+        assert_eq!(config.toggle_brightness(), LedBrightness::Low);
+
+        config.brightness = LedBrightness::Off;
+        config.last_brightness = LedBrightness::Off;
+        assert_eq!(config.toggle_brightness(), LedBrightness::Med);
+    }
+
+    #[test]
+    fn idle_dim_level() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.brightness = LedBrightness::High;
+        assert_eq!(config.idle_dim_level(), None);
+
+        config.backlight_timeout_secs = 30;
+        assert_eq!(config.idle_dim_level(), Some(LedBrightness::Off));
+
+        config.dim_to = LedBrightness::Low;
+        assert_eq!(config.idle_dim_level(), Some(LedBrightness::Low));
+        config.brightness = LedBrightness::Low;
+        assert_eq!(config.idle_dim_level(), None);
+        config.brightness = LedBrightness::Off;
+        assert_eq!(config.idle_dim_level(), None);
+    }
 }
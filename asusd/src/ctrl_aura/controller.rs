@@ -1,36 +1,50 @@
 use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use config_traits::{StdConfig, StdConfigLoad};
 use dmi_id::DMIID;
-use log::{info, warn};
-use rog_aura::advanced::{LedUsbPackets, UsbPackets};
-use rog_aura::aura_detection::{LaptopLedData, ASUS_KEYBOARD_DEVICES};
-use rog_aura::usb::{AuraDevice, LED_APPLY, LED_SET};
-use rog_aura::{AuraEffect, AuraZone, Direction, LedBrightness, Speed, GRADIENT, LED_MSG_LEN};
+use log::{debug, info, warn};
+use rog_aura::advanced::{KeyMap, LedCode, LedUsbPackets, UsbPackets};
+use rog_aura::aura_detection::{LaptopLedData, ASUS_KEYBOARD_DEVICES, ASUS_LED_MODE_CONF};
+use rog_aura::layouts::KeyLayout;
+use rog_aura::usb::{aura_brightness_bytes, AuraDevice, KeyboardTarget, LED_APPLY, LED_SET};
+use rog_aura::{
+    AdvancedAuraType, AuraEffect, AuraModeNum, AuraParameters, AuraZone, Colour, Direction,
+    LedBrightness, Speed, GAMMA_NONE, GRADIENT, LED_MSG_LEN,
+};
 use rog_platform::hid_raw::HidRaw;
 use rog_platform::keyboard_led::KeyboardLed;
-use rog_platform::supported::LedSupportedFunctions;
+use rog_platform::platform::AsusPlatform;
+use rog_platform::supported::{LedSupportedFunctions, UnsupportedReason, NODE_NOT_FOUND};
+use rog_profiles::Profile;
+use tokio::sync::watch;
 
-use super::config::{AuraConfig, AuraPowerConfig};
+use super::config::{AuraConfig, AuraPowerConfig, RestoreOrder};
 use crate::error::RogError;
-use crate::GetSupported;
+use crate::{Devices, GetSupported};
+
+/// Time between each brightness level of a fade
+const FADE_STEP: Duration = Duration::from_millis(40);
 
 impl GetSupported for CtrlKbdLed {
     type A = LedSupportedFunctions;
 
-    fn get_supported() -> Self::A {
+    fn get_supported(devices: &Devices) -> Self::A {
         // let mode = <&str>::from(&<AuraModes>::from(*mode));
         let laptop = LaptopLedData::get_data();
 
         let mut prod_id = AuraDevice::Unknown;
         for prod in ASUS_KEYBOARD_DEVICES {
-            if HidRaw::new(prod.into()).is_ok() {
+            if devices.hidraw(prod.into()).is_ok() {
                 prod_id = prod;
                 break;
             }
         }
 
-        let rgb = KeyboardLed::new();
+        let rgb = devices.kbd_led();
         if let Ok(p) = rgb.as_ref() {
             if p.has_kbd_rgb_mode() {
                 prod_id = AuraDevice::Tuf;
@@ -46,6 +60,198 @@ impl GetSupported for CtrlKbdLed {
             power_zones: laptop.power_zones,
         }
     }
+
+    fn unsupported_reasons(supported: &Self::A, _devices: &Devices) -> Vec<UnsupportedReason> {
+        let mut reasons = Vec::new();
+        if !supported.brightness {
+            reasons.push(UnsupportedReason::new(
+                "keyboard_led.brightness",
+                "The asus::kbd_backlight LED was not found",
+            ));
+        }
+        if supported.dev_id == AuraDevice::Unknown {
+            reasons.push(UnsupportedReason::new(
+                "keyboard_led.dev_id",
+                "No ASUS USB keyboard or TUF `kbd_rgb_mode` node was found",
+            ));
+        }
+        if supported.basic_modes.is_empty() {
+            reasons.push(UnsupportedReason::new(
+                "keyboard_led.basic_modes",
+                format!("This laptop has no LED modes listed in {ASUS_LED_MODE_CONF}"),
+            ));
+        }
+        reasons
+    }
+}
+
+/// How long each on/off step lasts when blinking a zone for identification
+pub const IDENTIFY_BLINK_MS: u64 = 250;
+
+/// Where the keyboard layouts are installed, they are shipped with
+/// rog-control-center
+const LAYOUT_DATA_DIR: &str = "/usr/share/rog-gui/";
+
+/// The longest a notification flash may hold the keyboard on its colour
+pub const FLASH_MAX_MS: u64 = 10_000;
+
+/// What triggered the last change to the keyboard lighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// A dbus client such as asusctl or the GUI
+    Dbus,
+    /// Brightness changed outside of asusd, such as with the Fn keys
+    Hotkey,
+    /// Restored on start of asusd
+    Boot,
+    /// Restored on resume from sleep
+    Resume,
+    /// Restored on shutdown
+    Shutdown,
+    /// Lighting mapped to the platform profile
+    Profile,
+    /// The mode demo started with `demo_cycle`
+    Demo,
+    /// The temperature effect started with `start_thermal_effect`
+    Thermal,
+    /// The config file was edited outside of asusd
+    Config,
+}
+
+/// A small xorshift generator for `random_effect`. It is seedable so that
+/// tests are repeatable.
+pub(super) struct EffectRng(u64);
+
+impl EffectRng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift is stuck at zero
+        Self(seed.max(1))
+    }
+
+    /// Seeded from the clock
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number from `0` to below `n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, from: &[T]) -> T {
+        from[self.below(from.len())]
+    }
+
+    fn colour(&mut self) -> Colour {
+        let c = self.next().to_le_bytes();
+        Colour {
+            r: c[0],
+            g: c[1],
+            b: c[2],
+        }
+    }
+}
+
+/// A random effect for one of `modes` on the whole keyboard. Only the
+/// parameters the mode accepts are randomised, the rest are the defaults.
+/// `None` if `modes` is empty.
+fn random_effect_for(modes: &[AuraModeNum], rng: &mut EffectRng) -> Option<AuraEffect> {
+    if modes.is_empty() {
+        return None;
+    }
+    let mode = rng.pick(modes);
+    let allowed = AuraEffect::allowed_parameters(mode);
+    let mut effect = AuraEffect::default_with_mode(mode);
+    if allowed.colour1 {
+        effect.colour1 = rng.colour();
+    }
+    if allowed.colour2 {
+        effect.colour2 = rng.colour();
+    }
+    if allowed.speed {
+        effect.speed = rng.pick(&[Speed::Low, Speed::Med, Speed::High]);
+    }
+    if allowed.direction {
+        // Up and down are only valid for rainbow
+        effect.direction = if mode == AuraModeNum::Rainbow {
+            rng.pick(&[
+                Direction::Right,
+                Direction::Left,
+                Direction::Up,
+                Direction::Down,
+            ])
+        } else {
+            rng.pick(&[Direction::Right, Direction::Left])
+        };
+    }
+    Some(effect)
+}
+
+/// A copy of per-key packets with the gamma correction applied to the colours.
+/// The colours are RGB triples after the 9 header bytes of each packet.
+fn gamma_corrected_packets(effect: &UsbPackets, gamma: (f32, f32, f32)) -> UsbPackets {
+    let mut effect = effect.clone();
+    for row in &mut effect {
+        if let Some(colours) = row.get_mut(9..) {
+            for rgb in colours.chunks_exact_mut(3) {
+                let colour = Colour {
+                    r: rgb[0],
+                    g: rgb[1],
+                    b: rgb[2],
+                }
+                .gamma_corrected(gamma);
+                rgb.copy_from_slice(&[colour.r, colour.g, colour.b]);
+            }
+        }
+    }
+    effect
+}
+
+/// The brightness levels to step through to fade from `from` to `to`, not
+/// including `from`
+fn fade_steps(from: LedBrightness, to: LedBrightness) -> Vec<LedBrightness> {
+    let (from, to) = (from as u32, to as u32);
+    if from <= to {
+        (from + 1..=to).map(LedBrightness::from).collect()
+    } else {
+        (to..from).rev().map(LedBrightness::from).collect()
+    }
+}
+
+impl From<ChangeSource> for &str {
+    fn from(s: ChangeSource) -> Self {
+        match s {
+            ChangeSource::Dbus => "dbus",
+            ChangeSource::Hotkey => "hotkey",
+            ChangeSource::Boot => "boot",
+            ChangeSource::Resume => "resume",
+            ChangeSource::Shutdown => "shutdown",
+            ChangeSource::Profile => "profile",
+            ChangeSource::Demo => "demo",
+            ChangeSource::Thermal => "thermal",
+            ChangeSource::Config => "config",
+        }
+    }
+}
+
+/// The platform profile as read from the kernel, so changes made by other
+/// tools or the Fn+F5 key are seen too
+pub(super) fn active_platform_profile(platform: &AsusPlatform) -> Option<Profile> {
+    platform
+        .get_platform_profile()
+        .ok()
+        .and_then(|profile| Profile::from_str(&profile).ok())
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -55,6 +261,30 @@ pub enum LEDNode {
     None,
 }
 
+impl LEDNode {
+    /// No RGB node was found, only the brightness can be set
+    pub fn is_none(&self) -> bool {
+        matches!(self, LEDNode::None)
+    }
+
+    /// The device node in use, or `NODE_NOT_FOUND`
+    pub fn path(&self) -> String {
+        match self {
+            LEDNode::KbdLed(k) => k.path().display().to_string(),
+            LEDNode::Rog(r) => r.path().display().to_string(),
+            LEDNode::None => NODE_NOT_FOUND.to_owned(),
+        }
+    }
+}
+
+/// Another ROG USB keyboard found with the laptop keyboard, such as an external
+/// one. It is addressed with a `KeyboardTarget`.
+#[derive(Debug)]
+pub struct SecondaryKeyboard {
+    pub prod: AuraDevice,
+    pub node: HidRaw,
+}
+
 pub struct CtrlKbdLed {
     // TODO: config stores the keyboard type as an AuraPower, use or update this
     pub led_prod: AuraDevice,
@@ -63,23 +293,142 @@ pub struct CtrlKbdLed {
     pub supported_modes: LaptopLedData,
     pub flip_effect_write: bool,
     pub per_key_mode_active: bool,
+    /// The last per-key colours written by asusd, used to update only some
+    /// keys without clearing the rest
+    pub per_key_buffer: LedUsbPackets,
+    /// The function row and media keys, taken from the keyboard layout
+    pub fn_row: Vec<LedCode>,
+    pub thermal_effect_running: Arc<AtomicBool>,
+    pub demo_running: Arc<AtomicBool>,
+    /// Held by a notification flash or zone identify until it has restored
+    /// the mode, so that they run one after the other without holding the
+    /// controller
+    pub flash_lock: Arc<tokio::sync::Mutex<()>>,
+    /// When and why the lighting last changed, for debugging unexpected
+    /// changes
+    pub last_change: Option<(SystemTime, ChangeSource)>,
+    /// Set while the backlight is dimmed for inactivity, holding the level to
+    /// restore on the next key press
+    pub idle_restore: Option<LedBrightness>,
+    /// Set while the backlight is off because the lid is closed
+    pub lid_off: bool,
+    /// Other ROG keyboards found, the first found is `led_node`
+    pub secondary: Vec<SecondaryKeyboard>,
+    /// Brightness changes are sent to the AniMe controller so that it can
+    /// follow the keyboard
+    pub brightness_tx: watch::Sender<LedBrightness>,
+    /// Used to read the platform profile, `None` if the platform driver is
+    /// not available
+    pub platform: Option<AsusPlatform>,
+    /// Where the nodes are found again by `refresh_nodes`
+    pub devices: Devices,
     pub config: AuraConfig,
 }
 
 impl CtrlKbdLed {
-    pub fn new(supported_modes: LaptopLedData) -> Result<Self, RogError> {
+    pub fn new(
+        supported_modes: LaptopLedData,
+        brightness_tx: watch::Sender<LedBrightness>,
+        devices: &Devices,
+    ) -> Result<Self, RogError> {
+        let rgb_led = Self::find_brightness_node(devices)?;
+        let (led_prod, led_node, secondary) = Self::find_led_node(devices, &rgb_led)?;
+
+        // Loads the defaults from the DB for the keyboard found
+        let config_init = AuraConfig::from_default_support(led_prod, &supported_modes);
+        let mut config_loaded = config_init.clone().try_load().unwrap_or_else(|e| {
+            warn!("Could not load the LED config, using defaults: {e}");
+            config_init.clone()
+        });
+        // Just incase the supported modes changed since the config was saved
+        config_loaded.sync_builtins(&supported_modes);
+
+        if let (Some(mut multizone_init), Some(multizone_loaded)) =
+            (config_init.multizone, config_loaded.multizone.as_mut())
+        {
+            for mode in multizone_init.iter_mut() {
+                // update init values from loaded values if they exist
+                if let Some(loaded) = multizone_loaded.get(mode.0) {
+                    let mut new_set = Vec::new();
+                    // only reuse a zone mode if the mode is supported
+                    for mode in loaded {
+                        if supported_modes.basic_modes.contains(&mode.mode) {
+                            new_set.push(mode.clone());
+                        }
+                    }
+                    *mode.1 = new_set;
+                }
+            }
+            *multizone_loaded = multizone_init;
+        }
+
+        let fn_row = KeyLayout::find_layout(supported_modes.clone(), LAYOUT_DATA_DIR.into())
+            .unwrap_or_else(|e| {
+                info!("No keyboard layout found, using the default function row: {e}");
+                KeyLayout::default_layout()
+            })
+            .function_row();
+
+        let ctrl = CtrlKbdLed {
+            led_prod,
+            led_node,               // on TUF this is the same as rgb_led / kd_brightness
+            kd_brightness: rgb_led, // If was none then we already returned above
+            supported_modes,
+            flip_effect_write: false,
+            per_key_mode_active: false,
+            per_key_buffer: LedUsbPackets::new_per_key(),
+            fn_row,
+            thermal_effect_running: Arc::new(AtomicBool::new(false)),
+            demo_running: Arc::new(AtomicBool::new(false)),
+            flash_lock: Default::default(),
+            last_change: None,
+            idle_restore: None,
+            lid_off: false,
+            secondary,
+            brightness_tx,
+            platform: devices.platform().ok(),
+            devices: devices.clone(),
+            config: config_loaded,
+        };
+        ctrl.send_brightness();
+        Ok(ctrl)
+    }
+
+    /// Find the `asus::kbd_backlight` node used for brightness
+    fn find_brightness_node(devices: &Devices) -> Result<KeyboardLed, RogError> {
+        devices.kbd_led().map_err(|e| {
+            warn!("{e}");
+            RogError::BrightnessNodeMissing
+        })
+    }
+
+    /// Find the keyboard controller, either the ROG USB device or the TUF
+    /// `kbd_rgb_mode` node of `rgb_led`. Any further ROG USB keyboards, such
+    /// as an external one, are returned as secondary.
+    fn find_led_node(
+        devices: &Devices,
+        rgb_led: &KeyboardLed,
+    ) -> Result<(AuraDevice, LEDNode, Vec<SecondaryKeyboard>), RogError> {
         let mut led_prod = AuraDevice::Unknown;
         let mut usb_node = None;
+        let mut secondary = Vec::new();
         for prod in ASUS_KEYBOARD_DEVICES {
-            match HidRaw::new(prod.into()) {
+            match devices.hidraw(prod.into()) {
                 Ok(node) => {
-                    led_prod = prod;
-                    usb_node = Some(node);
                     info!(
                         "Looked for keyboard controller 0x{}: Found",
                         <&str>::from(prod)
                     );
-                    break;
+                    if usb_node.is_none() {
+                        led_prod = prod;
+                        usb_node = Some(node);
+                    } else if devices.is_simulated() {
+                        // Every product is simulated, only the first is used
+                        break;
+                    } else {
+                        info!("Using 0x{} as a secondary keyboard", <&str>::from(prod));
+                        secondary.push(SecondaryKeyboard { prod, node });
+                    }
                 }
                 Err(err) => info!(
                     "Looked for keyboard controller 0x{}: {err}",
@@ -88,8 +437,6 @@ impl CtrlKbdLed {
             }
         }
 
-        let rgb_led = KeyboardLed::new()?;
-
         if usb_node.is_none() && !rgb_led.has_kbd_rgb_mode() {
             let dmi = DMIID::new().unwrap_or_default();
             if dmi.dmi_family.contains("TUF") {
@@ -97,8 +444,11 @@ impl CtrlKbdLed {
                     "kbd_rgb_mode was not found in the /sys/. You require a minimum 6.1 kernel \
                      and a supported TUF laptop"
                 );
+                return Err(RogError::KernelTooOld(
+                    "kbd_rgb_mode requires a minimum 6.1 kernel".to_owned(),
+                ));
             }
-            return Err(RogError::NoAuraKeyboard);
+            return Err(RogError::KeyboardNotFound);
         }
 
         let led_node = if let Some(rog) = usb_node {
@@ -110,49 +460,21 @@ impl CtrlKbdLed {
         } else {
             LEDNode::None
         };
+        Ok((led_prod, led_node, secondary))
+    }
 
-        // New loads data fromt he DB also
-        let mut config_init = AuraConfig::new();
-        let mut config_loaded = config_init.clone().load();
-        // update the initialised data with what we loaded from disk
-        for mode in &mut config_init.builtins {
-            // update init values from loaded values if they exist
-            if let Some(loaded) = config_loaded.builtins.get(mode.0) {
-                *mode.1 = loaded.clone();
-            }
-        }
-        // Then replace just incase the initialised data contains new modes added
-        config_loaded.builtins = config_init.builtins;
-
-        if let (Some(mut multizone_init), Some(multizone_loaded)) =
-            (config_init.multizone, config_loaded.multizone.as_mut())
-        {
-            for mode in multizone_init.iter_mut() {
-                // update init values from loaded values if they exist
-                if let Some(loaded) = multizone_loaded.get(mode.0) {
-                    let mut new_set = Vec::new();
-                    // only reuse a zone mode if the mode is supported
-                    for mode in loaded {
-                        if supported_modes.basic_modes.contains(&mode.mode) {
-                            new_set.push(mode.clone());
-                        }
-                    }
-                    *mode.1 = new_set;
-                }
-            }
-            *multizone_loaded = multizone_init;
-        }
-
-        let ctrl = CtrlKbdLed {
-            led_prod,
-            led_node,               // on TUF this is the same as rgb_led / kd_brightness
-            kd_brightness: rgb_led, // If was none then we already returned above
-            supported_modes,
-            flip_effect_write: false,
-            per_key_mode_active: false,
-            config: config_loaded,
-        };
-        Ok(ctrl)
+    /// Find the brightness and keyboard nodes again, for when they have gone
+    /// missing such as after a resume quirk. The config is kept.
+    pub(super) fn refresh_nodes(&mut self) -> Result<(), RogError> {
+        info!("Looking for the keyboard LED nodes again");
+        let rgb_led = Self::find_brightness_node(&self.devices)?;
+        let (led_prod, led_node, secondary) = Self::find_led_node(&self.devices, &rgb_led)?;
+        self.kd_brightness = rgb_led;
+        self.led_prod = led_prod;
+        self.led_node = led_node;
+        self.secondary = secondary;
+        self.per_key_mode_active = false;
+        Ok(())
     }
 
     pub(super) fn get_brightness(&self) -> Result<u8, RogError> {
@@ -161,20 +483,112 @@ impl CtrlKbdLed {
             .map_err(RogError::Platform)
     }
 
+    /// Send the current brightness to listeners if it changed
+    pub(super) fn send_brightness(&self) {
+        if let Ok(bright) = self.get_brightness() {
+            let bright = LedBrightness::from(bright as u32);
+            self.brightness_tx.send_if_modified(|last| {
+                let changed = *last != bright;
+                *last = bright;
+                changed
+            });
+        }
+    }
+
     pub(super) fn set_brightness(&self, brightness: LedBrightness) -> Result<(), RogError> {
         self.kd_brightness
             .set_brightness(brightness as u8)
             .map_err(RogError::Platform)
     }
 
+    /// Step the brightness one level at a time from `from` to `to`. The
+    /// controller lock is held for the length of the fade so that further
+    /// changes queue behind it.
+    pub(super) async fn set_brightness_faded(
+        &self,
+        from: LedBrightness,
+        to: LedBrightness,
+    ) -> Result<(), RogError> {
+        for level in fade_steps(from, to) {
+            self.set_brightness(level)?;
+            tokio::time::sleep(FADE_STEP).await;
+        }
+        Ok(())
+    }
+
+    /// Run `write`, which changes the mode, with the brightness faded out and
+    /// back in if `mode_switch_fade` is set. Nothing is faded while the
+    /// backlight is off or dimmed.
+    async fn with_mode_fade<F>(&mut self, write: F) -> Result<(), RogError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), RogError>,
+    {
+        let brightness = self.config.brightness;
+        if !self.config.mode_switch_fade
+            || brightness == LedBrightness::Off
+            || self.lid_off
+            || self.idle_restore.is_some()
+        {
+            return write(self);
+        }
+        self.set_brightness_faded(brightness, LedBrightness::Off)
+            .await?;
+        let res = write(self);
+        self.set_brightness_faded(LedBrightness::Off, brightness)
+            .await?;
+        res
+    }
+
+    /// Dim the backlight to `dim_to` if the keyboard has been idle for
+    /// `backlight_timeout_secs`. The config brightness is left unchanged.
+    pub(super) fn idle_dim(&mut self) -> Result<(), RogError> {
+        if self.idle_restore.is_some() || self.lid_off {
+            return Ok(());
+        }
+        if let Some(dim_to) = self.config.idle_dim_level() {
+            debug!("Keyboard idle, dimming backlight to {dim_to:?}");
+            self.set_brightness(dim_to)?;
+            self.idle_restore = Some(self.config.brightness);
+        }
+        Ok(())
+    }
+
+    /// Restore the backlight after an idle dim. Nothing is restored if the
+    /// brightness was changed while idle.
+    pub(super) fn idle_wake(&mut self) -> Result<(), RogError> {
+        if self.lid_off {
+            return Ok(());
+        }
+        if let Some(restore) = self.idle_restore.take() {
+            let current: LedBrightness = (self.kd_brightness.get_brightness()? as u32).into();
+            if self.config.brightness == restore && Some(current) == self.config.idle_dim_level() {
+                debug!("Keyboard active, restoring backlight to {restore:?}");
+                self.set_brightness(restore)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn next_brightness(&mut self) -> Result<(), RogError> {
         let mut bright = (self.config.brightness as u32) + 1;
         if bright > 3 {
             bright = 0;
         }
-        self.config.brightness = <LedBrightness>::from(bright);
-        self.config.write();
-        self.set_brightness(self.config.brightness)
+        self.config.set_brightness(<LedBrightness>::from(bright));
+        self.remember_profile_lighting();
+        self.set_brightness(self.config.brightness)?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Turn the backlight off, or back on at the last level it was at before
+    /// being turned off. Returns the new brightness.
+    pub(super) fn toggle_brightness(&mut self) -> Result<LedBrightness, RogError> {
+        let brightness = self.config.toggle_brightness();
+        self.remember_profile_lighting();
+        self.set_brightness(brightness)?;
+        self.config.try_write()?;
+        Ok(brightness)
     }
 
     pub fn prev_brightness(&mut self) -> Result<(), RogError> {
@@ -184,21 +598,28 @@ impl CtrlKbdLed {
         } else {
             bright -= 1;
         }
-        self.config.brightness = <LedBrightness>::from(bright);
-        self.config.write();
-        self.set_brightness(self.config.brightness)
+        self.config.set_brightness(<LedBrightness>::from(bright));
+        self.remember_profile_lighting();
+        self.set_brightness(self.config.brightness)?;
+        self.config.try_write()?;
+        Ok(())
     }
 
     /// Set combination state for boot animation/sleep animation/all leds/keys
     /// leds/side leds LED active
     pub(super) fn set_power_states(&mut self) -> Result<(), RogError> {
+        let enabled = self.config.enabled.clone();
+        self.write_power_states(&enabled)
+    }
+
+    fn write_power_states(&mut self, enabled: &AuraPowerConfig) -> Result<(), RogError> {
         if let LEDNode::KbdLed(platform) = &mut self.led_node {
-            if let Some(pwr) = AuraPowerConfig::to_tuf_bool_array(&self.config.enabled) {
+            if let Some(pwr) = AuraPowerConfig::to_tuf_bool_array(enabled) {
                 let buf = [1, pwr[1] as u8, pwr[2] as u8, pwr[3] as u8, pwr[4] as u8];
                 platform.set_kbd_rgb_state(&buf)?;
             }
         } else if let LEDNode::Rog(hid_raw) = &self.led_node {
-            let bytes = AuraPowerConfig::to_bytes(&self.config.enabled);
+            let bytes = AuraPowerConfig::to_bytes(enabled);
             let message = [0x5d, 0xbd, 0x01, bytes[0], bytes[1], bytes[2], bytes[3]];
 
             hid_raw.write_bytes(&message)?;
@@ -211,43 +632,275 @@ impl CtrlKbdLed {
 
     /// Set an Aura effect if the effect mode or zone is supported.
     ///
-    /// On success the aura config file is read to refresh cached values, then
-    /// the effect is stored and config written to disk.
-    pub(crate) fn set_effect(&mut self, effect: AuraEffect) -> Result<(), RogError> {
-        if !self.supported_modes.basic_modes.contains(&effect.mode)
-            || effect.zone != AuraZone::None
-                && !self.supported_modes.basic_zones.contains(&effect.zone)
-        {
-            return Err(RogError::AuraEffectNotSupported);
-        }
+    /// The aura config file is read to refresh cached values, then the effect
+    /// is written and stored and the config written to disk.
+    pub(crate) async fn set_effect(&mut self, effect: AuraEffect) -> Result<(), RogError> {
+        self.validate_effect(&effect)?;
+        self.check_rgb()?;
 
-        self.write_mode(&effect)?;
-        self.config.read(); // refresh config if successful
-        self.config.set_builtin(effect);
+        self.config.read(); // refresh config before storing the effect
+        self.write_effect(effect).await?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Write `effect` and store it in the config without saving. Any dimmed
+    /// zone is written again after it so that it keeps its zone brightness.
+    async fn write_effect(&mut self, effect: AuraEffect) -> Result<(), RogError> {
+        self.thermal_effect_running.store(false, Ordering::SeqCst);
+        self.demo_running.store(false, Ordering::SeqCst);
+        self.with_mode_fade(|ctrl| {
+            ctrl.write_mode(&effect)?;
+            ctrl.config.set_builtin(effect);
+            ctrl.write_zone_brightness()
+        })
+        .await?;
         if self.config.brightness == LedBrightness::Off {
             self.config.brightness = LedBrightness::Med;
         }
-        self.config.write();
+        self.remember_profile_lighting();
         self.set_brightness(self.config.brightness)?;
         Ok(())
     }
 
+    /// Set and store a random effect from the supported modes. Errors with
+    /// `NotSupported` if the keyboard has no modes.
+    pub(super) async fn set_random_effect(
+        &mut self,
+        rng: &mut EffectRng,
+    ) -> Result<AuraEffect, RogError> {
+        let effect = random_effect_for(&self.supported_modes.basic_modes, rng)
+            .ok_or(RogError::NotSupported)?;
+        self.set_effect(effect.clone()).await?;
+        Ok(effect)
+    }
+
+    /// The product of every keyboard that can be addressed, the primary first
+    pub(super) fn keyboards(&self) -> Vec<AuraDevice> {
+        std::iter::once(self.led_prod)
+            .chain(self.secondary.iter().map(|k| k.prod))
+            .collect()
+    }
+
+    fn secondary_node(&self, prod: AuraDevice) -> Result<HidRaw, RogError> {
+        self.secondary
+            .iter()
+            .find(|k| k.prod == prod)
+            .map(|k| k.node.clone())
+            .ok_or_else(|| RogError::NotFound(format!("keyboard {}", <&str>::from(prod))))
+    }
+
+    fn write_secondary_mode(
+        node: &HidRaw,
+        effect: &AuraEffect,
+        gamma: (f32, f32, f32),
+    ) -> Result<(), RogError> {
+        let bytes: [u8; LED_MSG_LEN] = (&effect.with_gamma(gamma)).into();
+        node.write_bytes(&bytes)?;
+        node.write_bytes(&LED_SET)?;
+        node.write_bytes(&LED_APPLY)?;
+        Ok(())
+    }
+
+    /// Write and store an effect on a secondary keyboard. These are set as a
+    /// whole, so the zone must be `None`.
+    fn set_secondary_effect(
+        &mut self,
+        prod: AuraDevice,
+        effect: AuraEffect,
+    ) -> Result<(), RogError> {
+        if effect.zone != AuraZone::None {
+            return Err(RogError::AuraEffectInvalid(format!(
+                "zone {:?} is not available on secondary keyboards",
+                effect.zone
+            )));
+        }
+        let node = self.secondary_node(prod)?;
+        self.config.read();
+        let gamma = self.config.secondary_mut(prod).colour_gamma;
+        Self::write_secondary_mode(&node, &effect, gamma)?;
+        self.config.secondary_mut(prod).set_builtin(effect);
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    fn set_secondary_brightness(
+        &mut self,
+        prod: AuraDevice,
+        brightness: LedBrightness,
+    ) -> Result<(), RogError> {
+        let node = self.secondary_node(prod)?;
+        node.write_bytes(&aura_brightness_bytes(brightness as u8))?;
+        self.config.read();
+        self.config.secondary_mut(prod).brightness = brightness;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// `set_effect` on the keyboards of `target`. Secondary keyboards are
+    /// always set as a whole, ignoring the zone.
+    pub(super) async fn set_effect_for(
+        &mut self,
+        target: KeyboardTarget,
+        effect: AuraEffect,
+    ) -> Result<(), RogError> {
+        match target {
+            KeyboardTarget::Primary => self.set_effect(effect).await,
+            KeyboardTarget::Product(prod) if prod == self.led_prod => self.set_effect(effect).await,
+            KeyboardTarget::Product(prod) => self.set_secondary_effect(prod, effect),
+            KeyboardTarget::All => {
+                // A brightness-only primary is skipped so that the other
+                // keyboards are still set
+                if self.targets_primary(target) || self.secondary.is_empty() {
+                    self.set_effect(effect.clone()).await?;
+                }
+                let secondary: Vec<AuraDevice> = self.secondary.iter().map(|k| k.prod).collect();
+                for prod in secondary {
+                    let effect = AuraEffect {
+                        zone: AuraZone::None,
+                        ..effect.clone()
+                    };
+                    self.set_secondary_effect(prod, effect)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// If an effect set for `target` changes the primary keyboard
+    pub(super) fn targets_primary(&self, target: KeyboardTarget) -> bool {
+        match target {
+            KeyboardTarget::Primary => true,
+            KeyboardTarget::Product(prod) => prod == self.led_prod,
+            KeyboardTarget::All => !self.led_node.is_none(),
+        }
+    }
+
+    /// `set_brightness` on the keyboards of `target`
+    pub(super) fn set_brightness_for(
+        &mut self,
+        target: KeyboardTarget,
+        brightness: LedBrightness,
+    ) -> Result<(), RogError> {
+        match target {
+            KeyboardTarget::Primary => self.set_brightness(brightness),
+            KeyboardTarget::Product(prod) if prod == self.led_prod => {
+                self.set_brightness(brightness)
+            }
+            KeyboardTarget::Product(prod) => self.set_secondary_brightness(prod, brightness),
+            KeyboardTarget::All => {
+                self.set_brightness(brightness)?;
+                let secondary: Vec<AuraDevice> = self.secondary.iter().map(|k| k.prod).collect();
+                for prod in secondary {
+                    self.set_secondary_brightness(prod, brightness)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the stored brightness and mode of each secondary keyboard
+    pub(super) fn write_secondary_configs(&self) -> Result<(), RogError> {
+        for keyboard in &self.secondary {
+            if let Some(config) = self.config.secondary.get(<&str>::from(keyboard.prod)) {
+                keyboard
+                    .node
+                    .write_bytes(&aura_brightness_bytes(config.brightness as u8))?;
+                if let Some(effect) = config.builtins.get(&config.current_mode) {
+                    Self::write_secondary_mode(&keyboard.node, effect, config.colour_gamma)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Change only the speed of the current mode, keeping its colours and
+    /// direction. Errors with `NotSupported` if the mode has no speed.
+    pub(super) async fn set_speed(&mut self, speed: Speed) -> Result<(), RogError> {
+        let mode = self.config.current_mode;
+        if !AuraEffect::allowed_parameters(mode).speed {
+            return Err(RogError::NotSupported);
+        }
+        let mut effect = self
+            .config
+            .builtins
+            .get(&mode)
+            .cloned()
+            .unwrap_or_else(|| AuraEffect::default_with_mode(mode));
+        effect.speed = speed;
+        self.set_effect(effect).await
+    }
+
+    /// Set solid white at full brightness with all LED zones enabled, without
+    /// reading the config first so that this works even if the config is
+    /// corrupt. The config is only overwritten with this state if `save` is
+    /// true, otherwise a restart returns to the stored state.
+    pub(super) fn safe_state(&mut self, save: bool) -> Result<AuraEffect, RogError> {
+        self.thermal_effect_running.store(false, Ordering::SeqCst);
+        self.demo_running.store(false, Ordering::SeqCst);
+
+        let effect = AuraEffect {
+            mode: AuraModeNum::Static,
+            zone: AuraZone::None,
+            colour1: Colour {
+                r: 0xff,
+                g: 0xff,
+                b: 0xff,
+            },
+            ..Default::default()
+        };
+        let enabled = AuraPowerConfig::all_on(self.led_prod);
+        self.write_power_states(&enabled).map_err(|e| {
+            warn!("safe_state: could not enable LED zones: {e}");
+            e
+        })?;
+        self.write_mode(&effect).map_err(|e| {
+            warn!("safe_state: could not write the static white mode: {e}");
+            e
+        })?;
+        self.set_brightness(LedBrightness::High).map_err(|e| {
+            warn!("safe_state: could not set brightness: {e}");
+            e
+        })?;
+        // Kept in step with the hardware so later brightness changes start
+        // from the right level, it is only written to disk with `save`
+        self.config.brightness = LedBrightness::High;
+        self.idle_restore = None;
+        self.send_brightness();
+
+        if save {
+            self.config.enabled = enabled;
+            self.config.set_builtin(effect.clone());
+            self.config.try_write()?;
+        }
+        Ok(effect)
+    }
+
     /// Write an effect block. This is for per-key, but can be repurposed to
     /// write the raw factory mode packets - when doing this it is expected that
     /// only the first `Vec` (`effect[0]`) is valid.
     pub fn write_effect_block(&mut self, effect: &UsbPackets) -> Result<(), RogError> {
-        if self.config.brightness == LedBrightness::Off {
-            self.config.brightness = LedBrightness::Med;
-            self.config.write();
-        }
+        Self::validate_effect_block(effect)?;
+        self.check_rgb()?;
 
         let pkt_type = effect[0][1];
         const PER_KEY_TYPE: u8 = 0xbc;
 
+        let corrected;
+        let effect = if pkt_type == PER_KEY_TYPE && self.config.colour_gamma != GAMMA_NONE {
+            corrected = gamma_corrected_packets(effect, self.config.colour_gamma);
+            &corrected
+        } else {
+            effect
+        };
+
         if pkt_type != PER_KEY_TYPE {
             self.per_key_mode_active = false;
             if let LEDNode::Rog(hid_raw) = &self.led_node {
-                hid_raw.write_bytes(&effect[0])?;
+                // Zones are set together so that their effects start in phase
+                for row in effect.iter() {
+                    hid_raw.write_bytes(row)?;
+                }
                 hid_raw.write_bytes(&LED_SET)?;
                 // hid_raw.write_bytes(&LED_APPLY)?;
             }
@@ -273,45 +926,338 @@ impl CtrlKbdLed {
             }
             self.flip_effect_write = !self.flip_effect_write;
         }
+        if self.config.brightness == LedBrightness::Off {
+            self.config.brightness = LedBrightness::Med;
+            self.config.try_write()?;
+        }
         Ok(())
     }
 
-    pub(super) fn toggle_mode(&mut self, reverse: bool) -> Result<(), RogError> {
-        let current = self.config.current_mode;
-        if let Some(idx) = self
-            .supported_modes
-            .basic_modes
+    /// The per-zone effects for a breathe that is in phase across all zones.
+    /// Empty if the keyboard has no zones.
+    pub(super) fn synced_breathe_zones(&self, colour: Colour, speed: Speed) -> Vec<AuraEffect> {
+        self.supported_modes
+            .basic_zones
             .iter()
-            .position(|v| *v == current)
-        {
-            let mut idx = idx;
-            // goes past end of array
-            if reverse {
-                if idx == 0 {
-                    idx = self.supported_modes.basic_modes.len() - 1;
-                } else {
-                    idx -= 1;
-                }
-            } else {
-                idx += 1;
-                if idx == self.supported_modes.basic_modes.len() {
-                    idx = 0;
-                }
-            }
+            .map(|zone| AuraEffect {
+                mode: AuraModeNum::Breathe,
+                zone: *zone,
+                colour1: colour,
+                colour2: colour,
+                speed,
+                direction: Direction::default(),
+            })
+            .collect()
+    }
+
+    /// Set a single colour breathe on all zones, in phase. Keyboards without
+    /// zones, or that can't have them written together, get a single breathe
+    /// effect instead.
+    pub(super) async fn set_synced_breathe(
+        &mut self,
+        colour: Colour,
+        speed: Speed,
+    ) -> Result<(), RogError> {
+        let zones = self.synced_breathe_zones(colour, speed);
+        if zones.is_empty() || !matches!(self.led_node, LEDNode::Rog(_)) {
+            return self
+                .set_effect(AuraEffect {
+                    mode: AuraModeNum::Breathe,
+                    zone: AuraZone::None,
+                    colour1: colour,
+                    colour2: colour,
+                    speed,
+                    direction: Direction::default(),
+                })
+                .await;
+        }
+        for effect in &zones {
+            self.validate_effect(effect)?;
+        }
+
+        self.thermal_effect_running.store(false, Ordering::SeqCst);
+        self.demo_running.store(false, Ordering::SeqCst);
+        let packets: UsbPackets = zones.iter().map(Vec::from).collect();
+        self.write_effect_block(&packets)?;
+        self.config.read();
+        for effect in zones {
+            self.config.set_builtin(effect);
+        }
+        self.config.multizone_on = true;
+        if self.config.brightness == LedBrightness::Off {
+            self.config.brightness = LedBrightness::Med;
+        }
+        self.remember_profile_lighting();
+        self.set_brightness(self.config.brightness)?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Set the colour of the function row and media keys only. The rest of
+    /// the per-key colours are kept as they were last written.
+    pub fn set_fn_row_colour(&mut self, colour: Colour) -> Result<(), RogError> {
+        if self.supported_modes.advanced_type != AdvancedAuraType::PerKey {
+            return Err(RogError::NotSupported);
+        }
+        let mut buffer = self.per_key_buffer.clone();
+        for key in &self.fn_row {
+            buffer.set(*key, colour.r, colour.g, colour.b);
+        }
+        self.write_per_key_buffer(buffer)
+    }
+
+    /// Set the colour of a single key by its `LedCode` name. The rest of the
+    /// per-key colours are kept as they were last written.
+    pub fn set_key_colour(&mut self, key: &str, colour: Colour) -> Result<(), RogError> {
+        if self.supported_modes.advanced_type != AdvancedAuraType::PerKey {
+            return Err(RogError::NotSupported);
+        }
+        let code =
+            LedCode::from_name(key).ok_or_else(|| RogError::NotFound(format!("key {key}")))?;
+        let mut buffer = self.per_key_buffer.clone();
+        if !buffer.set(code, colour.r, colour.g, colour.b) {
+            return Err(RogError::NotFound(format!(
+                "key {key} is not addressable on this keyboard"
+            )));
+        }
+        self.write_per_key_buffer(buffer)
+    }
+
+    /// Set every key on a per-key keyboard from the map. Keys not in the map
+    /// are turned off.
+    pub fn set_keymap(&mut self, map: &KeyMap) -> Result<(), RogError> {
+        if self.supported_modes.advanced_type != AdvancedAuraType::PerKey {
+            return Err(RogError::NotSupported);
+        }
+        self.write_per_key_buffer(LedUsbPackets::from(map))
+    }
+
+    /// Write the per-key colours and keep them as the last written if the
+    /// write succeeds
+    fn write_per_key_buffer(&mut self, buffer: LedUsbPackets) -> Result<(), RogError> {
+        self.write_effect_block(&buffer.get())?;
+        self.per_key_buffer = buffer;
+        Ok(())
+    }
+
+    pub(super) async fn toggle_mode(&mut self, reverse: bool) -> Result<(), RogError> {
+        self.check_rgb()?;
+        let current = self.config.current_mode;
+        if let Some(idx) = self
+            .supported_modes
+            .basic_modes
+            .iter()
+            .position(|v| *v == current)
+        {
+            let mut idx = idx;
+            // goes past end of array
+            if reverse {
+                if idx == 0 {
+                    idx = self.supported_modes.basic_modes.len() - 1;
+                } else {
+                    idx -= 1;
+                }
+            } else {
+                idx += 1;
+                if idx == self.supported_modes.basic_modes.len() {
+                    idx = 0;
+                }
+            }
             let next = self.supported_modes.basic_modes[idx];
+            self.thermal_effect_running.store(false, Ordering::SeqCst);
+            self.demo_running.store(false, Ordering::SeqCst);
 
             self.config.read();
             // if self.config.builtins.contains_key(&next) {
             self.config.current_mode = next;
-            self.write_current_config_mode()?;
+            self.with_mode_fade(|ctrl| ctrl.write_current_config_mode())
+                .await?;
             // }
-            self.config.write();
+            self.config.try_write()?;
+            self.remember_profile_lighting();
+        }
+
+        Ok(())
+    }
+
+    /// Check that an effect can be applied to this keyboard without writing to
+    /// it. The error has the reason the effect is not valid. Colours and
+    /// speeds are always in range as they are bounded by their types.
+    pub(super) fn validate_effect(&self, effect: &AuraEffect) -> Result<(), RogError> {
+        if !self.supported_modes.basic_modes.contains(&effect.mode) {
+            return Err(RogError::AuraEffectInvalid(format!(
+                "mode {} is not supported by this keyboard, supported modes are: {}",
+                effect.mode,
+                self.supported_mode_list()
+            )));
+        }
+        self.check_zone(effect.zone)?;
+        if matches!(effect.direction, Direction::Up | Direction::Down)
+            && effect.mode != AuraModeNum::Rainbow
+        {
+            return Err(RogError::AuraEffectInvalid(format!(
+                "direction {:?} is only valid for the {} mode",
+                effect.direction,
+                AuraModeNum::Rainbow
+            )));
+        }
+        Ok(())
+    }
+
+    /// Errors with `NotSupported` on keyboards with only a brightness node, so
+    /// that mode changes fail clearly before anything is written
+    pub(super) fn check_rgb(&self) -> Result<(), RogError> {
+        if self.led_node.is_none() {
+            return Err(RogError::NotSupported);
+        }
+        Ok(())
+    }
+
+    /// The supported mode names as a comma separated list for error messages
+    fn supported_mode_list(&self) -> String {
+        let supported: Vec<&str> = self
+            .supported_modes
+            .basic_modes
+            .iter()
+            .map(<&str>::from)
+            .collect();
+        supported.join(", ")
+    }
+
+    /// Serialise the full config as JSON so that it can be backed up or moved
+    /// to another machine
+    pub(super) fn export_config(&self) -> Result<String, RogError> {
+        serde_json::to_string_pretty(&self.config)
+            .map_err(|e| RogError::AuraConfigInvalid(e.to_string()))
+    }
+
+    /// Parse and check a config from `export_config()`. Effects for modes this
+    /// keyboard does not have are dropped, but the current mode must be
+    /// supported and all remaining effects must be valid.
+    fn parse_config(&self, json: &str) -> Result<AuraConfig, RogError> {
+        let mut config: AuraConfig = serde_json::from_str(json)
+            .map_err(|e| RogError::AuraConfigInvalid(format!("could not parse: {e}")))?;
+        if !self
+            .supported_modes
+            .basic_modes
+            .contains(&config.current_mode)
+        {
+            return Err(RogError::AuraConfigInvalid(format!(
+                "current mode {} is not supported by this keyboard, supported modes are: {}",
+                config.current_mode,
+                self.supported_mode_list()
+            )));
+        }
+
+        let supported = &self.supported_modes.basic_modes;
+        config.builtins.retain(|mode, _| supported.contains(mode));
+        if let Some(multizones) = config.multizone.as_mut() {
+            multizones.retain(|mode, _| supported.contains(mode));
+        }
+        let effects = config
+            .builtins
+            .values()
+            .chain(config.multizone.iter().flat_map(|m| m.values().flatten()));
+        for effect in effects {
+            self.validate_effect(effect).map_err(|e| match e {
+                RogError::AuraEffectInvalid(reason) => RogError::AuraConfigInvalid(reason),
+                e => e,
+            })?;
+        }
+        Ok(config)
+    }
+
+    /// Replace the config with one from `export_config()`, save it, and apply
+    /// it to the keyboard
+    pub(super) fn import_config(&mut self, json: &str) -> Result<(), RogError> {
+        self.config = self.parse_config(json)?;
+        self.set_power_states()?;
+        self.write_current_config_mode()?;
+        self.set_brightness(self.config.brightness)?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Replace the LED config with the defaults for this keyboard, store it,
+    /// and write it to the keyboard. Other config files are not touched.
+    pub(super) fn reset_defaults(&mut self) -> Result<(), RogError> {
+        self.thermal_effect_running.store(false, Ordering::SeqCst);
+        self.demo_running.store(false, Ordering::SeqCst);
+        self.per_key_mode_active = false;
+        self.idle_restore = None;
+        self.config = AuraConfig::from_default_support(self.led_prod, &self.supported_modes);
+        self.set_power_states()?;
+        self.write_current_config_mode()?;
+        self.set_brightness(self.config.brightness)?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Load and apply the config file if it was edited outside of asusd.
+    /// Returns `false` if the file matches the loaded config, which is the
+    /// case after asusd writes it.
+    pub(super) fn reload_config(&mut self) -> Result<bool, RogError> {
+        let config = match self.config.read_if_changed()? {
+            Some(config) => config,
+            None => return Ok(false),
+        };
+        info!("Reloading {} after it was edited", self.config.file_name());
+        self.use_config(config);
+        self.set_power_states()?;
+        self.write_current_config_mode()?;
+        if !self.lid_off {
+            self.set_brightness(self.config.brightness)?;
         }
+        Ok(true)
+    }
+
+    /// Replace the config, stopping any running effects so that they do not
+    /// overwrite it. Nothing is written to the keyboard.
+    pub(super) fn use_config(&mut self, config: AuraConfig) {
+        self.thermal_effect_running.store(false, Ordering::SeqCst);
+        self.demo_running.store(false, Ordering::SeqCst);
+        self.per_key_mode_active = false;
+        self.idle_restore = None;
+        self.config = config;
+        self.config.sync_builtins(&self.supported_modes);
+    }
 
+    /// Check raw USB packets are long enough to be written before writing any
+    /// of them
+    fn validate_effect_block(effect: &UsbPackets) -> Result<(), RogError> {
+        if effect.is_empty() {
+            return Err(RogError::AuraEffectInvalid(
+                "no packets to write".to_owned(),
+            ));
+        }
+        if let Some((i, row)) = effect
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() < LED_MSG_LEN)
+        {
+            return Err(RogError::AuraEffectInvalid(format!(
+                "packet {i} is {} bytes, packets must be at least {LED_MSG_LEN} bytes",
+                row.len()
+            )));
+        }
         Ok(())
     }
 
-    fn write_mode(&mut self, mode: &AuraEffect) -> Result<(), RogError> {
+    /// Check the zone is one the keyboard has. `AuraZone::None` is always
+    /// valid as it addresses the whole keyboard.
+    fn check_zone(&self, zone: AuraZone) -> Result<(), RogError> {
+        if zone == AuraZone::None || self.supported_modes.basic_zones.contains(&zone) {
+            return Ok(());
+        }
+        Err(RogError::AuraEffectInvalid(format!(
+            "zone {:?} is not available on this keyboard, supported zones are: {:?}",
+            zone, self.supported_modes.basic_zones
+        )))
+    }
+
+    pub(super) fn write_mode(&mut self, mode: &AuraEffect) -> Result<(), RogError> {
+        self.check_zone(mode.zone)?;
+        let mode = &mode.with_gamma(self.config.colour_gamma);
         if let LEDNode::KbdLed(platform) = &self.led_node {
             let buf = [
                 1,
@@ -368,9 +1314,317 @@ impl CtrlKbdLed {
             }
         }
 
+        self.write_zone_brightness()
+    }
+
+    /// The effect shown on `zone` for the current mode
+    fn zone_effect(&self, zone: AuraZone) -> Option<AuraEffect> {
+        let mode = self.config.current_mode;
+        if self.config.multizone_on {
+            if let Some(set) = self.config.multizone.as_ref().and_then(|m| m.get(&mode)) {
+                return set.iter().find(|effect| effect.zone == zone).cloned();
+            }
+        }
+        self.config.builtins.get(&mode).map(|effect| AuraEffect {
+            zone,
+            ..effect.clone()
+        })
+    }
+
+    /// Write the current effect of each dimmed zone with its colours scaled to
+    /// the zone brightness. The keyboard brightness applies to every zone, so
+    /// this is the only way to dim a zone on its own.
+    fn write_zone_brightness(&mut self) -> Result<(), RogError> {
+        if !matches!(self.led_node, LEDNode::Rog(_)) {
+            return Ok(());
+        }
+        for (zone, level) in self.config.zone_brightness.clone() {
+            if level == LedBrightness::High || !self.supported_modes.basic_zones.contains(&zone) {
+                continue;
+            }
+            if let Some(effect) = self.zone_effect(zone) {
+                self.write_mode(&effect.with_brightness(level))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the brightness of a single zone, such as the lightbar. Errors with
+    /// `NotSupported` if the keyboard does not have the zone.
+    pub(super) fn set_zone_brightness(
+        &mut self,
+        zone: AuraZone,
+        level: LedBrightness,
+    ) -> Result<(), RogError> {
+        if zone == AuraZone::None
+            || !self.supported_modes.basic_zones.contains(&zone)
+            || !matches!(self.led_node, LEDNode::Rog(_))
+        {
+            return Err(RogError::NotSupported);
+        }
+        self.config.read();
+        self.config.zone_brightness.insert(zone, level);
+        self.write_current_config_mode()?;
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Set the per-channel `(r, g, b)` gamma correction and rewrite the current
+    /// mode with it. Each value must be above `0.0`, `1.0` is no change.
+    pub(super) fn set_colour_gamma(&mut self, gamma: (f32, f32, f32)) -> Result<(), RogError> {
+        for value in [gamma.0, gamma.1, gamma.2] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(RogError::AuraConfigInvalid(format!(
+                    "gamma {value} is not valid, it must be above 0.0"
+                )));
+            }
+        }
+        self.config.read();
+        self.config.colour_gamma = gamma;
+        // Per-key effects are corrected on their next write
+        if !self.per_key_mode_active {
+            self.write_current_config_mode()?;
+        }
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Use `colour` as the primary colour of every builtin mode that has one,
+    /// then rewrite the current mode
+    pub(super) fn set_global_colour(&mut self, colour: Colour) -> Result<(), RogError> {
+        self.check_rgb()?;
+        self.config.read();
+        let changed = self.config.set_global_colour(colour);
+        debug!("Global colour set for modes {changed:?}");
+        if !self.per_key_mode_active {
+            self.write_current_config_mode()?;
+        }
+        self.config.try_write()?;
+        Ok(())
+    }
+
+    /// Remove the per-zone effects for a mode, reverting it to the standard
+    /// builtin. Re-applies if it is the active mode. Returns `false` if the
+    /// mode had no multizone data.
+    pub(super) fn clear_multizone(&mut self, mode: AuraModeNum) -> Result<bool, RogError> {
+        self.config.read();
+        if !self.config.clear_multizone(mode) {
+            return Ok(false);
+        }
+        if mode == self.config.current_mode {
+            self.write_current_config_mode()?;
+        }
+        self.config.try_write()?;
+        Ok(true)
+    }
+
+    /// Return the supported modes paired with their human readable name
+    pub(super) fn mode_names(&self) -> Vec<(AuraModeNum, String)> {
+        self.supported_modes
+            .basic_modes
+            .iter()
+            .map(|m| (*m, String::from(*m)))
+            .collect()
+    }
+
+    /// The `AuraEffect` fields each supported mode uses
+    pub(super) fn mode_capabilities(&self) -> BTreeMap<AuraModeNum, AuraParameters> {
+        self.supported_modes
+            .basic_modes
+            .iter()
+            .map(|m| (*m, AuraEffect::allowed_parameters(*m)))
+            .collect()
+    }
+
+    /// Find a supported mode by its name. The name is case-insensitive.
+    pub(super) fn mode_from_name(&self, name: &str) -> Result<AuraModeNum, RogError> {
+        let names = self.mode_names();
+        if let Some((mode, _)) = names
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name.trim()))
+        {
+            return Ok(*mode);
+        }
+        let valid: Vec<String> = names.into_iter().map(|(_, n)| n).collect();
+        Err(RogError::AuraModeNotFound(name.to_owned(), valid))
+    }
+
+    /// Write a transient static colour mapped from the temperature. This is
+    /// not stored in the config.
+    pub(super) fn write_thermal_effect(&mut self, temp: u8) -> Result<(), RogError> {
+        let effect = AuraEffect {
+            mode: AuraModeNum::Static,
+            colour1: self.config.thermal_effect.colour_for(temp),
+            ..Default::default()
+        };
+        self.write_mode(&effect)
+    }
+
+    /// The effects shown by the demo, one per supported mode in order, each
+    /// with the default colours
+    pub(super) fn demo_sequence(&self) -> Vec<AuraEffect> {
+        self.supported_modes
+            .basic_modes
+            .iter()
+            .map(|m| AuraEffect::default_with_mode(*m))
+            .collect()
+    }
+
+    /// Write the stored mode and power states in the order set by
+    /// `restore_order`. A power state failure is only logged.
+    pub(super) fn restore_mode_and_power(&mut self) -> Result<(), RogError> {
+        let power_first = self.config.restore_order == RestoreOrder::PowerFirst;
+        if power_first {
+            self.set_power_states().map_err(|err| warn!("{err}")).ok();
+        }
+        self.write_current_config_mode()?;
+        if !power_first {
+            self.set_power_states().map_err(|err| warn!("{err}")).ok();
+        }
+        Ok(())
+    }
+
+    /// Turn the backlight off on lid close if `leds_off_on_lid_close` is set.
+    /// On open the stored brightness and mode are written back. The config
+    /// is not changed.
+    pub(super) fn lid_change(&mut self, lid_closed: bool) -> Result<(), RogError> {
+        if lid_closed {
+            if self.config.leds_off_on_lid_close && !self.lid_off {
+                debug!("Lid closed, turning keyboard backlight off");
+                self.set_brightness(LedBrightness::Off)?;
+                self.lid_off = true;
+            }
+        } else if self.lid_off {
+            debug!("Lid opened, restoring keyboard backlight");
+            self.lid_off = false;
+            self.set_brightness(self.config.brightness)?;
+            self.restore_mode_and_power()?;
+        }
         Ok(())
     }
 
+    /// Re-write the stored mode and power states unless a software or per-key
+    /// effect is showing, which the stored mode would replace. Returns `false`
+    /// if it was skipped.
+    pub(super) fn keepalive(&mut self) -> Result<bool, RogError> {
+        if self.per_key_mode_active
+            || self.thermal_effect_running.load(Ordering::SeqCst)
+            || self.demo_running.load(Ordering::SeqCst)
+        {
+            return Ok(false);
+        }
+        self.restore_mode_and_power()?;
+        Ok(true)
+    }
+
+    /// Restore the stored effect and brightness after a transient effect such
+    /// as the demo
+    pub(super) fn restore_config_mode(&mut self) -> Result<(), RogError> {
+        self.write_current_config_mode()?;
+        self.set_brightness(self.config.brightness)
+    }
+
+    pub(super) fn mark_change(&mut self, source: ChangeSource) {
+        debug!("Keyboard lighting changed by {}", <&str>::from(source));
+        self.last_change = Some((SystemTime::now(), source));
+    }
+
+    /// The time in seconds since the UNIX epoch and the source of the last
+    /// lighting change
+    pub(super) fn last_change(&self) -> (u64, String) {
+        match self.last_change {
+            Some((time, source)) => (
+                time.duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+                <&str>::from(source).to_owned(),
+            ),
+            None => (0, "none".to_owned()),
+        }
+    }
+
+    /// The active platform profile, if the platform driver is available
+    pub(super) fn active_platform_profile(&self) -> Option<Profile> {
+        self.platform.as_ref().and_then(active_platform_profile)
+    }
+
+    /// Store the current lighting against the active platform profile so that
+    /// it is restored when switching back to that profile
+    pub(super) fn remember_profile_lighting(&mut self) {
+        if !self.config.follows_profile {
+            return;
+        }
+        if let Some(profile) = self.active_platform_profile() {
+            if self.config.remember_profile_lighting(profile) {
+                self.config.write();
+            }
+        }
+    }
+
+    /// Apply the brightness, mode, and multizone state mapped to the platform
+    /// profile. Returns `false` if following profiles is disabled or the
+    /// profile has no mapping.
+    pub(super) fn apply_profile_lighting(&mut self, profile: Profile) -> Result<bool, RogError> {
+        let lighting = match self.config.lighting_for_profile(profile) {
+            Some(lighting) => lighting,
+            None => return Ok(false),
+        };
+        if !self.supported_modes.basic_modes.contains(&lighting.mode) {
+            warn!(
+                "Lighting for {profile} uses {} which this keyboard does not support",
+                <&str>::from(&lighting.mode)
+            );
+            return Err(RogError::AuraEffectNotSupported);
+        }
+        info!("Applying lighting for platform profile {profile}");
+        self.config.brightness = lighting.brightness;
+        self.config.current_mode = lighting.mode;
+        self.config.multizone_on = lighting.multizone_on;
+        self.write_current_config_mode()?;
+        self.set_brightness(lighting.brightness)?;
+        self.config.try_write()?;
+        Ok(true)
+    }
+
+    /// Build the sequence of effects used to blink a single zone so the user
+    /// can identify which physical region it is. Each step is expected to be
+    /// shown for `IDENTIFY_BLINK_MS`, followed by `write_current_config_mode`
+    /// to restore the whole keyboard.
+    pub(super) fn identify_zone_sequence(
+        &self,
+        zone: AuraZone,
+        seconds: u8,
+    ) -> Result<Vec<AuraEffect>, RogError> {
+        if self.supported_modes.basic_zones.is_empty() {
+            return Err(RogError::NotSupported);
+        }
+        if !self.supported_modes.basic_zones.contains(&zone) {
+            return Err(RogError::AuraEffectNotSupported);
+        }
+
+        let blink = |colour1: Colour| AuraEffect {
+            mode: AuraModeNum::Static,
+            zone,
+            colour1,
+            ..Default::default()
+        };
+        let steps = (seconds as u64 * 1000 / IDENTIFY_BLINK_MS).max(2);
+        let sequence: Vec<AuraEffect> = (0..steps)
+            .map(|i| {
+                if i % 2 == 0 {
+                    blink(Colour {
+                        r: 0xff,
+                        g: 0xff,
+                        b: 0xff,
+                    })
+                } else {
+                    blink(Colour { r: 0, g: 0, b: 0 })
+                }
+            })
+            .collect();
+        Ok(sequence)
+    }
+
     /// Create a default for the `current_mode` if multizone and no config
     /// exists.
     fn create_multizone_default(&mut self) -> Result<(), RogError> {
@@ -402,82 +1656,394 @@ impl CtrlKbdLed {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::Ordering;
+
+    use rog_aura::advanced::{LedCode, LedUsbPackets};
     use rog_aura::aura_detection::{LaptopLedData, PowerZones};
-    use rog_aura::usb::AuraDevice;
-    use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour};
+    use rog_aura::layouts::KeyLayout;
+    use rog_aura::usb::{AuraDevice, KeyboardTarget};
+    use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, LedBrightness, Speed};
+    use rog_platform::hid_raw::HidRaw;
     use rog_platform::keyboard_led::KeyboardLed;
+    use tokio::sync::watch;
 
-    use super::CtrlKbdLed;
+    use super::{
+        fade_steps, gamma_corrected_packets, random_effect_for, ChangeSource, CtrlKbdLed, EffectRng,
+    };
     use crate::ctrl_aura::config::AuraConfig;
     use crate::ctrl_aura::controller::LEDNode;
+    use crate::error::RogError;
+    use crate::Devices;
+
+    /// A controller with no keyboard nodes, for testing the logic around them
+    fn test_controller(supported_modes: LaptopLedData, config: AuraConfig) -> CtrlKbdLed {
+        CtrlKbdLed {
+            led_prod: AuraDevice::X19b6,
+            led_node: LEDNode::None,
+            kd_brightness: KeyboardLed::default(),
+            supported_modes,
+            flip_effect_write: false,
+            per_key_mode_active: false,
+            per_key_buffer: LedUsbPackets::new_per_key(),
+            fn_row: KeyLayout::default_layout().function_row(),
+            thermal_effect_running: Default::default(),
+            demo_running: Default::default(),
+            flash_lock: Default::default(),
+            last_change: None,
+            idle_restore: None,
+            lid_off: false,
+            secondary: Vec::new(),
+            brightness_tx: watch::channel(LedBrightness::Med).0,
+            platform: None,
+            devices: Devices::Hardware,
+            config,
+        }
+    }
 
     #[test]
-    // #[ignore = "Must be manually run due to detection stage"]
-    fn check_set_mode_errors() {
-        // Checking to ensure set_mode errors when unsupported modes are tried
+    fn zone_brightness_needs_zone() {
         let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
         let supported_modes = LaptopLedData {
             board_name: String::new(),
             layout_name: "ga401".to_owned(),
             basic_modes: vec![AuraModeNum::Static],
-            basic_zones: vec![],
+            basic_zones: vec![AuraZone::BarLeft, AuraZone::BarRight],
             advanced_type: rog_aura::AdvancedAuraType::None,
-            power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+            power_zones: vec![PowerZones::Keyboard, PowerZones::Lightbar],
         };
-        let mut controller = CtrlKbdLed {
-            led_prod: AuraDevice::X19b6,
-            led_node: LEDNode::None,
-            kd_brightness: KeyboardLed::default(),
-            supported_modes,
-            flip_effect_write: false,
-            per_key_mode_active: false,
-            config,
+        let mut controller = test_controller(supported_modes, config);
+
+        for zone in [AuraZone::None, AuraZone::Key1, AuraZone::Logo] {
+            assert!(matches!(
+                controller.set_zone_brightness(zone, LedBrightness::Low),
+                Err(RogError::NotSupported)
+            ));
+        }
+        // Only ROG USB keyboards can write zones
+        assert!(matches!(
+            controller.set_zone_brightness(AuraZone::BarLeft, LedBrightness::Low),
+            Err(RogError::NotSupported)
+        ));
+        assert!(controller.config.zone_brightness.is_empty());
+    }
+
+    #[tokio::test]
+    async fn zone_brightness_kept_after_effect() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config
+            .zone_brightness
+            .insert(AuraZone::BarLeft, LedBrightness::Low);
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![AuraZone::BarLeft, AuraZone::BarRight],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard, PowerZones::Lightbar],
         };
+        let mut controller = test_controller(supported_modes, config);
+        let hid = HidRaw::simulated("19b6");
+        let sim = hid.simulation().unwrap().clone();
+        controller.led_node = LEDNode::Rog(hid);
+        controller.kd_brightness = KeyboardLed::simulated();
 
-        let mut effect = AuraEffect {
+        let effect = AuraEffect {
+            mode: AuraModeNum::Static,
             colour1: Colour {
                 r: 0xff,
-                g: 0x00,
+                g: 0xff,
                 b: 0xff,
             },
             zone: AuraZone::None,
             ..Default::default()
         };
+        controller.write_effect(effect).await.unwrap();
 
-        // This error comes from write_bytes because we don't have a keyboard node
-        // stored
+        // The lightbar zone is written dimmed after the whole keyboard
+        let writes = sim.written_bytes();
+        let mode_write = |zone: AuraZone| {
+            writes
+                .iter()
+                .rposition(|w| w[1] == 0xb3 && w[2] == zone as u8)
+                .unwrap()
+        };
+        let bar_left = mode_write(AuraZone::BarLeft);
+        assert!(bar_left > mode_write(AuraZone::None));
+        assert!(writes[bar_left][4] < 0xff);
         assert_eq!(
-            controller
-                .set_effect(effect.clone())
-                .unwrap_err()
-                .to_string(),
-            "No supported Aura keyboard"
+            controller.config.zone_brightness.get(&AuraZone::BarLeft),
+            Some(&LedBrightness::Low)
         );
+    }
 
-        effect.mode = AuraModeNum::Laser;
+    #[test]
+    fn fade_steps_between_levels() {
         assert_eq!(
-            controller
-                .set_effect(effect.clone())
-                .unwrap_err()
-                .to_string(),
-            "Aura effect not supported"
+            fade_steps(LedBrightness::High, LedBrightness::Off),
+            vec![LedBrightness::Med, LedBrightness::Low, LedBrightness::Off]
+        );
+        assert_eq!(
+            fade_steps(LedBrightness::Off, LedBrightness::Med),
+            vec![LedBrightness::Low, LedBrightness::Med]
         );
+        assert!(fade_steps(LedBrightness::Low, LedBrightness::Low).is_empty());
+    }
 
-        effect.mode = AuraModeNum::Static;
-        effect.zone = AuraZone::Key2;
+    #[tokio::test]
+    async fn keyboard_targets() {
+        let supported_modes = LaptopLedData {
+            basic_modes: vec![AuraModeNum::Static],
+            ..Default::default()
+        };
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &supported_modes);
+        let mut controller = test_controller(supported_modes, config);
+        assert_eq!(controller.keyboards(), vec![AuraDevice::X19b6]);
+
+        let effect = AuraEffect::default_with_mode(AuraModeNum::Static);
         assert_eq!(
             controller
-                .set_effect(effect.clone())
+                .set_effect_for(KeyboardTarget::Product(AuraDevice::X1866), effect)
+                .await
                 .unwrap_err()
                 .to_string(),
-            "Aura effect not supported"
+            "Not found: keyboard 1866"
+        );
+        assert!(matches!(
+            controller.set_brightness_for(
+                KeyboardTarget::Product(AuraDevice::X1866),
+                LedBrightness::Low
+            ),
+            Err(RogError::NotFound(_))
+        ));
+        // With no secondary keyboards stored there is nothing to write
+        assert!(controller.write_secondary_configs().is_ok());
+        assert!(controller.config.secondary.is_empty());
+
+        // The primary here is brightness-only, so only a secondary keyboard
+        // would change with `All`
+        assert!(controller.targets_primary(KeyboardTarget::Primary));
+        assert!(controller.targets_primary(KeyboardTarget::Product(AuraDevice::X19b6)));
+        assert!(!controller.targets_primary(KeyboardTarget::Product(AuraDevice::X1866)));
+        assert!(!controller.targets_primary(KeyboardTarget::All));
+        // With nothing else to set the primary's error is returned
+        assert!(matches!(
+            controller
+                .set_effect_for(
+                    KeyboardTarget::All,
+                    AuraEffect::default_with_mode(AuraModeNum::Static)
+                )
+                .await,
+            Err(RogError::NotSupported)
+        ));
+    }
+
+    #[test]
+    fn random_effect_is_valid() {
+        let modes = [AuraModeNum::Static, AuraModeNum::Rainbow, AuraModeNum::Star];
+        assert!(random_effect_for(&[], &mut EffectRng::new(1)).is_none());
+        for seed in 0..200 {
+            let effect = random_effect_for(&modes, &mut EffectRng::new(seed)).unwrap();
+            assert!(modes.contains(&effect.mode));
+            assert_eq!(effect.zone, AuraZone::None);
+            if effect.mode != AuraModeNum::Rainbow {
+                assert!(matches!(
+                    effect.direction,
+                    Direction::Left | Direction::Right
+                ));
+            }
+            let allowed = AuraEffect::allowed_parameters(effect.mode);
+            let default = AuraEffect::default_with_mode(effect.mode);
+            if !allowed.colour2 {
+                assert_eq!(effect.colour2, default.colour2);
+            }
+            if !allowed.speed {
+                assert_eq!(effect.speed, default.speed);
+            }
+            // The same seed gives the same effect
+            let again = random_effect_for(&modes, &mut EffectRng::new(seed)).unwrap();
+            assert_eq!(again.mode, effect.mode);
+            assert_eq!(again.colour1, effect.colour1);
+            assert_eq!(again.colour2, effect.colour2);
+            assert_eq!(again.speed, effect.speed);
+            assert_eq!(again.direction, effect.direction);
+        }
+    }
+
+    #[test]
+    fn gamma_leaves_packet_header() {
+        let mut packets = LedUsbPackets::new_per_key();
+        packets.set(LedCode::VolDown, 128, 128, 128);
+        packets.set(LedCode::Esc, 255, 0, 128);
+        let raw = packets.get();
+
+        let corrected = gamma_corrected_packets(&raw, (2.0, 1.0, 0.5));
+        for (row, corrected) in raw.iter().zip(corrected.iter()) {
+            assert_eq!(row[..9], corrected[..9]);
+        }
+        // VolDown is row 0 col 15, Esc is row 1 col 24
+        assert_eq!(corrected[0][15..18], [64, 128, 181]);
+        assert_eq!(corrected[1][24..27], [255, 0, 181]);
+        assert_eq!(gamma_corrected_packets(&raw, rog_aura::GAMMA_NONE), raw);
+    }
+
+    #[tokio::test]
+    // #[ignore = "Must be manually run due to detection stage"]
+    async fn check_set_mode_errors() {
+        // Checking to ensure set_mode errors when unsupported modes are tried
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+        };
+        let mut controller = test_controller(supported_modes, config);
+
+        let mut effect = AuraEffect {
+            colour1: Colour {
+                r: 0xff,
+                g: 0x00,
+                b: 0xff,
+            },
+            zone: AuraZone::None,
+            ..Default::default()
+        };
+
+        // Valid effects are refused as there is no keyboard node, only brightness
+        assert!(matches!(
+            controller.set_effect(effect.clone()).await,
+            Err(RogError::NotSupported)
+        ));
+
+        effect.mode = AuraModeNum::Laser;
+        assert_eq!(
+            controller
+                .set_effect(effect.clone())
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Aura effect not valid: mode Laser is not supported by this keyboard, supported \
+             modes are: Static"
+        );
+
+        effect.mode = AuraModeNum::Static;
+        effect.zone = AuraZone::Key2;
+        assert_eq!(
+            controller
+                .set_effect(effect.clone())
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Aura effect not valid: zone Key2 is not available on this keyboard, supported zones \
+             are: []"
         );
 
         controller.supported_modes.basic_zones.push(AuraZone::Key2);
+        assert!(matches!(
+            controller.set_effect(effect).await,
+            Err(RogError::NotSupported)
+        ));
+        assert!(matches!(
+            controller.toggle_mode(false).await,
+            Err(RogError::NotSupported)
+        ));
+        assert!(matches!(
+            controller.write_effect_block(&LedUsbPackets::new_per_key().get()),
+            Err(RogError::NotSupported)
+        ));
+    }
+
+    #[test]
+    fn demo_cycles_supported_modes() {
+        let mut config =
+            AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        config.current_mode = AuraModeNum::Breathe;
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+                AuraModeNum::Pulse,
+            ],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let controller = test_controller(supported_modes, config);
+
+        let modes: Vec<AuraModeNum> = controller.demo_sequence().iter().map(|e| e.mode).collect();
         assert_eq!(
-            controller.set_effect(effect).unwrap_err().to_string(),
+            modes,
+            vec![
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+                AuraModeNum::Pulse
+            ]
+        );
+        // The demo must not touch the stored mode that is restored after
+        assert_eq!(controller.config.current_mode, AuraModeNum::Breathe);
+    }
+
+    #[test]
+    fn write_mode_checks_zone() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let mut controller = test_controller(supported_modes, config);
+        let mut effect = AuraEffect::default();
+
+        // Single zone keyboard only accepts the whole keyboard
+        assert_eq!(
+            controller.write_mode(&effect).unwrap_err().to_string(),
             "No supported Aura keyboard"
         );
+        for zone in [AuraZone::Key1, AuraZone::Key4, AuraZone::Logo] {
+            effect.zone = zone;
+            assert!(controller
+                .write_mode(&effect)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Aura effect not valid: zone"));
+        }
+
+        // Four zone keyboard
+        controller.supported_modes.basic_zones = vec![
+            AuraZone::Key1,
+            AuraZone::Key2,
+            AuraZone::Key3,
+            AuraZone::Key4,
+        ];
+        for zone in [
+            AuraZone::None,
+            AuraZone::Key1,
+            AuraZone::Key2,
+            AuraZone::Key3,
+            AuraZone::Key4,
+        ] {
+            effect.zone = zone;
+            assert_eq!(
+                controller.write_mode(&effect).unwrap_err().to_string(),
+                "No supported Aura keyboard"
+            );
+        }
+        for zone in [AuraZone::Logo, AuraZone::BarLeft, AuraZone::BarRight] {
+            effect.zone = zone;
+            assert!(controller
+                .write_mode(&effect)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Aura effect not valid: zone"));
+        }
     }
 
     #[test]
@@ -492,15 +2058,7 @@ mod tests {
             advanced_type: rog_aura::AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
         };
-        let mut controller = CtrlKbdLed {
-            led_prod: AuraDevice::X19b6,
-            led_node: LEDNode::None,
-            kd_brightness: KeyboardLed::default(),
-            supported_modes,
-            flip_effect_write: false,
-            per_key_mode_active: false,
-            config,
-        };
+        let mut controller = test_controller(supported_modes, config);
 
         assert!(controller.config.multizone.is_none());
         assert!(controller.create_multizone_default().is_err());
@@ -531,15 +2089,7 @@ mod tests {
             advanced_type: rog_aura::AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
         };
-        let mut controller = CtrlKbdLed {
-            led_prod: AuraDevice::X19b6,
-            led_node: LEDNode::None,
-            kd_brightness: KeyboardLed::default(),
-            supported_modes,
-            flip_effect_write: false,
-            per_key_mode_active: false,
-            config,
-        };
+        let mut controller = test_controller(supported_modes, config);
 
         assert!(controller.config.multizone.is_none());
         controller.config.multizone_on = true;
@@ -561,4 +2111,410 @@ mod tests {
         assert_eq!(e[0].zone, AuraZone::Key1);
         assert_eq!(e[1].zone, AuraZone::Key2);
     }
+
+    #[test]
+    fn identify_zone_blinks() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+        };
+        let mut controller = test_controller(supported_modes, config);
+
+        // Not a multizone keyboard
+        assert_eq!(
+            controller
+                .identify_zone_sequence(AuraZone::Key1, 1)
+                .unwrap_err()
+                .to_string(),
+            "Not supported"
+        );
+
+        controller.supported_modes.basic_zones.push(AuraZone::Key1);
+        controller.supported_modes.basic_zones.push(AuraZone::Key2);
+        controller.config.multizone_on = true;
+        controller.create_multizone_default().unwrap();
+
+        let seq = controller
+            .identify_zone_sequence(AuraZone::Key2, 1)
+            .unwrap();
+        assert_eq!(seq.len(), 4);
+        for (i, step) in seq.iter().enumerate() {
+            assert_eq!(step.zone, AuraZone::Key2);
+            assert_eq!(step.mode, AuraModeNum::Static);
+            if i % 2 == 0 {
+                assert_eq!(
+                    step.colour1,
+                    Colour {
+                        r: 0xff,
+                        g: 0xff,
+                        b: 0xff
+                    }
+                );
+            } else {
+                assert_eq!(step.colour1, Colour { r: 0, g: 0, b: 0 });
+            }
+        }
+        // Writing the sequence fails without a keyboard node
+        assert_eq!(
+            controller.write_mode(&seq[0]).unwrap_err().to_string(),
+            "No supported Aura keyboard"
+        );
+    }
+
+    #[test]
+    fn mode_from_name() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static, AuraModeNum::Rainbow, AuraModeNum::Star],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+        };
+        let controller = test_controller(supported_modes, config);
+
+        assert_eq!(
+            controller.mode_from_name("static").unwrap(),
+            AuraModeNum::Static
+        );
+        assert_eq!(
+            controller.mode_from_name("RAINBOW").unwrap(),
+            AuraModeNum::Rainbow
+        );
+        assert_eq!(
+            controller.mode_from_name("Stars").unwrap(),
+            AuraModeNum::Star
+        );
+        // Exists but is not supported on this laptop
+        assert_eq!(
+            controller.mode_from_name("laser").unwrap_err().to_string(),
+            "Not found: mode laser, valid modes are: Static, Rainbow, Stars"
+        );
+        assert!(matches!(
+            controller.mode_from_name("laser"),
+            Err(RogError::AuraModeNotFound(..))
+        ));
+    }
+
+    #[test]
+    fn fn_row_colour_only_sets_fn_row() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard, PowerZones::RearGlow],
+        };
+        let mut controller = test_controller(supported_modes, config);
+        let colour = Colour {
+            r: 0x11,
+            g: 0x22,
+            b: 0x33,
+        };
+
+        assert_eq!(
+            controller
+                .set_fn_row_colour(colour)
+                .unwrap_err()
+                .to_string(),
+            "Not supported"
+        );
+
+        controller.supported_modes.advanced_type = rog_aura::AdvancedAuraType::PerKey;
+        // A key set previously must be kept
+        controller.per_key_buffer.set(LedCode::A, 0xff, 0xff, 0xff);
+        let mut expected = LedUsbPackets::new_per_key();
+        expected.set(LedCode::A, 0xff, 0xff, 0xff);
+        // The write is refused as there is no keyboard node, so the buffer is
+        // unchanged
+        assert!(matches!(
+            controller.set_fn_row_colour(colour),
+            Err(RogError::NotSupported)
+        ));
+        assert!(matches!(
+            controller.set_key_colour("enter", colour),
+            Err(RogError::NotSupported)
+        ));
+        assert_eq!(controller.per_key_buffer.get(), expected.get());
+
+        controller.led_node = LEDNode::Rog(HidRaw::simulated("19b6"));
+        controller.set_fn_row_colour(colour).unwrap();
+        for key in KeyLayout::default_layout().function_row() {
+            expected.set(key, 0x11, 0x22, 0x33);
+        }
+        assert_eq!(controller.per_key_buffer.get(), expected.get());
+
+        let pkt = controller.per_key_buffer.get();
+        assert_eq!(&pkt[1][24..27], &[0x11, 0x22, 0x33]); // Esc
+        assert_eq!(&pkt[2][21..24], &[0x11, 0x22, 0x33]); // F12
+        assert_eq!(&pkt[2][24..27], &[0, 0, 0]); // Del
+        assert_eq!(&pkt[5][24..27], &[0xff, 0xff, 0xff]); // A
+
+        controller.set_key_colour("enter", colour).unwrap();
+        controller
+            .set_key_colour("W", Colour { r: 1, g: 2, b: 3 })
+            .unwrap();
+        expected.set(LedCode::Return, 0x11, 0x22, 0x33);
+        expected.set(LedCode::W, 1, 2, 3);
+        assert_eq!(controller.per_key_buffer.get(), expected.get());
+
+        assert_eq!(
+            controller
+                .set_key_colour("Hyper", colour)
+                .unwrap_err()
+                .to_string(),
+            "Not found: key Hyper"
+        );
+        assert!(controller.set_key_colour("Spacebar", colour).is_err());
+        assert_eq!(controller.per_key_buffer.get(), expected.get());
+    }
+
+    #[test]
+    fn last_change_source() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let mut controller = test_controller(LaptopLedData::default(), config);
+        assert_eq!(controller.last_change(), (0, "none".to_owned()));
+
+        controller.mark_change(ChangeSource::Resume);
+        let (time, source) = controller.last_change();
+        assert!(time > 0);
+        assert_eq!(source, "resume");
+    }
+
+    #[test]
+    fn validate_effect_reasons() {
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static, AuraModeNum::Rainbow],
+            basic_zones: vec![AuraZone::Key1, AuraZone::Key2],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &supported_modes);
+        let controller = test_controller(supported_modes, config);
+
+        let mut effect = AuraEffect::default();
+        assert!(controller.validate_effect(&effect).is_ok());
+
+        effect.mode = AuraModeNum::Strobe;
+        assert_eq!(
+            controller.validate_effect(&effect).unwrap_err().to_string(),
+            "Aura effect not valid: mode Strobe is not supported by this keyboard, supported \
+             modes are: Static, Rainbow"
+        );
+
+        effect.mode = AuraModeNum::Static;
+        effect.zone = AuraZone::Logo;
+        assert_eq!(
+            controller.validate_effect(&effect).unwrap_err().to_string(),
+            "Aura effect not valid: zone Logo is not available on this keyboard, supported zones \
+             are: [Key1, Key2]"
+        );
+
+        effect.zone = AuraZone::Key2;
+        effect.direction = Direction::Up;
+        assert_eq!(
+            controller.validate_effect(&effect).unwrap_err().to_string(),
+            "Aura effect not valid: direction Up is only valid for the Rainbow mode"
+        );
+        effect.mode = AuraModeNum::Rainbow;
+        assert!(controller.validate_effect(&effect).is_ok());
+    }
+
+    #[test]
+    fn validate_effect_block_lengths() {
+        assert_eq!(
+            CtrlKbdLed::validate_effect_block(&vec![])
+                .unwrap_err()
+                .to_string(),
+            "Aura effect not valid: no packets to write"
+        );
+        assert_eq!(
+            CtrlKbdLed::validate_effect_block(&vec![vec![0; 64], vec![0; 8]])
+                .unwrap_err()
+                .to_string(),
+            "Aura effect not valid: packet 1 is 8 bytes, packets must be at least 17 bytes"
+        );
+        assert!(CtrlKbdLed::validate_effect_block(&LedUsbPackets::new_per_key().get()).is_ok());
+    }
+
+    #[test]
+    fn synced_breathe_zones_match() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let mut controller = test_controller(LaptopLedData::default(), config);
+        let colour = Colour {
+            r: 0xff,
+            g: 0x00,
+            b: 0x40,
+        };
+        assert!(controller
+            .synced_breathe_zones(colour, Speed::Low)
+            .is_empty());
+
+        controller.supported_modes.basic_zones = vec![
+            AuraZone::Key1,
+            AuraZone::Key2,
+            AuraZone::Key3,
+            AuraZone::Key4,
+        ];
+        let zones = controller.synced_breathe_zones(colour, Speed::Low);
+        assert_eq!(zones.len(), 4);
+        for (effect, zone) in zones.iter().zip(&controller.supported_modes.basic_zones) {
+            assert_eq!(effect.zone, *zone);
+            assert_eq!(effect.mode, AuraModeNum::Breathe);
+            assert_eq!(effect.colour1, colour);
+            assert_eq!(effect.colour2, colour);
+            assert_eq!(effect.speed, Speed::Low);
+            assert_eq!(effect.direction, zones[0].direction);
+        }
+    }
+
+    #[test]
+    fn import_config_checks_modes() {
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static, AuraModeNum::Rainbow],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let all_modes = LaptopLedData {
+            basic_modes: vec![
+                AuraModeNum::Static,
+                AuraModeNum::Rainbow,
+                AuraModeNum::Strobe,
+            ],
+            ..supported_modes.clone()
+        };
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &supported_modes);
+        let controller = test_controller(supported_modes, config);
+
+        let json = controller.export_config().unwrap();
+        let parsed = controller.parse_config(&json).unwrap();
+        assert_eq!(parsed.current_mode, controller.config.current_mode);
+        assert_eq!(parsed.builtins.len(), 2);
+
+        // Modes from another machine are dropped
+        let mut other = AuraConfig::from_default_support(AuraDevice::X19b6, &all_modes);
+        let parsed = controller
+            .parse_config(&serde_json::to_string(&other).unwrap())
+            .unwrap();
+        assert!(!parsed.builtins.contains_key(&AuraModeNum::Strobe));
+
+        other.current_mode = AuraModeNum::Strobe;
+        assert_eq!(
+            controller
+                .parse_config(&serde_json::to_string(&other).unwrap())
+                .unwrap_err()
+                .to_string(),
+            "Aura config not valid: current mode Strobe is not supported by this keyboard, \
+             supported modes are: Static, Rainbow"
+        );
+        assert!(matches!(
+            controller.parse_config("{"),
+            Err(RogError::AuraConfigInvalid(_))
+        ));
+    }
+
+    #[test]
+    fn keepalive_skips_transient_effects() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let mut controller = test_controller(supported_modes, config);
+
+        assert!(controller.keepalive().unwrap());
+
+        controller.demo_running.store(true, Ordering::SeqCst);
+        assert!(!controller.keepalive().unwrap());
+        controller.demo_running.store(false, Ordering::SeqCst);
+
+        controller
+            .thermal_effect_running
+            .store(true, Ordering::SeqCst);
+        assert!(!controller.keepalive().unwrap());
+        controller
+            .thermal_effect_running
+            .store(false, Ordering::SeqCst);
+
+        controller.per_key_mode_active = true;
+        assert!(!controller.keepalive().unwrap());
+
+        controller.per_key_mode_active = false;
+        assert!(controller.keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn set_speed_only_for_modes_with_speed() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static, AuraModeNum::Breathe],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let mut controller = test_controller(supported_modes, config);
+
+        controller.config.current_mode = AuraModeNum::Static;
+        assert!(matches!(
+            controller.set_speed(Speed::High).await,
+            Err(RogError::NotSupported)
+        ));
+
+        // Gets as far as setting the effect, which is refused as there is no
+        // keyboard node
+        controller.config.current_mode = AuraModeNum::Breathe;
+        assert!(matches!(
+            controller.set_speed(Speed::High).await,
+            Err(RogError::NotSupported)
+        ));
+    }
+
+    #[test]
+    fn lid_close_only_when_enabled() {
+        let config = AuraConfig::from_default_support(AuraDevice::X19b6, &LaptopLedData::default());
+        assert!(!config.leds_off_on_lid_close);
+        let supported_modes = LaptopLedData {
+            board_name: String::new(),
+            layout_name: "ga401".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![],
+            advanced_type: rog_aura::AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let mut controller = test_controller(supported_modes, config);
+
+        assert!(controller.lid_change(true).is_ok());
+        assert!(!controller.lid_off);
+        assert!(controller.lid_change(false).is_ok());
+
+        // Nothing to restore if the backlight was never turned off
+        controller.config.leds_off_on_lid_close = true;
+        assert!(controller.lid_change(false).is_ok());
+        assert!(!controller.lid_off);
+
+        // While off for the lid the idle timeout must not touch the backlight
+        controller.lid_off = true;
+        controller.config.backlight_timeout_secs = 10;
+        assert!(controller.idle_dim().is_ok());
+        assert!(controller.idle_restore.is_none());
+    }
 }
@@ -1,33 +1,227 @@
 use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{debug, error, info, warn};
-use rog_aura::advanced::UsbPackets;
-use rog_aura::usb::{AuraDevice, AuraPowerDev};
-use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+use rog_aura::advanced::{KeyMap, UsbPackets};
+use rog_aura::aura_detection::{KeyboardInfo, ASUS_KEYBOARD_DEVICES};
+use rog_aura::usb::{AuraDevice, AuraPowerDev, KeyboardTarget};
+use rog_aura::{AuraEffect, AuraModeNum, AuraParameters, AuraZone, Colour, LedBrightness, Speed};
+use rog_platform::hid_raw::HidRaw;
+use rog_platform::hwmon::{read_temperature_from, temperature_inputs};
+use rog_platform::keyboard_led::keyboard_input_nodes;
+use rog_profiles::Profile;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+use tokio::task::JoinHandle;
 use zbus::export::futures_util::lock::{Mutex, MutexGuard};
 use zbus::export::futures_util::StreamExt;
 use zbus::{dbus_interface, Connection, SignalContext};
 
-use super::controller::CtrlKbdLed;
+use super::controller::{
+    active_platform_profile, ChangeSource, CtrlKbdLed, EffectRng, FLASH_MAX_MS, IDENTIFY_BLINK_MS,
+};
 use crate::error::RogError;
 use crate::CtrlTask;
 
 pub(super) const ZBUS_PATH: &str = "/org/asuslinux/Aura";
 
+/// How often the thermal effect reads the temperature and updates the keyboard
+const THERMAL_EFFECT_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the backlight timeout and LED keepalive are checked for being
+/// enabled when they are off
+const BACKLIGHT_TIMEOUT_POLL: Duration = Duration::from_secs(60);
+
+/// Map the `NotSupported` from a mode change on a keyboard without an RGB node
+fn brightness_only_err(e: RogError) -> zbus::fdo::Error {
+    match e {
+        RogError::NotSupported => {
+            zbus::fdo::Error::NotSupported("this keyboard supports brightness only".to_owned())
+        }
+        e => e.into(),
+    }
+}
+
 #[derive(Clone)]
 pub struct CtrlKbdLedZbus(pub Arc<Mutex<CtrlKbdLed>>);
 
 impl CtrlKbdLedZbus {
-    fn update_config(lock: &mut CtrlKbdLed) -> Result<(), RogError> {
-        let bright = lock.kd_brightness.get_brightness()?;
+    /// Update the config from the brightness node. Returns the new brightness
+    /// if it differs from what was stored.
+    fn update_config(lock: &mut CtrlKbdLed) -> Result<Option<LedBrightness>, RogError> {
+        let bright: LedBrightness = (lock.kd_brightness.get_brightness()? as u32).into();
+        if lock.lid_off && bright == LedBrightness::Off {
+            // Turned off for the closed lid, keep the stored level
+            return Ok(None);
+        }
+        if lock.idle_restore.is_some() {
+            if Some(bright) == lock.config.idle_dim_level() {
+                // Dimmed by the idle timeout, keep the stored level
+                return Ok(None);
+            }
+            lock.idle_restore = None;
+        }
         lock.config.read();
-        lock.config.brightness = (bright as u32).into();
-        lock.config.write();
+        if lock.config.brightness == bright {
+            return Ok(None);
+        }
+        lock.config.set_brightness(bright);
+        // The keyboard already changed, so listeners are told even if saving
+        // fails
+        lock.config
+            .try_write()
+            .unwrap_or_else(|e| error!("Could not save the LED brightness: {e}"));
+        lock.remember_profile_lighting();
+        Ok(Some(bright))
+    }
+
+    /// Apply the lighting mapped to the profile and notify listeners of the
+    /// changed mode and brightness
+    async fn apply_profile_lighting(
+        ctrl: &mut CtrlKbdLed,
+        profile: Profile,
+        signal_ctxt: &SignalContext<'_>,
+    ) -> Result<(), RogError> {
+        if ctrl.apply_profile_lighting(profile)? {
+            ctrl.mark_change(ChangeSource::Profile);
+            if let Some(effect) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+                Self::notify_led(signal_ctxt, effect.clone())
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+            Self::notify_brightness(signal_ctxt, ctrl.config.brightness)
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
         Ok(())
     }
+
+    /// Dim the backlight when there are no key events for
+    /// `backlight_timeout_secs`. The keyboard input nodes are only open while
+    /// a timeout is set.
+    fn watch_keyboard_idle(&self) {
+        let ctrl = self.0.clone();
+        tokio::spawn(async move {
+            let mut activity: Option<KeyboardActivity> = None;
+            loop {
+                let secs = ctrl.lock().await.config.backlight_timeout_secs;
+                if secs == 0 {
+                    if activity.take().is_some() {
+                        debug!("CtrlKbdLedTask: backlight timeout off, input nodes closed");
+                        ctrl.lock()
+                            .await
+                            .idle_wake()
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    }
+                    tokio::time::sleep(BACKLIGHT_TIMEOUT_POLL).await;
+                    continue;
+                }
+
+                if activity.is_none() {
+                    activity = KeyboardActivity::open();
+                }
+                let keys = match activity.as_mut() {
+                    Some(keys) => keys,
+                    None => {
+                        tokio::time::sleep(BACKLIGHT_TIMEOUT_POLL).await;
+                        continue;
+                    }
+                };
+                match tokio::time::timeout(Duration::from_secs(secs), keys.rx.recv()).await {
+                    Ok(Some(())) => {
+                        let mut lock = ctrl.lock().await;
+                        if lock.idle_restore.is_some() {
+                            lock.idle_wake()
+                                .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                                .ok();
+                        }
+                    }
+                    Ok(None) => {
+                        debug!("CtrlKbdLedTask: keyboard input nodes closed");
+                        activity = None;
+                    }
+                    Err(_) => {
+                        ctrl.lock()
+                            .await
+                            .idle_dim()
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Key activity on the keyboard input nodes. The nodes are closed when this
+/// is dropped.
+struct KeyboardActivity {
+    rx: tokio::sync::mpsc::Receiver<()>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl KeyboardActivity {
+    /// Open every keyboard input node, or `None` if there are none
+    fn open() -> Option<Self> {
+        let nodes = keyboard_input_nodes()
+            .map_err(|e| debug!("CtrlKbdLedTask: keyboard input nodes: {e}"))
+            .ok()?;
+        let (tx, rx) = tokio::sync::mpsc::channel::<()>(1);
+        let readers: Vec<JoinHandle<()>> = nodes
+            .into_iter()
+            .filter_map(|node| {
+                open_input_node(&node)
+                    .map_err(|e| warn!("CtrlKbdLedTask: could not open {node:?}: {e}"))
+                    .ok()
+            })
+            .map(|fd| tokio::spawn(read_activity(fd, tx.clone())))
+            .collect();
+        if readers.is_empty() {
+            debug!("CtrlKbdLedTask: no keyboard input nodes for the backlight timeout");
+            return None;
+        }
+        Some(Self { rx, readers })
+    }
+}
+
+impl Drop for KeyboardActivity {
+    fn drop(&mut self) {
+        for reader in &self.readers {
+            reader.abort();
+        }
+    }
+}
+
+/// Open an input node without blocking reads so its reader can be stopped
+fn open_input_node(node: &Path) -> std::io::Result<AsyncFd<File>> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(node)?;
+    AsyncFd::with_interest(file, Interest::READABLE)
+}
+
+/// Send on `tx` for each read from the input node until it is closed
+async fn read_activity(fd: AsyncFd<File>, tx: tokio::sync::mpsc::Sender<()>) {
+    let mut buffer = [0; 128];
+    while let Ok(mut guard) = fd.readable().await {
+        match guard.try_io(|fd| fd.get_ref().read(&mut buffer)) {
+            Ok(Ok(0)) | Ok(Err(_)) => break,
+            // Only the fact there was activity matters
+            Ok(Ok(_)) => {
+                tx.try_send(()).ok();
+            }
+            Err(_would_block) => continue,
+        }
+    }
 }
 
 #[async_trait]
@@ -44,10 +238,39 @@ impl crate::ZbusRun for CtrlKbdLedZbus {
 impl CtrlKbdLedZbus {
     /// Set the keyboard brightness level (0-3)
     async fn set_brightness(&mut self, brightness: LedBrightness) {
-        let ctrl = self.0.lock().await;
-        ctrl.set_brightness(brightness)
+        let mut ctrl = self.0.lock().await;
+        if ctrl
+            .set_brightness(brightness)
             .map_err(|err| warn!("{}", err))
-            .ok();
+            .is_ok()
+        {
+            ctrl.mark_change(ChangeSource::Dbus);
+        }
+    }
+
+    /// Set the keyboard brightness as a percentage, which is rounded to the
+    /// nearest level: 0-16 Off, 17-49 Low, 50-83 Med, 84-100 High
+    async fn set_brightness_percent(&mut self, pct: u8) -> zbus::fdo::Result<()> {
+        if pct > 100 {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "brightness percent must be 0-100, got {pct}"
+            )));
+        }
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_brightness(LedBrightness::from_percent(pct))
+            .map_err(|err| {
+                warn!("{}", err);
+                err
+            })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// The keyboard brightness as a percentage: 0, 33, 67, or 100
+    async fn brightness_percent(&self) -> zbus::fdo::Result<u8> {
+        let ctrl = self.0.lock().await;
+        let level: LedBrightness = (ctrl.get_brightness()? as u32).into();
+        Ok(level.to_percent())
     }
 
     /// Set a variety of states, input is array of enum.
@@ -76,6 +299,86 @@ impl CtrlKbdLedZbus {
             e
         })?;
 
+        Self::notify_power_states(&ctxt, &AuraPowerDev::from(&ctrl.config.enabled))
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Check an effect can be applied to this keyboard without applying it.
+    /// Returns an `InvalidArgs` error with the reason if it can not.
+    async fn validate_effect(&self, effect: AuraEffect) -> zbus::fdo::Result<()> {
+        let ctrl = self.0.lock().await;
+        ctrl.validate_effect(&effect).map_err(|e| match e {
+            RogError::AuraEffectInvalid(reason) => zbus::fdo::Error::InvalidArgs(reason),
+            e => e.into(),
+        })
+    }
+
+    /// Dump the full LED config as JSON for backup
+    async fn export_config(&self) -> zbus::fdo::Result<String> {
+        let ctrl = self.0.lock().await;
+        Ok(ctrl.export_config().map_err(|e| {
+            warn!("{}", e);
+            e
+        })?)
+    }
+
+    /// Restore a LED config from `export_config`. Effects for modes this
+    /// keyboard does not have are dropped, and the config is rejected if the
+    /// current mode is not supported.
+    async fn import_config(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        json: String,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.import_config(&json).map_err(|e| {
+            warn!("{}", e);
+            match e {
+                RogError::AuraConfigInvalid(reason) => zbus::fdo::Error::InvalidArgs(reason),
+                e => e.into(),
+            }
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+
+        if let Some(effect) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, effect.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        Ok(())
+    }
+
+    /// Read the config file again and apply all of it to the keyboard, the
+    /// same as at startup. For scripts that edit the config file.
+    #[dbus_interface(name = "Reload")]
+    async fn reload_from_config(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        {
+            let mut ctrl = self.0.lock().await;
+            let mut config = ctrl.config.clone();
+            config.read();
+            ctrl.use_config(config);
+        }
+        crate::Reloadable::reload(self).await.map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+
+        let mut ctrl = self.0.lock().await;
+        ctrl.mark_change(ChangeSource::Dbus);
+        if let Some(effect) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, effect.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        Self::notify_brightness(&ctxt, ctrl.config.brightness)
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
         Self::notify_power_states(&ctxt, &AuraPowerDev::from(&ctrl.config.enabled))
             .await
             .unwrap_or_else(|err| warn!("{}", err));
@@ -89,9 +392,9 @@ impl CtrlKbdLedZbus {
     ) -> zbus::fdo::Result<()> {
         let mut ctrl = self.0.lock().await;
 
-        ctrl.set_effect(effect).map_err(|e| {
+        ctrl.set_effect(effect).await.map_err(|e| {
             warn!("{}", e);
-            e
+            brightness_only_err(e)
         })?;
 
         ctrl.set_brightness(ctrl.config.brightness).map_err(|e| {
@@ -104,6 +407,267 @@ impl CtrlKbdLedZbus {
                 .await
                 .unwrap_or_else(|err| warn!("{}", err));
         }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set a single colour breathe that is in phase across all zones. Keyboards
+    /// without zones get a normal breathe.
+    async fn set_synced_breathe(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        colour: Colour,
+        speed: Speed,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_synced_breathe(colour, speed).await.map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+
+        if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, mode.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set a static colour from a `#RRGGBB` or `RRGGBB` hex string, for
+    /// scripts that don't want to build a full `AuraEffect`
+    async fn set_static_hex(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        hex: String,
+    ) -> zbus::fdo::Result<()> {
+        let colour = Colour::from_hex(&hex).map_err(|_| {
+            zbus::fdo::Error::InvalidArgs(format!(
+                "\"{hex}\" is not a hex colour, expected #RRGGBB or RRGGBB"
+            ))
+        })?;
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_effect(AuraEffect {
+            colour1: colour,
+            ..AuraEffect::default_with_mode(AuraModeNum::Static)
+        })
+        .await
+        .map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+
+        if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, mode.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Change only the speed of the current mode, keeping its colours. Errors
+    /// with `NotSupported` for modes without a speed such as Static.
+    async fn set_speed(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        speed: Speed,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_speed(speed).await.map_err(|e| {
+            warn!("{}", e);
+            match e {
+                RogError::NotSupported if !ctrl.led_node.is_none() => {
+                    zbus::fdo::Error::NotSupported(format!(
+                        "{:?} has no speed setting",
+                        ctrl.config.current_mode
+                    ))
+                }
+                e => brightness_only_err(e),
+            }
+        })?;
+
+        if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, mode.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// The product ID of every keyboard that can be addressed with a
+    /// `KeyboardTarget`, the primary laptop keyboard first
+    async fn keyboards(&self) -> Vec<AuraDevice> {
+        let ctrl = self.0.lock().await;
+        ctrl.keyboards()
+    }
+
+    /// `set_led_mode` for a chosen keyboard when an external ROG keyboard is
+    /// also connected. The target is "primary", "all" or a product ID such as
+    /// "1866". External keyboards are always set as a whole.
+    async fn set_led_mode_target(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        target: KeyboardTarget,
+        effect: AuraEffect,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_effect_for(target, effect).await.map_err(|e| {
+            warn!("{}", e);
+            brightness_only_err(e)
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+
+        // `notify_led` is for the primary keyboard only
+        if ctrl.targets_primary(target) {
+            if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+                Self::notify_led(&ctxt, mode.clone())
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+        }
+        Ok(())
+    }
+
+    /// `set_brightness` for a chosen keyboard, see `set_led_mode_target`
+    async fn set_brightness_target(
+        &mut self,
+        target: KeyboardTarget,
+        brightness: LedBrightness,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_brightness_for(target, brightness).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set a random effect from the modes this keyboard supports, with random
+    /// colours, speed and direction where the mode has them. The effect is
+    /// stored as the mode's setting and returned.
+    async fn random_effect(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<AuraEffect> {
+        let mut ctrl = self.0.lock().await;
+        let effect = ctrl
+            .set_random_effect(&mut EffectRng::from_time())
+            .await
+            .map_err(|e| {
+                warn!("{}", e);
+                match e {
+                    RogError::NotSupported if !ctrl.led_node.is_none() => {
+                        zbus::fdo::Error::NotSupported("this keyboard has no LED modes".to_owned())
+                    }
+                    e => brightness_only_err(e),
+                }
+            })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Self::notify_led(&ctxt, effect.clone())
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        Ok(effect)
+    }
+
+    /// Set the brightness of a single zone, such as the lightbar, separately
+    /// to the keyboard. The zone can only be dimmed below the keyboard
+    /// brightness. Errors with `NotSupported` if the keyboard lacks the zone.
+    async fn set_zone_brightness(
+        &mut self,
+        zone: AuraZone,
+        level: LedBrightness,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_zone_brightness(zone, level).map_err(|e| {
+            warn!("{}", e);
+            match e {
+                RogError::NotSupported => zbus::fdo::Error::NotSupported(format!(
+                    "{zone:?} brightness can not be set on this keyboard"
+                )),
+                e => e.into(),
+            }
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set a per-channel gamma correction for keyboards that show colours with
+    /// a cast. `1.0` is no change and each value must be above `0.0`.
+    async fn set_colour_gamma(&mut self, r: f32, g: f32, b: f32) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_colour_gamma((r, g, b)).map_err(|e| {
+            warn!("{}", e);
+            match e {
+                RogError::AuraConfigInvalid(reason) => zbus::fdo::Error::InvalidArgs(reason),
+                e => e.into(),
+            }
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// The per-channel `(r, g, b)` gamma correction
+    async fn colour_gamma(&self) -> (f32, f32, f32) {
+        let ctrl = self.0.lock().await;
+        ctrl.config.colour_gamma
+    }
+
+    /// Set the primary colour of every builtin mode that uses one, and
+    /// reapply the current mode. Modes without a colour are not changed.
+    async fn set_global_colour(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        colour: Colour,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_global_colour(colour).map_err(|e| {
+            warn!("{}", e);
+            brightness_only_err(e)
+        })?;
+
+        if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, mode.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set the mode by its human readable name, such as "static" or
+    /// "rainbow". The stored settings for that mode are used.
+    async fn set_led_mode_by_name(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        name: String,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+
+        let mode = ctrl.mode_from_name(&name).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        let effect = ctrl
+            .config
+            .builtins
+            .get(&mode)
+            .cloned()
+            .unwrap_or_else(|| AuraEffect::default_with_mode(mode));
+
+        ctrl.set_effect(effect).await.map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+
+        if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, mode.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
@@ -113,9 +677,9 @@ impl CtrlKbdLedZbus {
     ) -> zbus::fdo::Result<()> {
         let mut ctrl = self.0.lock().await;
 
-        ctrl.toggle_mode(false).map_err(|e| {
+        ctrl.toggle_mode(false).await.map_err(|e| {
             warn!("{}", e);
-            e
+            brightness_only_err(e)
         })?;
 
         if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
@@ -124,6 +688,7 @@ impl CtrlKbdLedZbus {
                 .unwrap_or_else(|err| warn!("{}", err));
         }
 
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
@@ -133,9 +698,9 @@ impl CtrlKbdLedZbus {
     ) -> zbus::fdo::Result<()> {
         let mut ctrl = self.0.lock().await;
 
-        ctrl.toggle_mode(true).map_err(|e| {
+        ctrl.toggle_mode(true).await.map_err(|e| {
             warn!("{}", e);
-            e
+            brightness_only_err(e)
         })?;
 
         if let Some(mode) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
@@ -144,6 +709,7 @@ impl CtrlKbdLedZbus {
                 .unwrap_or_else(|err| warn!("{}", err));
         }
 
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
@@ -153,24 +719,264 @@ impl CtrlKbdLedZbus {
             warn!("{}", e);
             e
         })?;
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
+    /// Turn the backlight off, or back on at the level it was at before being
+    /// turned off. Returns the new brightness.
+    async fn toggle_backlight(&self) -> zbus::fdo::Result<LedBrightness> {
+        let mut ctrl = self.0.lock().await;
+        let brightness = ctrl.toggle_brightness().map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(brightness)
+    }
+
     async fn prev_led_brightness(&self) -> zbus::fdo::Result<()> {
         let mut ctrl = self.0.lock().await;
         ctrl.prev_brightness().map_err(|e| {
             warn!("{}", e);
             e
         })?;
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
+    /// Blink a single zone for a number of seconds so the user can identify
+    /// which physical region it maps to. The current mode is restored
+    /// afterwards. Only available on multizone keyboards.
+    async fn identify_zone(&self, zone: AuraZone, seconds: u8) -> zbus::fdo::Result<()> {
+        let (sequence, flash_lock) = {
+            let mut ctrl = self.0.lock().await;
+            let sequence = ctrl.identify_zone_sequence(zone, seconds).map_err(|e| {
+                warn!("{}", e);
+                e
+            })?;
+            // A software effect would write over the blinks
+            ctrl.thermal_effect_running.store(false, Ordering::SeqCst);
+            ctrl.demo_running.store(false, Ordering::SeqCst);
+            (sequence, ctrl.flash_lock.clone())
+        };
+
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            // Run after any flash, which would otherwise restore the mode mid-blink
+            let _flashing = flash_lock.lock().await;
+            for effect in sequence {
+                inner
+                    .lock()
+                    .await
+                    .write_mode(&effect)
+                    .map_err(|e| error!("identify_zone: {e}"))
+                    .ok();
+                tokio::time::sleep(Duration::from_millis(IDENTIFY_BLINK_MS)).await;
+            }
+            inner
+                .lock()
+                .await
+                .write_current_config_mode()
+                .map_err(|e| error!("identify_zone: failed to restore mode: {e}"))
+                .ok();
+        });
+        Ok(())
+    }
+
+    /// Flash the keyboard a static colour for `millis` (at most 10 seconds),
+    /// then restore the current mode. Concurrent flashes are run one after
+    /// the other, a mode set during a flash is what gets restored.
+    async fn flash(&self, colour: Colour, millis: u64) -> zbus::fdo::Result<()> {
+        if millis == 0 || millis > FLASH_MAX_MS {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "flash duration must be 1-{FLASH_MAX_MS} ms"
+            )));
+        }
+        let flash_lock = {
+            let ctrl = self.0.lock().await;
+            if ctrl.led_node.is_none() {
+                warn!("{}", RogError::NoAuraKeyboard);
+                return Err(RogError::NoAuraKeyboard.into());
+            }
+            ctrl.flash_lock.clone()
+        };
+
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            let _flashing = flash_lock.lock().await;
+            let effect = AuraEffect {
+                mode: AuraModeNum::Static,
+                colour1: colour,
+                ..Default::default()
+            };
+            if let Err(e) = inner.lock().await.write_mode(&effect) {
+                error!("flash: {e}");
+                return;
+            }
+            // The controller is free while waiting so dbus calls are not held up
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            inner
+                .lock()
+                .await
+                .write_current_config_mode()
+                .map_err(|e| error!("flash: failed to restore mode: {e}"))
+                .ok();
+        });
+        Ok(())
+    }
+
+    /// Start a software effect that colours the keyboard from blue to red
+    /// following the CPU/GPU temperature. The temperature bounds are set by
+    /// `thermal_effect` in the config.
+    async fn start_thermal_effect(&self) -> zbus::fdo::Result<()> {
+        let inputs = temperature_inputs()
+            .and_then(|inputs| read_temperature_from(&inputs).map(|_| inputs))
+            .map_err(|e| {
+                warn!("start_thermal_effect: {e}");
+                RogError::from(e)
+            })?;
+
+        let running = {
+            let mut ctrl = self.0.lock().await;
+            if ctrl.thermal_effect_running.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            // A new flag for each run, so that a stopped loop still in its
+            // sleep can not see this run as its own and keep going
+            ctrl.thermal_effect_running = Arc::new(AtomicBool::new(true));
+            ctrl.mark_change(ChangeSource::Thermal);
+            ctrl.thermal_effect_running.clone()
+        };
+
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            while running.load(Ordering::SeqCst) {
+                match read_temperature_from(&inputs) {
+                    Ok(temp) => {
+                        let mut ctrl = inner.lock().await;
+                        // May have been stopped while waiting on the lock
+                        if running.load(Ordering::SeqCst) {
+                            ctrl.write_thermal_effect(temp)
+                                .map_err(|e| error!("thermal effect: {e}"))
+                                .ok();
+                        }
+                    }
+                    Err(e) => {
+                        error!("thermal effect: {e}");
+                        running.store(false, Ordering::SeqCst);
+                    }
+                }
+                tokio::time::sleep(THERMAL_EFFECT_INTERVAL).await;
+            }
+            debug!("thermal effect stopped");
+        });
+        Ok(())
+    }
+
+    /// Stop the thermal effect and restore the previous effect
+    async fn stop_thermal_effect(&self) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if ctrl.thermal_effect_running.swap(false, Ordering::SeqCst) {
+            ctrl.write_current_config_mode().map_err(|e| {
+                warn!("{}", e);
+                e
+            })?;
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Step through every supported mode with its default colours, showing
+    /// each for `seconds_per_mode`, then restore the previous effect and
+    /// brightness. Nothing is saved to the config.
+    async fn demo_cycle(&self, seconds_per_mode: u8) -> zbus::fdo::Result<()> {
+        let (running, sequence) = {
+            let mut ctrl = self.0.lock().await;
+            let sequence = ctrl.demo_sequence();
+            if sequence.is_empty() || ctrl.demo_running.swap(true, Ordering::SeqCst) {
+                return Ok(());
+            }
+            ctrl.mark_change(ChangeSource::Demo);
+            (ctrl.demo_running.clone(), sequence)
+        };
+
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            for effect in sequence {
+                {
+                    let mut ctrl = inner.lock().await;
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    ctrl.write_mode(&effect)
+                        .map_err(|e| error!("demo_cycle: {e}"))
+                        .ok();
+                    if ctrl.config.brightness == LedBrightness::Off {
+                        ctrl.set_brightness(LedBrightness::Med)
+                            .map_err(|e| error!("demo_cycle: {e}"))
+                            .ok();
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(seconds_per_mode as u64)).await;
+            }
+            // Restore only if not already stopped by `stop_demo` or a new effect
+            if running.swap(false, Ordering::SeqCst) {
+                inner
+                    .lock()
+                    .await
+                    .restore_config_mode()
+                    .map_err(|e| error!("demo_cycle: {e}"))
+                    .ok();
+            }
+            debug!("demo cycle stopped");
+        });
+        Ok(())
+    }
+
+    /// Stop the demo and restore the previous effect and brightness
+    async fn stop_demo(&self) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if ctrl.demo_running.swap(false, Ordering::SeqCst) {
+            ctrl.restore_config_mode().map_err(|e| {
+                warn!("{}", e);
+                e
+            })?;
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// List every connected ASUS USB LED device, regardless of which one is
+    /// being controlled. Returns the product ID paired with the device node.
+    async fn list_aura_devices(&self) -> zbus::fdo::Result<Vec<(String, String)>> {
+        let ids: Vec<&str> = ASUS_KEYBOARD_DEVICES
+            .iter()
+            .filter(|d| **d != AuraDevice::Tuf)
+            .map(|d| <&str>::from(*d))
+            .collect();
+        let found = HidRaw::find_all(&ids).map_err(|e| {
+            warn!("{}", e);
+            RogError::from(e)
+        })?;
+        Ok(found
+            .into_iter()
+            .map(|(id, path)| (id, path.to_string_lossy().to_string()))
+            .collect())
+    }
+
     /// Return the device type for this Aura keyboard
     async fn device_type(&self) -> AuraDevice {
         let ctrl = self.0.lock().await;
         ctrl.led_prod
     }
 
+    /// Return the keyboard product ID, device type and layout details
+    async fn keyboard_info(&self) -> KeyboardInfo {
+        let ctrl = self.0.lock().await;
+        KeyboardInfo::new(ctrl.led_prod, &ctrl.supported_modes)
+    }
+
     // As property doesn't work for AuraPowerDev (complexity of serialization?)
     // #[dbus_interface(property)]
     async fn led_power(&self) -> AuraPowerDev {
@@ -184,21 +990,202 @@ impl CtrlKbdLedZbus {
         ctrl.config.current_mode
     }
 
+    /// Return the supported modes paired with their human readable name
+    async fn mode_names(&self) -> Vec<(AuraModeNum, String)> {
+        let ctrl = self.0.lock().await;
+        ctrl.mode_names()
+    }
+
+    /// Return which effect fields each supported mode uses, such as if it
+    /// takes a second colour or a speed
+    async fn mode_capabilities(&self) -> BTreeMap<AuraModeNum, AuraParameters> {
+        let ctrl = self.0.lock().await;
+        ctrl.mode_capabilities()
+    }
+
     /// Return a list of available modes
     async fn led_modes(&self) -> BTreeMap<AuraModeNum, AuraEffect> {
         let ctrl = self.0.lock().await;
         ctrl.config.builtins.clone()
     }
 
+    /// Remove the per-zone effects for a mode, reverting it to the standard
+    /// single colour builtin. Does nothing if the mode has no multizone data.
+    async fn clear_multizone(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        mode: AuraModeNum,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        let cleared = ctrl.clear_multizone(mode).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+
+        if cleared && mode == ctrl.config.current_mode {
+            if let Some(effect) = ctrl.config.builtins.get(&mode) {
+                Self::notify_led(&ctxt, effect.clone())
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+        }
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Return the multizone effects currently set, if any
+    async fn led_multizone(&self) -> BTreeMap<AuraModeNum, Vec<AuraEffect>> {
+        let ctrl = self.0.lock().await;
+        ctrl.config.multizone.clone().unwrap_or_default()
+    }
+
     /// On machine that have some form of either per-key keyboard or per-zone
     /// this can be used to write custom effects over dbus. The input is a
     /// nested `Vec<Vec<8>>` where `Vec<u8>` is a raw USB packet
     async fn direct_addressing_raw(&self, data: UsbPackets) -> zbus::fdo::Result<()> {
         let mut ctrl = self.0.lock().await;
-        ctrl.write_effect_block(&data)?;
+        ctrl.write_effect_block(&data).map_err(|e| {
+            warn!("{}", e);
+            brightness_only_err(e)
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set the colour of a single key on per-key keyboards by its name, such
+    /// as "Enter" or "W". The other keys keep their last colour.
+    async fn set_key_colour(&self, key: String, colour: Colour) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_key_colour(&key, colour).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set the colour of only the function row and media keys on per-key
+    /// keyboards, leaving the other keys as they are
+    async fn set_fn_row_colour(&self, colour: Colour) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_fn_row_colour(colour).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Set the colour of each key on per-key keyboards. Keys missing from the
+    /// map are turned off.
+    async fn set_keymap(&self, map: KeyMap) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.set_keymap(&map).map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+        Ok(())
+    }
+
+    /// Return if the keyboard brightness and mode follow the platform profile
+    async fn led_follows_profile(&self) -> bool {
+        let ctrl = self.0.lock().await;
+        ctrl.config.follows_profile
+    }
+
+    /// Set if the keyboard brightness and mode change with the platform
+    /// profile. A default mapping per profile is created the first time this
+    /// is enabled. The LED power states for AC/battery, sleep, and boot still
+    /// apply and take precedence, as they decide if the LEDs are lit at all.
+    async fn set_led_follows_profile(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.config.set_follows_profile(enabled);
+        ctrl.config.write();
+        if let Some(profile) = ctrl.active_platform_profile() {
+            Self::apply_profile_lighting(&mut ctrl, profile, &ctxt)
+                .await
+                .map_err(|e| {
+                    warn!("{}", e);
+                    e
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Reset the keyboard to solid white at full brightness with all LED zones
+    /// enabled, ignoring the stored config. This is a known good state for
+    /// when effects have gone wrong. It is only stored if `save` is true.
+    async fn safe_state(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        save: bool,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        let effect = ctrl.safe_state(save)?;
+        Self::notify_led(&ctxt, effect)
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        Self::notify_brightness(&ctxt, LedBrightness::High)
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        ctrl.mark_change(ChangeSource::Dbus);
         Ok(())
     }
 
+    /// Reset the LED config to the defaults for this keyboard and apply it.
+    /// Only the LED config is reset.
+    async fn reset_aura_defaults(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.reset_defaults().map_err(|e| {
+            warn!("{}", e);
+            e
+        })?;
+        ctrl.mark_change(ChangeSource::Dbus);
+
+        if let Some(effect) = ctrl.config.builtins.get(&ctrl.config.current_mode) {
+            Self::notify_led(&ctxt, effect.clone())
+                .await
+                .unwrap_or_else(|err| warn!("{}", err));
+        }
+        Self::notify_brightness(&ctxt, ctrl.config.brightness)
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        Self::notify_power_states(&ctxt, &AuraPowerDev::from(&ctrl.config.enabled))
+            .await
+            .unwrap_or_else(|err| warn!("{}", err));
+        Ok(())
+    }
+
+    /// When the keyboard lighting was last changed by asusd, as seconds since
+    /// the UNIX epoch, and what triggered it. Returns `(0, "none")` if nothing
+    /// has changed it since asusd started.
+    async fn last_effect_change(&self) -> (u64, String) {
+        let ctrl = self.0.lock().await;
+        ctrl.last_change()
+    }
+
+    /// The exact byte read from the brightness node, for diagnostics. Unlike
+    /// `led_brightness` a failed read is an error naming the node rather than
+    /// `-1`.
+    async fn brightness_raw(&self) -> zbus::fdo::Result<u8> {
+        let ctrl = self.0.lock().await;
+        ctrl.get_brightness().map_err(|e| {
+            warn!("{}", e);
+            zbus::fdo::Error::Failed(format!(
+                "could not read {}/brightness: {e}",
+                ctrl.kd_brightness.path().display()
+            ))
+        })
+    }
+
     /// Return the current LED brightness
     #[dbus_interface(property)]
     async fn led_brightness(&self) -> i8 {
@@ -209,11 +1196,22 @@ impl CtrlKbdLedZbus {
     #[dbus_interface(signal)]
     async fn notify_led(signal_ctxt: &SignalContext<'_>, data: AuraEffect) -> zbus::Result<()>;
 
+    #[dbus_interface(signal)]
+    async fn notify_brightness(
+        signal_ctxt: &SignalContext<'_>,
+        brightness: LedBrightness,
+    ) -> zbus::Result<()>;
+
     #[dbus_interface(signal)]
     async fn notify_power_states(
         signal_ctxt: &SignalContext<'_>,
         data: &AuraPowerDev,
     ) -> zbus::Result<()>;
+
+    /// Emitted after the config file was edited outside of asusd and the new
+    /// settings applied
+    #[dbus_interface(signal)]
+    async fn notify_config_reloaded(signal_ctxt: &SignalContext<'_>) -> zbus::Result<()>;
 }
 
 #[async_trait]
@@ -222,44 +1220,58 @@ impl CtrlTask for CtrlKbdLedZbus {
         ZBUS_PATH
     }
 
-    async fn create_tasks(&self, _: SignalContext<'static>) -> Result<(), RogError> {
-        let load_save = |start: bool, mut lock: MutexGuard<'_, CtrlKbdLed>| {
-            // If waking up
-            if !start {
-                info!("CtrlKbdLedTask reloading brightness and modes");
-                lock.set_brightness(lock.config.brightness)
-                    .map_err(|e| error!("CtrlKbdLedTask: {e}"))
-                    .ok();
-                lock.write_current_config_mode()
-                    .map_err(|e| error!("CtrlKbdLedTask: {e}"))
-                    .ok();
-            } else if start {
-                Self::update_config(&mut lock)
-                    .map_err(|e| error!("CtrlKbdLedTask: {e}"))
-                    .ok();
-            }
-        };
+    async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        let load_save =
+            |start: bool, source: ChangeSource, mut lock: MutexGuard<'_, CtrlKbdLed>| {
+                // If waking up
+                if !start {
+                    info!("CtrlKbdLedTask reloading brightness and modes");
+                    if lock.get_brightness().is_err() {
+                        // The nodes can go missing on some machines after resume
+                        lock.refresh_nodes()
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    }
+                    lock.set_brightness(lock.config.brightness)
+                        .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        .ok();
+                    lock.restore_mode_and_power()
+                        .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        .ok();
+                    lock.mark_change(source);
+                } else if start {
+                    Self::update_config(&mut lock)
+                        .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        .ok();
+                }
+            };
 
         let inner1 = self.0.clone();
+        let inner2 = self.0.clone();
         let inner3 = self.0.clone();
         self.create_sys_event_tasks(
             move |sleeping| {
                 let inner1 = inner1.clone();
                 async move {
                     let lock = inner1.lock().await;
-                    load_save(sleeping, lock);
+                    load_save(sleeping, ChangeSource::Resume, lock);
                 }
             },
             move |_shutting_down| {
                 let inner3 = inner3.clone();
                 async move {
                     let lock = inner3.lock().await;
-                    load_save(false, lock);
+                    load_save(false, ChangeSource::Shutdown, lock);
                 }
             },
-            move |_lid_closed| {
-                // on lid change
-                async move {}
+            move |lid_closed| {
+                let inner2 = inner2.clone();
+                async move {
+                    let mut lock = inner2.lock().await;
+                    lock.lid_change(lid_closed)
+                        .map_err(|e| error!("CtrlKbdLedTask: lid change {e}"))
+                        .ok();
+                }
             },
             move |_power_plugged| {
                 // power change
@@ -268,6 +1280,95 @@ impl CtrlTask for CtrlKbdLedZbus {
         )
         .await;
 
+        let platform = self.0.lock().await.platform.clone();
+        match platform.map(|platform| {
+            platform
+                .monitor_platform_profile()
+                .map(|watch| (watch, platform))
+        }) {
+            Some(Ok((watch, platform))) => {
+                let ctrl = self.0.clone();
+                let signal_ctxt = signal_ctxt.clone();
+                tokio::spawn(async move {
+                    let mut buffer = [0; 32];
+                    let mut last = active_platform_profile(&platform);
+                    if let Ok(mut stream) = watch.into_event_stream(&mut buffer) {
+                        while stream.next().await.is_some() {
+                            let profile = active_platform_profile(&platform);
+                            if profile == last {
+                                continue;
+                            }
+                            last = profile;
+                            if let Some(profile) = profile {
+                                let mut lock = ctrl.lock().await;
+                                Self::apply_profile_lighting(&mut lock, profile, &signal_ctxt)
+                                    .await
+                                    .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                                    .ok();
+                            }
+                        }
+                    }
+                });
+            }
+            Some(Err(e)) => info!("CtrlKbdLedTask: platform_profile watch not available: {e}"),
+            None => info!("CtrlKbdLedTask: platform_profile watch not available"),
+        }
+
+        self.watch_keyboard_idle();
+
+        let ctrl = self.0.clone();
+        let reload_ctxt = signal_ctxt.clone();
+        let file_name = self.0.lock().await.config.file_name();
+        crate::watch_config_file(file_name, move || {
+            let ctrl = ctrl.clone();
+            let signal_ctxt = reload_ctxt.clone();
+            async move {
+                let mut lock = ctrl.lock().await;
+                match lock.reload_config() {
+                    Ok(true) => lock.mark_change(ChangeSource::Config),
+                    Ok(false) => return,
+                    Err(e) => {
+                        warn!("CtrlKbdLedTask: config reload {e}");
+                        return;
+                    }
+                }
+                if let Some(effect) = lock.config.builtins.get(&lock.config.current_mode) {
+                    Self::notify_led(&signal_ctxt, effect.clone())
+                        .await
+                        .unwrap_or_else(|err| warn!("{}", err));
+                }
+                Self::notify_brightness(&signal_ctxt, lock.config.brightness)
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+                Self::notify_power_states(&signal_ctxt, &AuraPowerDev::from(&lock.config.enabled))
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+                Self::notify_config_reloaded(&signal_ctxt)
+                    .await
+                    .unwrap_or_else(|err| warn!("{}", err));
+            }
+        })
+        .map_err(|e| info!("CtrlKbdLedTask: config watch not available: {e}"))
+        .ok();
+
+        let ctrl = self.0.clone();
+        tokio::spawn(async move {
+            loop {
+                let secs = ctrl.lock().await.config.led_keepalive_secs;
+                if secs == 0 {
+                    tokio::time::sleep(BACKLIGHT_TIMEOUT_POLL).await;
+                    continue;
+                }
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+                // Skip this round if something else is changing the lighting
+                if let Some(mut lock) = ctrl.try_lock() {
+                    lock.keepalive()
+                        .map_err(|e| error!("CtrlKbdLedTask: keepalive {e}"))
+                        .ok();
+                }
+            }
+        });
+
         let ctrl2 = self.0.clone();
         let ctrl = self.0.lock().await;
         let watch = ctrl.kd_brightness.monitor_brightness()?;
@@ -277,9 +1378,19 @@ impl CtrlTask for CtrlKbdLedZbus {
                 .into_event_stream(&mut buffer)
                 .unwrap()
                 .for_each(|_| async {
-                    if let Some(lock) = ctrl2.try_lock() {
-                        load_save(true, lock);
+                    if let Some(mut lock) = ctrl2.try_lock() {
+                        if let Ok(Some(brightness)) = Self::update_config(&mut lock)
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        {
+                            // Not set by asusd, so most likely the Fn keys
+                            lock.mark_change(ChangeSource::Hotkey);
+                            Self::notify_brightness(&signal_ctxt, brightness)
+                                .await
+                                .unwrap_or_else(|err| warn!("{}", err));
+                        }
                     }
+                    // Sent for every change including those made by asusd
+                    ctrl2.lock().await.send_brightness();
                 })
                 .await;
         });
@@ -292,10 +1403,15 @@ impl CtrlTask for CtrlKbdLedZbus {
 impl crate::Reloadable for CtrlKbdLedZbus {
     async fn reload(&mut self) -> Result<(), RogError> {
         let mut ctrl = self.0.lock().await;
-        debug!("CtrlKbdLedZbus: reloading keyboard mode");
-        ctrl.write_current_config_mode()?;
-        debug!("CtrlKbdLedZbus: reloading power states");
-        ctrl.set_power_states().map_err(|err| warn!("{err}")).ok();
+        debug!(
+            "CtrlKbdLedZbus: reloading keyboard mode and power states, {:?}",
+            ctrl.config.restore_order
+        );
+        ctrl.restore_mode_and_power()?;
+        ctrl.write_secondary_configs()
+            .map_err(|e| warn!("CtrlKbdLedZbus: secondary keyboards {e}"))
+            .ok();
+        ctrl.mark_change(ChangeSource::Boot);
         Ok(())
     }
 }
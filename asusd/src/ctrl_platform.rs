@@ -1,26 +1,50 @@
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{error, info, warn};
-use rog_platform::platform::{AsusPlatform, GpuMode};
-use rog_platform::supported::PlatformSupportedFunctions;
+use rog_platform::error::PlatformError;
+use rog_platform::platform::{
+    AsusPlatform, DgpuPower, GpuMode, GpuStatus, MiniLedLevel, PptLimits, ThermalPolicy,
+};
+use rog_platform::supported::{PlatformSupportedFunctions, UnsupportedReason};
+use rog_profiles::Profile;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::fdo::Error as FdoErr;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use crate::config::Config;
+use crate::ctrl_aura::controller::{CtrlKbdLed, LEDNode};
+use crate::ctrl_profiles::controller::CtrlPlatformProfile;
 use crate::error::RogError;
-use crate::{task_watch_item, CtrlTask, GetSupported};
+use crate::{CtrlTask, Devices, GetSupported};
 
 const ZBUS_PATH: &str = "/org/asuslinux/Platform";
 
+/// Extra time the power source must be stable before `panel_od` is changed for
+/// it, so a flaky charger doesn't thrash the panel
+const PANEL_OD_POWER_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// The eGPU can only be enabled while the dGPU is enabled. Disabling it is
+/// always allowed.
+fn check_egpu_change(enable: bool, dgpu_disabled: bool) -> Result<(), RogError> {
+    if enable && dgpu_disabled {
+        return Err(RogError::UnsafeGpuState(
+            "the eGPU can not be enabled while the dGPU is disabled, enable the dGPU first"
+                .to_owned(),
+        ));
+    }
+    Ok(())
+}
+
 macro_rules! platform_get_value {
     ($self:ident, $property:tt, $prop_name:literal) => {
         concat_idents::concat_idents!(has = has_, $property {
-            if $self.platform.has() {
+            if $self.platform().has() {
                 concat_idents::concat_idents!(get = get_, $property {
-                    $self.platform
+                    $self.platform()
                     .get()
                     .map_err(|err| {
                         warn!("CtrlRogBios: {}: {}", $prop_name, err);
@@ -38,9 +62,9 @@ macro_rules! platform_get_value {
 macro_rules! platform_get_value_if_some {
     ($self:ident, $property:tt, $prop_name:literal, $default:expr) => {
         concat_idents::concat_idents!(has = has_, $property {
-            if $self.platform.has() {
+            if $self.platform().has() {
                 let lock = $self.config.lock().await;
-                Ok(lock.ppt_pl1_spl.unwrap_or($default))
+                Ok(lock.$property.unwrap_or($default))
             } else {
                 error!("CtrlRogBios: {} not supported", $prop_name);
                 return Err(FdoErr::NotSupported(format!("CtrlRogBios: {} not supported", $prop_name)));
@@ -52,11 +76,16 @@ macro_rules! platform_get_value_if_some {
 macro_rules! platform_set_bool {
     ($self:ident, $property:tt, $prop_name:literal, $new_value:expr) => {
         concat_idents::concat_idents!(has = has_, $property {
-            if $self.platform.has() {
+            if $self.platform().has() {
                 concat_idents::concat_idents!(set = set_, $property {
-                    $self.platform.set($new_value).map_err(|err| {
+                    $self.platform().set($new_value).map_err(|err| {
                         error!("CtrlRogBios: {} {err}", $prop_name);
-                        FdoErr::NotSupported(format!("CtrlRogBios: {} {err}", $prop_name))
+                        match err {
+                            PlatformError::WriteNotApplied(_) => {
+                                FdoErr::Failed(format!("CtrlRogBios: {} {err}", $prop_name))
+                            }
+                            _ => FdoErr::NotSupported(format!("CtrlRogBios: {} {err}", $prop_name)),
+                        }
                     })?;
                 });
                 let mut lock = $self.config.lock().await;
@@ -71,65 +100,445 @@ macro_rules! platform_set_bool {
     }
 }
 
-/// Intended only for setting platform object values where the value isn't
-/// retained across boots
-macro_rules! platform_set_with_min_max {
-    ($self:ident, $property:tt, $prop_name:literal, $new_value:expr, $min_value:expr, $max_value:expr) => {
-        if !($min_value..=$max_value).contains(&$new_value) {
-            Err(FdoErr::Failed(
-                format!("CtrlRogBios: {} value not in range {}=..={}", $prop_name, $min_value, $max_value)
-            ))
-        } else {
+/// Set a power limit with the `write_` method of the same name
+macro_rules! platform_set_power_limit {
+    ($self:ident, $property:tt, $prop_name:literal, $new_value:expr) => {
+        concat_idents::concat_idents!(write_fn = write_, $property {
+            $self.write_fn($new_value).await.map_err(|err| {
+                error!("CtrlRogBios: {} {err}", $prop_name);
+                match err {
+                    RogError::NotSupported => {
+                        FdoErr::NotSupported(format!("CtrlRogBios: {} not supported", $prop_name))
+                    }
+                    _ => FdoErr::Failed(format!("CtrlRogBios: {} {err}", $prop_name)),
+                }
+            })
+        })
+    }
+}
+
+/// Generate a `write_` method for a power limit. Intended only for setting
+/// platform object values where the value isn't retained across boots.
+macro_rules! power_limit_writer {
+    ($property:tt, $min_value:expr, $max_value:expr) => {
+        concat_idents::concat_idents!(write_fn = write_, $property {
+            /// Write the limit to the platform and store it in the config.
+            /// Errors if the value is not in range or the limit is not
+            /// supported.
+            pub async fn write_fn(&self, value: u8) -> Result<(), RogError> {
+                check_power_limit(stringify!($property), value, $min_value, $max_value)?;
+                concat_idents::concat_idents!(has = has_, $property {
+                    if !self.platform().has() {
+                        return Err(RogError::NotSupported);
+                    }
+                });
+                concat_idents::concat_idents!(set = set_, $property {
+                    self.platform().set(value)?;
+                });
+                let mut lock = self.config.lock().await;
+                lock.$property = Some(value);
+                lock.write();
+                Ok(())
+            }
+        });
+    };
+}
+
+fn check_power_limit(name: &str, value: u8, min: u8, max: u8) -> Result<(), RogError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(RogError::PowerLimit(name.to_owned(), value, min, max))
+    }
+}
+
+/// Write the stored mini-LED setting and `post_animation_sound` if the
+/// laptop supports them. A stored `mini_led_level` is written in place of the
+/// on/off `mini_led_mode`, which would reduce it to `MultiZone`.
+fn write_stored_settings(platform: &AsusPlatform, config: &Config) {
+    if let Some(level) = config.mini_led_level {
+        platform
+            .set_mini_led_level(level)
+            .map_err(|err| warn!("CtrlRogBios: mini_led_level {err}"))
+            .ok();
+    } else if platform.has_mini_led_mode() {
+        platform
+            .set_mini_led_mode(config.mini_led_mode)
+            .map_err(|err| warn!("CtrlRogBios: mini_led_mode {err}"))
+            .ok();
+    }
+    if platform.has_post_animation_sound() {
+        platform
+            .set_post_animation_sound(config.post_animation_sound)
+            .map_err(|err| warn!("CtrlRogBios: post_animation_sound {err}"))
+            .ok();
+    }
+}
+
+/// The other controllers whose live state `config_matches_hardware` checks.
+/// Either is `None` if that controller is not running.
+#[derive(Clone, Default)]
+pub struct LiveControllers {
+    pub aura: Option<Arc<Mutex<CtrlKbdLed>>>,
+    pub profiles: Option<Arc<Mutex<CtrlPlatformProfile>>>,
+}
+
+/// What asusd expects the hardware to be in right now. Anything `None` is not
+/// compared.
+#[derive(Debug, Default)]
+struct ExpectedState {
+    power_plugged: bool,
+    /// The MUX mode requested this session, else the one booted with
+    gpu_mux_mode: Option<u8>,
+    kbd_brightness: Option<u8>,
+    /// The mode last written to `kbd_rgb_mode`
+    kbd_rgb_mode: Option<u8>,
+    platform_profile: Option<Profile>,
+}
+
+/// Compare the hardware against `config` and `expected`, returning a list of
+/// `(name, matches)` for every value that could be read
+fn hardware_matches(
+    platform: &AsusPlatform,
+    devices: &Devices,
+    config: &Config,
+    expected: &ExpectedState,
+) -> Vec<(String, bool)> {
+    let mut matches = Vec::new();
+
+    macro_rules! push_bool {
+        ($property:tt, $prop_name:literal) => {
+            push_bool!($property, $prop_name, config.$property)
+        };
+        ($property:tt, $prop_name:literal, $expected:expr) => {
             concat_idents::concat_idents!(has = has_, $property {
-                if $self.platform.has() {
-                    concat_idents::concat_idents!(set = set_, $property {
-                        $self.platform.set($new_value).map_err(|err| {
-                            error!("CtrlRogBios: {} {err}", $prop_name);
-                            FdoErr::NotSupported(format!("CtrlRogBios: {} {err}", $prop_name))
-                        })?;
-                    });
-                    let mut lock = $self.config.lock().await;
-                    lock.$property = Some($new_value);
-                    lock.write();
-                } else {
-                    error!("CtrlRogBios: {} not supported", $prop_name);
-                    return Err(FdoErr::NotSupported(format!("CtrlRogBios: {} not supported", $prop_name)));
+                if platform.has() {
+                    concat_idents::concat_idents!(get = get_, $property {
+                        if let Ok(value) = platform.get() {
+                            matches.push(($prop_name.to_owned(), value == $expected));
+                        }
+                    })
                 }
-            });
-            Ok(())
+            })
+        };
+    }
+
+    macro_rules! push_if_some {
+        ($property:tt, $prop_name:literal) => {
+            if let Some(stored) = config.$property {
+                push_bool!($property, $prop_name, stored);
+            }
+        };
+    }
+
+    // `panel_od` is written off on battery if `panel_od_off_on_battery` is set
+    push_bool!(
+        panel_od,
+        "panel_od",
+        config.panel_od_for_power(expected.power_plugged)
+    );
+    push_bool!(mini_led_mode, "mini_led_mode");
+    push_bool!(post_animation_sound, "post_animation_sound");
+    if let Some(mux) = expected.gpu_mux_mode {
+        push_bool!(gpu_mux_mode, "gpu_mux_mode", mux);
+    }
+
+    push_if_some!(ppt_pl1_spl, "ppt_pl1_spl");
+    push_if_some!(ppt_pl2_sppt, "ppt_pl2_sppt");
+    push_if_some!(ppt_fppt, "ppt_fppt");
+    push_if_some!(ppt_apu_sppt, "ppt_apu_sppt");
+    push_if_some!(ppt_platform_sppt, "ppt_platform_sppt");
+    push_if_some!(nv_dynamic_boost, "nv_dynamic_boost");
+    push_if_some!(nv_temp_target, "nv_temp_target");
+
+    if let Some(active) = expected.platform_profile {
+        if platform.has_platform_profile() {
+            if let Ok(profile) = platform.get_platform_profile() {
+                matches.push((
+                    "platform_profile".to_owned(),
+                    profile.parse::<Profile>().ok() == Some(active),
+                ));
+            }
+        }
+    }
+
+    if let Ok(limit) = devices
+        .power()
+        .and_then(|p| p.get_charge_control_end_threshold())
+    {
+        matches.push((
+            "charge_control_end_threshold".to_owned(),
+            limit == config.bat_charge_limit,
+        ));
+    }
+
+    if let Ok(kbd_led) = devices.kbd_led() {
+        if let Some(level) = expected.kbd_brightness {
+            if let Ok(bright) = kbd_led.get_brightness() {
+                matches.push(("kbd_brightness".to_owned(), bright == level));
+            }
+        }
+        // Most kernels only allow `kbd_rgb_mode` to be written
+        if let Some(current_mode) = expected.kbd_rgb_mode {
+            if let Some(mode) = kbd_led
+                .get_kbd_rgb_mode()
+                .ok()
+                .and_then(|buf| buf.get(1).copied())
+            {
+                matches.push(("kbd_rgb_mode".to_owned(), mode == current_mode));
+            }
         }
     }
+
+    matches
 }
 
 #[derive(Clone)]
 pub struct CtrlPlatform {
-    platform: AsusPlatform,
+    /// Shared by every clone so that a refresh after resume reaches all of
+    /// them
+    platform: Arc<RwLock<AsusPlatform>>,
     config: Arc<Mutex<Config>>,
+    signal_context: SignalContext<'static>,
+    /// The `gpu_mux_mode` the system booted with
+    gpu_mux_booted: Option<u8>,
+    /// The last `gpu_mux_mode` requested by a client this session
+    gpu_mux_requested: Arc<Mutex<Option<u8>>>,
+    power_plugged: Arc<AtomicBool>,
+    /// Bumped on each power change so that only the last one within
+    /// `PANEL_OD_POWER_DEBOUNCE` is applied
+    panel_od_power_change: Arc<AtomicU64>,
+    devices: Devices,
+    live: LiveControllers,
 }
 
 impl GetSupported for CtrlPlatform {
     type A = PlatformSupportedFunctions;
 
-    fn get_supported() -> Self::A {
-        let platform = AsusPlatform::new().unwrap_or_default();
+    fn get_supported(devices: &Devices) -> Self::A {
+        let platform = devices.platform().unwrap_or_default();
         platform.into()
     }
+
+    fn unsupported_reasons(supported: &Self::A, devices: &Devices) -> Vec<UnsupportedReason> {
+        supported.unsupported_reasons(devices.platform().is_ok())
+    }
 }
 
 impl CtrlPlatform {
-    pub fn new(config: Arc<Mutex<Config>>) -> Result<Self, RogError> {
-        let platform = AsusPlatform::new()?;
+    pub fn new(
+        config: Arc<Mutex<Config>>,
+        signal_context: SignalContext<'static>,
+        devices: &Devices,
+        live: LiveControllers,
+    ) -> Result<Self, RogError> {
+        let platform = devices.platform()?;
 
         if !platform.has_gpu_mux_mode() {
             info!("G-Sync Switchable Graphics or GPU MUX not detected");
             info!("Standard graphics switching will still work.");
         }
 
-        Ok(CtrlPlatform { platform, config })
+        let gpu_mux_booted = platform.get_gpu_mux_mode().ok();
+        let power_plugged = devices
+            .power()
+            .and_then(|power| power.get_online())
+            .map(|online| online == 1)
+            .unwrap_or(true);
+
+        Ok(CtrlPlatform {
+            platform: Arc::new(RwLock::new(platform)),
+            config,
+            signal_context,
+            gpu_mux_booted,
+            gpu_mux_requested: Arc::new(Mutex::new(None)),
+            power_plugged: Arc::new(AtomicBool::new(power_plugged)),
+            panel_od_power_change: Arc::new(AtomicU64::new(0)),
+            devices: devices.clone(),
+            live,
+        })
+    }
+
+    /// The platform device. A copy is returned so that no lock is held
+    /// across an await.
+    fn platform(&self) -> AsusPlatform {
+        self.platform
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Gather what the hardware should be in from this and the other live
+    /// controllers
+    async fn expected_state(&self) -> ExpectedState {
+        let mut expected = ExpectedState {
+            power_plugged: self.power_plugged.load(Ordering::SeqCst),
+            gpu_mux_mode: self.gpu_mux_requested.lock().await.or(self.gpu_mux_booted),
+            ..Default::default()
+        };
+        if let Some(aura) = &self.live.aura {
+            let aura = aura.lock().await;
+            // The lighting is changed on purpose while dimmed, off for the lid,
+            // or running an effect from asusd
+            let overridden = aura.idle_restore.is_some()
+                || aura.lid_off
+                || aura.thermal_effect_running.load(Ordering::SeqCst)
+                || aura.demo_running.load(Ordering::SeqCst);
+            if !overridden {
+                expected.kbd_brightness = Some(aura.config.brightness as u8);
+                if matches!(aura.led_node, LEDNode::KbdLed(_))
+                    && !aura.per_key_mode_active
+                    && !aura.config.multizone_on
+                {
+                    expected.kbd_rgb_mode = Some(aura.config.current_mode as u8);
+                }
+            }
+        }
+        if let Some(profiles) = &self.live.profiles {
+            expected.platform_profile = Some(profiles.lock().await.profile_config.active_profile);
+        }
+        expected
+    }
+
+    /// Find the platform device again, as it can re-enumerate at a new path
+    /// after some suspend cycles which makes every write fail
+    fn refresh_platform(&self) -> Result<(), RogError> {
+        let platform = self.devices.platform()?;
+        let mut current = self.platform.write().unwrap_or_else(|err| err.into_inner());
+        if current.path() != platform.path() {
+            info!(
+                "CtrlRogBios: platform device moved from {} to {}",
+                current.path().display(),
+                platform.path().display()
+            );
+            *current = platform;
+        }
+        Ok(())
+    }
+
+    /// Write `panel_od` for the current power source without storing it
+    fn apply_panel_od(&self, config: &Config) -> Result<(), RogError> {
+        if self.platform().has_panel_od() {
+            let power_plugged = self.power_plugged.load(Ordering::SeqCst);
+            self.platform()
+                .set_panel_od(config.panel_od_for_power(power_plugged))?;
+        }
+        Ok(())
+    }
+
+    /// Write the stored mini-LED setting and `post_animation_sound` if the
+    /// laptop supports them
+    fn apply_stored_settings(&self, config: &Config) {
+        write_stored_settings(&self.platform(), config);
+    }
+
+    async fn notify_stored_settings(&self) {
+        self.panel_od_changed(&self.signal_context)
+            .await
+            .map_err(|err| warn!("CtrlRogBios: panel_od_changed {err}"))
+            .ok();
+        self.mini_led_mode_changed(&self.signal_context)
+            .await
+            .map_err(|err| warn!("CtrlRogBios: mini_led_mode_changed {err}"))
+            .ok();
+        self.post_animation_sound_changed(&self.signal_context)
+            .await
+            .map_err(|err| warn!("CtrlRogBios: post_animation_sound_changed {err}"))
+            .ok();
+    }
+
+    /// Load and apply `asusd.ron` if it was edited outside of asusd. The
+    /// battery charge limit is read from the file by `CtrlPower` when it is
+    /// next set.
+    async fn reload_config(&self) {
+        let mut lock = self.config.lock().await;
+        let config = match lock.read_if_changed() {
+            Ok(Some(config)) => config,
+            Ok(None) => return,
+            Err(err) => {
+                warn!("CtrlRogBios: config reload {err}");
+                return;
+            }
+        };
+        info!(
+            "CtrlRogBios: reloading {} after it was edited",
+            lock.file_name()
+        );
+        *lock = config;
+        self.apply_panel_od(&lock)
+            .map_err(|err| warn!("CtrlRogBios: panel_od {err}"))
+            .ok();
+        self.apply_stored_settings(&lock);
+        drop(lock);
+
+        self.notify_stored_settings().await;
+        Self::notify_config_reloaded(&self.signal_context)
+            .await
+            .map_err(|err| warn!("CtrlRogBios: notify_config_reloaded {err}"))
+            .ok();
+    }
+
+    /// Same as `task_watch_item!(panel_od platform)` except that the value is
+    /// not stored while `panel_od` is forced off on battery, so that the
+    /// preferred setting can be restored on AC
+    async fn watch_panel_od(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        use zbus::export::futures_util::StreamExt;
+
+        let ctrl = self.clone();
+        match self.platform().monitor_panel_od() {
+            Ok(watch) => {
+                tokio::spawn(async move {
+                    let mut buffer = [0; 32];
+                    watch
+                        .into_event_stream(&mut buffer)
+                        .unwrap()
+                        .for_each(|_| async {
+                            if let Ok(value) = ctrl.panel_od() {
+                                Self::panel_od_changed(&ctrl, &signal_ctxt).await.ok();
+                                if let Some(mut lock) = ctrl.config.try_lock() {
+                                    let forced_off = lock.panel_od_off_on_battery
+                                        && !ctrl.power_plugged.load(Ordering::SeqCst);
+                                    if !forced_off {
+                                        lock.panel_od = value;
+                                        lock.write();
+                                    }
+                                }
+                            }
+                        })
+                        .await;
+                });
+            }
+            Err(e) => info!(
+                "inotify watch failed: {}. You can ignore this if your device does not support \
+                 the feature",
+                e
+            ),
+        }
+        Ok(())
+    }
+
+    /// Enable or disable the eGPU, refusing if the dGPU is disabled as that
+    /// combination can hang the machine
+    pub fn set_egpu(&self, enable: bool) -> Result<(), RogError> {
+        if !self.platform().has_egpu_enable() {
+            return Err(RogError::NotSupported);
+        }
+        let dgpu_disabled = if self.platform().has_dgpu_disable() {
+            self.platform().get_dgpu_disable()?
+        } else {
+            false
+        };
+        check_egpu_change(enable, dgpu_disabled)?;
+        self.platform().set_egpu_enable(enable)?;
+        info!("eGPU enabled: {enable}");
+        Ok(())
     }
 
     fn set_gfx_mode(&self, mode: GpuMode) -> Result<(), RogError> {
-        self.platform.set_gpu_mux_mode(mode.to_mux_attr())?;
+        if matches!(mode, GpuMode::Error | GpuMode::NotSupported) {
+            return Err(RogError::NotSupported);
+        }
+        self.platform().set_gpu_mux_mode(mode.to_mux_attr())?;
         // self.update_initramfs(enable)?;
         if mode == GpuMode::Discrete {
             info!("Set system-level graphics mode: Dedicated Nvidia");
@@ -149,7 +558,7 @@ impl CtrlPlatform {
         macro_rules! push_name {
             ($property:tt, $prop_name:literal) => {
                 concat_idents::concat_idents!(has = has_, $property {
-                    if self.platform.has() {
+                    if self.platform().has() {
                         supported.push($prop_name.to_owned());
                     }
                 })
@@ -174,21 +583,84 @@ impl CtrlPlatform {
         supported
     }
 
+    /// Compare each stored config value, and the live state of the lighting
+    /// and profile controllers, against the hardware. Returns a list of
+    /// `(name, matches)`, any `false` means the hardware has diverged and a
+    /// reload may be required. Values that are not readable are skipped.
+    async fn config_matches_hardware(&self) -> Vec<(String, bool)> {
+        let expected = self.expected_state().await;
+        let config = self.config.lock().await;
+        hardware_matches(&self.platform(), &self.devices, &config, &expected)
+    }
+
     #[dbus_interface(property)]
     fn gpu_mux_mode(&self) -> Result<u8, FdoErr> {
-        self.platform.get_gpu_mux_mode().map_err(|err| {
+        self.platform().get_gpu_mux_mode().map_err(|err| {
             warn!("CtrlRogBios: set_gpu_mux_mode {err}");
             FdoErr::NotSupported("CtrlRogBios: set_gpu_mux_mode not supported".to_owned())
         })
     }
 
+    /// The MUX, dGPU, and eGPU state in one read, for status widgets.
+    /// `NotifyGpuStatus` is emitted when any of the three nodes change.
+    fn gpu_status(&self) -> GpuStatus {
+        self.platform().get_gpu_status()
+    }
+
+    /// If the dGPU is powered, suspended by runtime power management, or off.
+    /// Useful for checking that the dGPU sleeps when it is not in use.
+    fn dgpu_power(&self) -> Result<DgpuPower, FdoErr> {
+        self.platform().get_dgpu_power().map_err(|err| {
+            warn!("CtrlRogBios: dgpu_power {err}");
+            FdoErr::Failed(format!("CtrlRogBios: dgpu_power {err}"))
+        })
+    }
+
+    #[dbus_interface(signal)]
+    async fn notify_gpu_status(
+        signal_ctxt: &SignalContext<'_>,
+        status: GpuStatus,
+    ) -> zbus::Result<()> {
+    }
+
+    /// Emitted after `asusd.ron` was edited outside of asusd and the new
+    /// settings applied
+    #[dbus_interface(signal)]
+    async fn notify_config_reloaded(signal_ctxt: &SignalContext<'_>) -> zbus::Result<()> {}
+
+    /// If a `gpu_mux_mode` different to the one booted with has been set and a
+    /// reboot is required for it to take effect
+    #[dbus_interface(property)]
+    async fn gpu_mux_mode_pending(&self) -> bool {
+        let requested = *self.gpu_mux_requested.lock().await;
+        match (self.gpu_mux_booted, requested) {
+            (Some(booted), Some(requested)) => booted != requested,
+            _ => false,
+        }
+    }
+
+    /// If the GPU MUX can be switched. MUX-less laptops may expose a read-only
+    /// `gpu_mux_mode`
+    fn gpu_mux_switchable(&self) -> bool {
+        self.platform().gpu_mux_switchable()
+    }
+
     #[dbus_interface(property)]
     async fn set_gpu_mux_mode(&mut self, mode: u8) -> Result<(), FdoErr> {
-        if self.platform.has_gpu_mux_mode() {
+        if self.platform().has_gpu_mux_mode() {
             self.set_gfx_mode(mode.into()).map_err(|err| {
                 warn!("CtrlRogBios: set_gpu_mux_mode {}", err);
                 FdoErr::Failed(format!("CtrlRogBios: set_gpu_mux_mode: {err}"))
-            })
+            })?;
+            let was_pending = self.gpu_mux_mode_pending().await;
+            *self.gpu_mux_requested.lock().await = Some(mode);
+            if self.gpu_mux_mode_pending().await != was_pending {
+                self.gpu_mux_mode_pending_changed(&self.signal_context)
+                    .await
+                    .map_err(|err| warn!("CtrlRogBios: gpu_mux_mode_pending_changed {err}"))
+                    .ok();
+            }
+            Ok(())
         } else {
             Err(FdoErr::NotSupported(
                 "CtrlRogBios: set_gpu_mux_mode not supported".to_owned(),
@@ -227,7 +699,107 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_mini_led_mode(&mut self, on: bool) -> Result<(), FdoErr> {
-        platform_set_bool!(self, mini_led_mode, "mini_led_mode", on)
+        platform_set_bool!(self, mini_led_mode, "mini_led_mode", on)?;
+        // The on/off toggle replaces any level that was set
+        let mut lock = self.config.lock().await;
+        if lock.mini_led_level.take().is_some() {
+            lock.write();
+        }
+        Ok(())
+    }
+
+    /// The mini-LED levels this panel supports, empty if none
+    fn mini_led_levels(&self) -> Vec<MiniLedLevel> {
+        self.platform().mini_led_levels()
+    }
+
+    fn mini_led_level(&self) -> Result<MiniLedLevel, FdoErr> {
+        self.platform().get_mini_led_level().map_err(|err| {
+            warn!("CtrlRogBios: mini_led_level {err}");
+            FdoErr::NotSupported(format!("CtrlRogBios: mini_led_level {err}"))
+        })
+    }
+
+    /// Set the mini-LED level and store it. Panels that only support on/off
+    /// accept `Off` and `MultiZone`.
+    async fn set_mini_led_level(&mut self, level: MiniLedLevel) -> Result<(), FdoErr> {
+        self.platform().set_mini_led_level(level).map_err(|err| {
+            warn!("CtrlRogBios: set_mini_led_level {err}");
+            FdoErr::NotSupported(format!("CtrlRogBios: set_mini_led_level {level:?} {err}"))
+        })?;
+        let mut lock = self.config.lock().await;
+        lock.mini_led_level = Some(level);
+        lock.mini_led_mode = level != MiniLedLevel::Off;
+        lock.write();
+        drop(lock);
+        self.mini_led_mode_changed(&self.signal_context)
+            .await
+            .map_err(|err| warn!("CtrlRogBios: mini_led_mode_changed {err}"))
+            .ok();
+        Ok(())
+    }
+
+    /// Read `throttle_thermal_policy` directly, without going through the
+    /// platform profile
+    fn thermal_policy(&self) -> Result<ThermalPolicy, FdoErr> {
+        if !self.platform().has_throttle_thermal_policy() {
+            return Err(FdoErr::NotSupported(
+                "CtrlRogBios: throttle_thermal_policy not supported".to_owned(),
+            ));
+        }
+        self.platform().get_thermal_policy().map_err(|err| {
+            warn!("CtrlRogBios: thermal_policy {err}");
+            FdoErr::Failed(format!("CtrlRogBios: thermal_policy {err}"))
+        })
+    }
+
+    /// Write `throttle_thermal_policy` directly, for laptops where changing
+    /// the platform profile does not change the thermal policy. Where the
+    /// two are tied in-kernel the active profile follows this change. The
+    /// policy is not stored.
+    async fn set_thermal_policy(&self, policy: ThermalPolicy) -> Result<(), FdoErr> {
+        if !self.platform().has_throttle_thermal_policy() {
+            return Err(FdoErr::NotSupported(
+                "CtrlRogBios: throttle_thermal_policy not supported".to_owned(),
+            ));
+        }
+        self.platform().set_thermal_policy(policy).map_err(|err| {
+            warn!("CtrlRogBios: set_thermal_policy {err}");
+            FdoErr::Failed(format!("CtrlRogBios: set_thermal_policy {policy:?} {err}"))
+        })
+    }
+
+    /// Read `asusd.ron` again and apply it, the same as at startup. For
+    /// scripts that edit the file.
+    #[dbus_interface(name = "Reload")]
+    async fn reload_from_config(&mut self) -> Result<(), FdoErr> {
+        self.config.lock().await.read();
+        crate::Reloadable::reload(self).await.map_err(|err| {
+            warn!("CtrlRogBios: reload {err}");
+            FdoErr::Failed(format!("CtrlRogBios: reload {err}"))
+        })?;
+
+        self.notify_stored_settings().await;
+        Ok(())
+    }
+
+    /// Reset the platform settings to their defaults and apply them. Panel
+    /// overdrive, mini-LED and the POST sound are turned off. Stored power
+    /// limits are cleared so the firmware defaults are used from the next
+    /// boot. The charge limit and other settings are kept.
+    async fn reset_platform_defaults(&mut self) -> Result<(), FdoErr> {
+        let mut lock = self.config.lock().await;
+        lock.reset_platform();
+        lock.write();
+        self.apply_panel_od(&lock).map_err(|err| {
+            warn!("CtrlRogBios: reset panel_od {err}");
+            FdoErr::Failed(format!("CtrlRogBios: reset panel_od {err}"))
+        })?;
+        self.apply_stored_settings(&lock);
+        drop(lock);
+
+        self.notify_stored_settings().await;
+        Ok(())
     }
 
     #[dbus_interface(property)]
@@ -240,6 +812,56 @@ impl CtrlPlatform {
         platform_get_value!(self, egpu_enable, "egpu_enable")
     }
 
+    /// Enable or disable the eGPU after checking the dGPU state. Returns an
+    /// error describing why if the change is unsafe.
+    async fn set_egpu_safe(&self, enable: bool) -> Result<(), FdoErr> {
+        self.set_egpu(enable).map_err(|err| {
+            warn!("CtrlRogBios: set_egpu_safe {err}");
+            match err {
+                RogError::NotSupported => {
+                    FdoErr::NotSupported("CtrlRogBios: egpu_enable not supported".to_owned())
+                }
+                _ => FdoErr::Failed(format!("CtrlRogBios: set_egpu_safe: {err}")),
+            }
+        })
+    }
+
+    /// Get all of the PPT and Nvidia power limits applied. Limits that are not
+    /// supported or have not been set are `0`.
+    async fn power_limits(&self) -> PptLimits {
+        let config = self.config.lock().await;
+        PptLimits {
+            ppt_pl1_spl: config
+                .ppt_pl1_spl
+                .filter(|_| self.platform().has_ppt_pl1_spl())
+                .into(),
+            ppt_pl2_sppt: config
+                .ppt_pl2_sppt
+                .filter(|_| self.platform().has_ppt_pl2_sppt())
+                .into(),
+            ppt_fppt: config
+                .ppt_fppt
+                .filter(|_| self.platform().has_ppt_fppt())
+                .into(),
+            ppt_apu_sppt: config
+                .ppt_apu_sppt
+                .filter(|_| self.platform().has_ppt_apu_sppt())
+                .into(),
+            ppt_platform_sppt: config
+                .ppt_platform_sppt
+                .filter(|_| self.platform().has_ppt_platform_sppt())
+                .into(),
+            nv_dynamic_boost: config
+                .nv_dynamic_boost
+                .filter(|_| self.platform().has_nv_dynamic_boost())
+                .into(),
+            nv_temp_target: config
+                .nv_temp_target
+                .filter(|_| self.platform().has_nv_temp_target())
+                .into(),
+        }
+    }
+
     /// ************************************************************************
     #[dbus_interface(property)]
     async fn ppt_pl1_spl(&self) -> Result<u8, FdoErr> {
@@ -248,7 +870,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_ppt_pl1_spl(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, ppt_pl1_spl, "ppt_pl1_spl", value, 5, 250)
+        platform_set_power_limit!(self, ppt_pl1_spl, "ppt_pl1_spl", value)
     }
 
     #[dbus_interface(property)]
@@ -258,7 +880,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_ppt_pl2_sppt(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, ppt_pl2_sppt, "ppt_pl2_sppt", value, 5, 250)
+        platform_set_power_limit!(self, ppt_pl2_sppt, "ppt_pl2_sppt", value)
     }
 
     #[dbus_interface(property)]
@@ -268,7 +890,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_ppt_fppt(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, ppt_fppt, "ppt_fppt", value, 5, 250)
+        platform_set_power_limit!(self, ppt_fppt, "ppt_fppt", value)
     }
 
     #[dbus_interface(property)]
@@ -278,7 +900,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_ppt_apu_sppt(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, ppt_apu_sppt, "ppt_apu_sppt", value, 5, 130)
+        platform_set_power_limit!(self, ppt_apu_sppt, "ppt_apu_sppt", value)
     }
 
     #[dbus_interface(property)]
@@ -288,7 +910,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_ppt_platform_sppt(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, ppt_platform_sppt, "ppt_platform_sppt", value, 5, 130)
+        platform_set_power_limit!(self, ppt_platform_sppt, "ppt_platform_sppt", value)
     }
 
     #[dbus_interface(property)]
@@ -298,7 +920,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_nv_dynamic_boost(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, nv_dynamic_boost, "nv_dynamic_boost", value, 5, 25)
+        platform_set_power_limit!(self, nv_dynamic_boost, "nv_dynamic_boost", value)
     }
 
     #[dbus_interface(property)]
@@ -308,7 +930,7 @@ impl CtrlPlatform {
 
     #[dbus_interface(property)]
     async fn set_nv_temp_target(&mut self, value: u8) -> Result<(), FdoErr> {
-        platform_set_with_min_max!(self, nv_temp_target, "nv_temp_target", value, 5, 87)
+        platform_set_power_limit!(self, nv_temp_target, "nv_temp_target", value)
     }
 }
 
@@ -322,20 +944,67 @@ impl crate::ZbusRun for CtrlPlatform {
 #[async_trait]
 impl crate::Reloadable for CtrlPlatform {
     async fn reload(&mut self) -> Result<(), RogError> {
-        if self.platform.has_panel_od() {
-            let p = if let Some(lock) = self.config.try_lock() {
-                lock.panel_od
-            } else {
-                false
-            };
-            self.platform.set_panel_od(p)?;
-        }
+        let lock = self.config.lock().await;
+        self.apply_panel_od(&lock)?;
+        self.apply_stored_settings(&lock);
         Ok(())
     }
 }
 
 impl CtrlPlatform {
-    task_watch_item!(panel_od platform);
+    power_limit_writer!(ppt_pl1_spl, 5, 250);
+    power_limit_writer!(ppt_pl2_sppt, 5, 250);
+    power_limit_writer!(ppt_fppt, 5, 250);
+    power_limit_writer!(ppt_apu_sppt, 5, 130);
+    power_limit_writer!(ppt_platform_sppt, 5, 130);
+    power_limit_writer!(nv_dynamic_boost, 5, 25);
+    power_limit_writer!(nv_temp_target, 5, 87);
+
+    /// The syspath of the platform device
+    pub fn device_path(&self) -> String {
+        self.platform().path().display().to_string()
+    }
+
+    /// Emit `notify_gpu_status` when any of the GPU nodes change. The MUX
+    /// node is fine to watch here as nothing is written back to it.
+    async fn watch_gpu_status(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        use zbus::export::futures_util::StreamExt;
+
+        for (name, watch) in [
+            ("dgpu_disable", self.platform().monitor_dgpu_disable()),
+            ("egpu_enable", self.platform().monitor_egpu_enable()),
+            ("gpu_mux_mode", self.platform().monitor_gpu_mux_mode()),
+        ] {
+            match watch {
+                Ok(watch) => {
+                    let ctrl = self.clone();
+                    let signal_ctxt = signal_ctxt.clone();
+                    tokio::spawn(async move {
+                        let mut buffer = [0; 32];
+                        watch
+                            .into_event_stream(&mut buffer)
+                            .unwrap()
+                            .for_each(|_| async {
+                                Self::notify_gpu_status(
+                                    &signal_ctxt,
+                                    ctrl.platform().get_gpu_status(),
+                                )
+                                .await
+                                .ok();
+                            })
+                            .await;
+                    });
+                }
+                Err(e) => info!(
+                    "inotify watch of {name} failed: {}. You can ignore this if your device does \
+                     not support the feature",
+                    e
+                ),
+            }
+        }
+        Ok(())
+    }
+
     // task_watch_item!(dgpu_disable platform);
     // task_watch_item!(egpu_enable platform);
     // task_watch_item!(mini_led_mode platform);
@@ -352,21 +1021,26 @@ impl CtrlTask for CtrlPlatform {
     async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
         let platform1 = self.clone();
         let platform2 = self.clone();
+        let platform3 = self.clone();
         self.create_sys_event_tasks(
             move |sleeping| {
                 let platform1 = platform1.clone();
                 async move {
                     info!("CtrlRogBios reloading panel_od");
                     let lock = platform1.config.lock().await;
-                    if !sleeping && platform1.platform.has_panel_od() {
+                    if !sleeping {
                         platform1
-                            .platform
-                            .set_panel_od(lock.panel_od)
+                            .refresh_platform()
+                            .map_err(|err| warn!("CtrlRogBios: platform device {}", err))
+                            .ok();
+                        platform1
+                            .apply_panel_od(&lock)
                             .map_err(|err| {
                                 warn!("CtrlCharge: panel_od {}", err);
                                 err
                             })
                             .ok();
+                        platform1.apply_stored_settings(&lock);
                     }
                 }
             },
@@ -375,10 +1049,9 @@ impl CtrlTask for CtrlPlatform {
                 async move {
                     info!("CtrlRogBios reloading panel_od");
                     let lock = platform2.config.lock().await;
-                    if !shutting_down && platform2.platform.has_panel_od() {
+                    if !shutting_down {
                         platform2
-                            .platform
-                            .set_panel_od(lock.panel_od)
+                            .apply_panel_od(&lock)
                             .map_err(|err| {
                                 warn!("CtrlCharge: panel_od {}", err);
                                 err
@@ -391,14 +1064,50 @@ impl CtrlTask for CtrlPlatform {
                 // on lid change
                 async move {}
             },
-            move |_power_plugged| {
-                // power change
-                async move {}
+            move |power_plugged| {
+                let platform3 = platform3.clone();
+                async move {
+                    platform3
+                        .power_plugged
+                        .store(power_plugged, Ordering::SeqCst);
+                    if !platform3.platform().has_panel_od()
+                        || !platform3.config.lock().await.panel_od_off_on_battery
+                    {
+                        return;
+                    }
+                    // Only the last power change within the debounce is applied
+                    let change = platform3
+                        .panel_od_power_change
+                        .fetch_add(1, Ordering::SeqCst)
+                        + 1;
+                    tokio::spawn(async move {
+                        tokio::time::sleep(PANEL_OD_POWER_DEBOUNCE).await;
+                        if platform3.panel_od_power_change.load(Ordering::SeqCst) != change {
+                            return;
+                        }
+                        let lock = platform3.config.lock().await;
+                        info!("CtrlRogBios: setting panel_od for power plugged: {power_plugged}");
+                        platform3
+                            .apply_panel_od(&lock)
+                            .map_err(|err| warn!("CtrlRogBios: panel_od {}", err))
+                            .ok();
+                    });
+                }
             },
         )
         .await;
 
         self.watch_panel_od(signal_ctxt.clone()).await?;
+        self.watch_gpu_status(signal_ctxt.clone()).await?;
+
+        let platform = self.clone();
+        let file_name = self.config.lock().await.file_name();
+        crate::watch_config_file(file_name, move || {
+            let platform = platform.clone();
+            async move { platform.reload_config().await }
+        })
+        .map_err(|err| info!("CtrlRogBios: config watch not available: {err}"))
+        .ok();
         // self.watch_dgpu_disable(signal_ctxt.clone()).await?;
         // self.watch_egpu_enable(signal_ctxt.clone()).await?;
         // self.watch_mini_led_mode(signal_ctxt.clone()).await?;
@@ -409,3 +1118,117 @@ impl CtrlTask for CtrlPlatform {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rog_platform::platform::{AsusPlatform, MiniLedLevel};
+    use rog_profiles::Profile;
+
+    use super::{
+        check_egpu_change, check_power_limit, hardware_matches, write_stored_settings,
+        ExpectedState,
+    };
+    use crate::config::Config;
+    use crate::error::RogError;
+    use crate::Devices;
+
+    #[test]
+    fn egpu_dgpu_state_matrix() {
+        // (enable eGPU, dGPU disabled, allowed)
+        for (enable, dgpu_disabled, allowed) in [
+            (true, false, true),
+            (true, true, false),
+            (false, false, true),
+            (false, true, true),
+        ] {
+            let res = check_egpu_change(enable, dgpu_disabled);
+            assert_eq!(
+                res.is_ok(),
+                allowed,
+                "enable: {enable}, dgpu_disabled: {dgpu_disabled}"
+            );
+            if !allowed {
+                assert!(matches!(res, Err(RogError::UnsafeGpuState(_))));
+            }
+        }
+    }
+
+    #[test]
+    fn power_limit_range() {
+        assert!(check_power_limit("nv_dynamic_boost", 5, 5, 25).is_ok());
+        assert!(check_power_limit("nv_dynamic_boost", 25, 5, 25).is_ok());
+        assert_eq!(
+            check_power_limit("nv_dynamic_boost", 26, 5, 25)
+                .unwrap_err()
+                .to_string(),
+            "Invalid nv_dynamic_boost, not in range 5-25: 26"
+        );
+        assert!(matches!(
+            check_power_limit("ppt_pl1_spl", 4, 5, 250),
+            Err(RogError::PowerLimit(_, 4, 5, 250))
+        ));
+    }
+
+    #[test]
+    fn stored_mini_led_level_kept() {
+        let platform = AsusPlatform::simulated();
+        let mut config = Config {
+            mini_led_mode: true,
+            mini_led_level: Some(MiniLedLevel::MultiZoneStrong),
+            ..Default::default()
+        };
+        write_stored_settings(&platform, &config);
+        assert_eq!(
+            platform.get_mini_led_level().unwrap(),
+            MiniLedLevel::MultiZoneStrong
+        );
+
+        // Reset to defaults
+        config.reset_platform();
+        write_stored_settings(&platform, &config);
+        assert_eq!(platform.get_mini_led_level().unwrap(), MiniLedLevel::Off);
+    }
+
+    #[test]
+    fn hardware_matches_live_state() {
+        let devices = Devices::simulated();
+        let platform = devices.platform().unwrap();
+        let kbd_led = devices.kbd_led().unwrap();
+        kbd_led.set_kbd_rgb_mode(&[1, 2, 255, 0, 0, 0]).unwrap();
+        let config = Config {
+            bat_charge_limit: 80,
+            ..Default::default()
+        };
+        let expected = ExpectedState {
+            power_plugged: true,
+            gpu_mux_mode: Some(1),
+            kbd_brightness: Some(2),
+            kbd_rgb_mode: Some(2),
+            platform_profile: Some(Profile::Balanced),
+        };
+
+        let matches = hardware_matches(&platform, &devices, &config, &expected);
+        for name in [
+            "gpu_mux_mode",
+            "platform_profile",
+            "kbd_brightness",
+            "kbd_rgb_mode",
+        ] {
+            assert!(
+                matches.iter().any(|(n, _)| n == name),
+                "{name} not compared"
+            );
+        }
+        assert!(matches.iter().all(|(_, m)| *m), "{matches:?}");
+
+        // A MUX change made outside of asusd, and a different profile
+        platform.set_gpu_mux_mode(0).unwrap();
+        platform.set_platform_profile("performance").unwrap();
+        let diverged: Vec<String> = hardware_matches(&platform, &devices, &config, &expected)
+            .into_iter()
+            .filter(|(_, m)| !m)
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(diverged, ["gpu_mux_mode", "platform_profile"]);
+    }
+}
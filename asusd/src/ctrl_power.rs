@@ -1,42 +1,79 @@
-use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{error, info, warn};
-use rog_platform::power::AsusPower;
-use rog_platform::supported::ChargeSupportedFunctions;
+use rog_platform::power::{AsusPower, BatteryHealth, PowerSource};
+use rog_platform::supported::{ChargeSupportedFunctions, UnsupportedReason};
 use systemd_zbus::{ManagerProxy as SystemdProxy, Mode, UnitFileState};
-use tokio::time::sleep;
+use tokio::process::Command;
+use tokio::time::{sleep, timeout};
 use zbus::export::futures_util::lock::Mutex;
 use zbus::{dbus_interface, Connection, SignalContext};
 
 use crate::config::Config;
 use crate::error::RogError;
-use crate::{CtrlTask, GetSupported};
+use crate::{CtrlTask, Devices, GetSupported};
 
 const ZBUS_PATH: &str = "/org/asuslinux/Power";
 const NVIDIA_POWERD: &str = "nvidia-powerd.service";
+const CHARGE_LIMIT_MIN: u8 = 20;
+const CHARGE_LIMIT_MAX: u8 = 100;
+
+fn check_charge_limit(limit: u8) -> Result<(), RogError> {
+    if (CHARGE_LIMIT_MIN..=CHARGE_LIMIT_MAX).contains(&limit) {
+        Ok(())
+    } else {
+        Err(RogError::ChargeLimit(limit))
+    }
+}
+
+/// Bring a stored charge limit back into range, such as one edited by hand
+fn clamp_charge_limit(limit: u8) -> u8 {
+    limit.clamp(CHARGE_LIMIT_MIN, CHARGE_LIMIT_MAX)
+}
 
 impl GetSupported for CtrlPower {
     type A = ChargeSupportedFunctions;
 
-    fn get_supported() -> Self::A {
+    fn get_supported(devices: &Devices) -> Self::A {
         ChargeSupportedFunctions {
-            charge_level_set: if let Ok(power) = AsusPower::new() {
+            charge_level_set: if let Ok(power) = devices.power() {
                 power.has_charge_control_end_threshold()
             } else {
                 false
             },
         }
     }
+
+    fn unsupported_reasons(supported: &Self::A, devices: &Devices) -> Vec<UnsupportedReason> {
+        if supported.charge_level_set {
+            return Vec::new();
+        }
+        let reason = if devices.power().is_ok() {
+            "`charge_control_end_threshold` is not exposed by the battery. It needs a newer \
+             kernel or is not available on this laptop"
+        } else {
+            "No ASUS battery power supply was found"
+        };
+        vec![UnsupportedReason::new(
+            "charge_ctrl.charge_level_set",
+            reason,
+        )]
+    }
 }
 
 #[derive(Clone)]
 pub struct CtrlPower {
     power: AsusPower,
     config: Arc<Mutex<Config>>,
+    /// Charging to 100% until AC is unplugged. Not stored so that it does not
+    /// persist across a reboot.
+    full_charge_once: Arc<AtomicBool>,
+    /// The daemon's system bus connection, for talking to systemd
+    connection: Connection,
 }
 
 #[dbus_interface(name = "org.asuslinux.Daemon")]
@@ -46,22 +83,42 @@ impl CtrlPower {
         #[zbus(signal_context)] ctxt: SignalContext<'_>,
         limit: u8,
     ) -> zbus::fdo::Result<()> {
-        if !(20..=100).contains(&limit) {
-            return Err(RogError::ChargeLimit(limit))?;
-        }
-        self.set(limit)
-            .map_err(|err| {
-                warn!("CtrlCharge: set_limit {}", err);
-                err
-            })
-            .ok();
+        self.set(limit).map_err(|err| {
+            warn!("CtrlCharge: set_limit {}", err);
+            err
+        })?;
+        self.full_charge_once.store(false, Ordering::SeqCst);
         Self::notify_charge_control_end_threshold(&ctxt, limit)
             .await
             .ok();
         Ok(())
     }
 
+    /// Charge to 100% once. The stored charge limit is restored when AC is
+    /// next unplugged, or on reboot.
+    async fn charge_to_full_once(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        self.power
+            .set_charge_control_end_threshold(100)
+            .map_err(|err| {
+                warn!("CtrlCharge: charge_to_full_once {}", err);
+                RogError::from(err)
+            })?;
+        self.full_charge_once.store(true, Ordering::SeqCst);
+        info!("Battery charge limit: 100 until AC is unplugged");
+        Self::notify_charge_control_end_threshold(&ctxt, 100)
+            .await
+            .ok();
+        Ok(())
+    }
+
     fn charge_control_end_threshold(&self) -> u8 {
+        if self.full_charge_once.load(Ordering::SeqCst) {
+            // Don't store the temporary limit
+            return self.power.get_charge_control_end_threshold().unwrap_or(100);
+        }
         loop {
             if let Some(mut config) = self.config.try_lock() {
                 let limit = self
@@ -82,6 +139,45 @@ impl CtrlPower {
         }
     }
 
+    /// Stop `nvidia-powerd` while on battery and start it again on AC. If on
+    /// battery this takes effect immediately, otherwise on the next unplug.
+    async fn set_disable_nvidia_powerd_on_battery(
+        &mut self,
+        disable: bool,
+    ) -> zbus::fdo::Result<()> {
+        {
+            let mut config = self.config.lock().await;
+            config.read();
+            config.disable_nvidia_powerd_on_battery = disable;
+            config.write();
+        }
+        if matches!(self.power.get_online(), Ok(0)) {
+            let sysd = SystemdProxy::new(&self.connection).await?;
+            // If no longer managed it is started again, as it would be without asusd
+            do_nvidia_powerd_action(&sysd, !disable).await;
+        }
+        Ok(())
+    }
+
+    async fn disable_nvidia_powerd_on_battery(&self) -> bool {
+        self.config.lock().await.disable_nvidia_powerd_on_battery
+    }
+
+    /// The charge, cycle count and wear of the battery. Multi-battery
+    /// laptops report the ASUS battery that the charge limit is set on.
+    fn battery_health(&self) -> zbus::fdo::Result<BatteryHealth> {
+        self.power.battery_health().map_err(|err| {
+            warn!("CtrlCharge: battery_health {}", err);
+            zbus::fdo::Error::NotSupported(format!("battery_health: {err}"))
+        })
+    }
+
+    /// Whether the laptop is on AC or battery. `notify_power_source` is sent
+    /// when this changes.
+    fn power_source(&self) -> PowerSource {
+        PowerSource::from_online(self.power.get_online().unwrap_or(0))
+    }
+
     fn mains_online(&self) -> bool {
         if self.power.has_online() {
             if let Ok(v) = self.power.get_online() {
@@ -99,6 +195,10 @@ impl CtrlPower {
 
     #[dbus_interface(signal)]
     async fn notify_mains_online(ctxt: &SignalContext<'_>, on: bool) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn notify_power_source(ctxt: &SignalContext<'_>, source: PowerSource)
+        -> zbus::Result<()>;
 }
 
 #[async_trait]
@@ -113,7 +213,23 @@ impl crate::Reloadable for CtrlPower {
     async fn reload(&mut self) -> Result<(), RogError> {
         if let Some(mut config) = self.config.try_lock() {
             config.read();
-            self.set(config.bat_charge_limit)?;
+            let limit = clamp_charge_limit(config.bat_charge_limit);
+            if limit != config.bat_charge_limit {
+                warn!(
+                    "CtrlCharge: stored charge limit {} is out of range, using {limit}",
+                    config.bat_charge_limit
+                );
+                config.bat_charge_limit = limit;
+                config.write();
+            }
+            self.set(limit)?;
+
+            if config.disable_nvidia_powerd_on_battery {
+                if let Ok(value) = self.power.get_online() {
+                    let sysd = SystemdProxy::new(&self.connection).await?;
+                    do_nvidia_powerd_action(&sysd, value == 1).await;
+                }
+            }
         }
         Ok(())
     }
@@ -122,17 +238,21 @@ impl crate::Reloadable for CtrlPower {
 impl CtrlPower {
     // task_watch_item!(charge_control_end_threshold power);
 
-    pub fn new(config: Arc<Mutex<Config>>) -> Result<Self, RogError> {
+    pub fn new(
+        config: Arc<Mutex<Config>>,
+        connection: &Connection,
+        devices: &Devices,
+    ) -> Result<Self, RogError> {
         Ok(CtrlPower {
-            power: AsusPower::new()?,
+            power: devices.power()?,
             config,
+            full_charge_once: Arc::new(AtomicBool::new(false)),
+            connection: connection.clone(),
         })
     }
 
     pub(super) fn set(&self, limit: u8) -> Result<(), RogError> {
-        if !(20..=100).contains(&limit) {
-            return Err(RogError::ChargeLimit(limit));
-        }
+        check_charge_limit(limit)?;
 
         self.power.set_charge_control_end_threshold(limit)?;
 
@@ -146,6 +266,22 @@ impl CtrlPower {
 
         Ok(())
     }
+
+    /// Restore the stored charge limit if a one-shot full charge was active.
+    /// Returns the restored limit.
+    async fn end_full_charge_once(&self) -> Option<u8> {
+        if !self.full_charge_once.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        let limit = self.config.lock().await.bat_charge_limit;
+        self.set(limit)
+            .map_err(|err| {
+                warn!("CtrlCharge: set_limit {}", err);
+                err
+            })
+            .ok();
+        Some(limit)
+    }
 }
 
 #[async_trait]
@@ -155,28 +291,31 @@ impl CtrlTask for CtrlPower {
     }
 
     async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
-        let conn = zbus::Connection::system().await?;
-        let sysd1 = SystemdProxy::new(&conn).await?;
+        let sysd1 = SystemdProxy::new(&self.connection).await?;
         let sysd2 = sysd1.clone();
         let sysd3 = sysd1.clone();
 
         let power1 = self.clone();
         let power2 = self.clone();
+        let power3 = self.clone();
+        let signal_ctxt1 = signal_ctxt.clone();
         self.create_sys_event_tasks(
             move |sleeping| {
                 let power = power1.clone();
                 let sysd = sysd1.clone();
                 async move {
                     if !sleeping {
-                        info!("CtrlCharge reloading charge limit");
                         let lock = power.config.lock().await;
-                        power
-                            .set(lock.bat_charge_limit)
-                            .map_err(|err| {
-                                warn!("CtrlCharge: set_limit {}", err);
-                                err
-                            })
-                            .ok();
+                        if !power.full_charge_once.load(Ordering::SeqCst) {
+                            info!("CtrlCharge reloading charge limit");
+                            power
+                                .set(lock.bat_charge_limit)
+                                .map_err(|err| {
+                                    warn!("CtrlCharge: set_limit {}", err);
+                                    err
+                                })
+                                .ok();
+                        }
 
                         if lock.disable_nvidia_powerd_on_battery {
                             if let Ok(value) = power.power.get_online() {
@@ -213,14 +352,42 @@ impl CtrlTask for CtrlPower {
                 // on lid change
                 async move {}
             },
-            move |_power_plugged| {
-                // power change
-                async move {}
+            move |power_plugged| {
+                let power = power3.clone();
+                let sysd = sysd3.clone();
+                let ctxt = signal_ctxt1.clone();
+                async move {
+                    let (command, time_limit) = {
+                        let mut config = power.config.lock().await;
+                        config.read();
+                        let command = if power_plugged {
+                            config.ac_command.clone()
+                        } else {
+                            config.bat_command.clone()
+                        };
+                        (command, Duration::from_secs(config.command_timeout_secs))
+                    };
+                    // Run in its own task so a slow command can't hold up the
+                    // power and lid events
+                    tokio::spawn(run_power_command(command, power_plugged, time_limit));
+
+                    if power.config.lock().await.disable_nvidia_powerd_on_battery {
+                        do_nvidia_powerd_action(&sysd, power_plugged).await;
+                    }
+
+                    if !power_plugged {
+                        if let Some(limit) = power.end_full_charge_once().await {
+                            info!("CtrlCharge: AC unplugged, restored charge limit {limit}");
+                            Self::notify_charge_control_end_threshold(&ctxt, limit)
+                                .await
+                                .ok();
+                        }
+                    }
+                }
             },
         )
         .await;
 
-        let config = self.config.clone();
         // self.watch_charge_control_end_threshold(signal_ctxt.clone())
         //     .await?;
 
@@ -231,39 +398,13 @@ impl CtrlTask for CtrlPower {
                 if let Ok(value) = ctrl.power.get_online() {
                     if online != value {
                         online = value;
-                        let mut config = config.lock().await;
-                        config.read();
-
-                        if config.disable_nvidia_powerd_on_battery {
-                            do_nvidia_powerd_action(&sysd3, value == 1).await;
-                        }
-
                         Self::notify_mains_online(&signal_ctxt, value == 1)
                             .await
                             .unwrap();
-
-                        let mut prog: Vec<&str> = Vec::new();
-                        if value == 1 {
-                            // AC ONLINE
-                            prog = config.ac_command.split_whitespace().collect();
-                        } else if value == 0 {
-                            // BATTERY
-                            prog = config.bat_command.split_whitespace().collect();
-                        }
-
-                        if prog.len() > 1 {
-                            let mut cmd = Command::new(prog[0]);
-                            for arg in prog.iter().skip(1) {
-                                cmd.arg(*arg);
-                            }
-                            if let Err(e) = cmd.spawn() {
-                                if value == 1 {
-                                    error!("AC power command error: {e}");
-                                } else {
-                                    error!("Battery power command error: {e}");
-                                }
-                            }
-                        }
+                        Self::notify_power_source(&signal_ctxt, PowerSource::from_online(value))
+                            .await
+                            .map_err(|e| warn!("notify_power_source: {e}"))
+                            .ok();
                     }
                 }
                 // The inotify doesn't pick up events when the kernel changes internal value
@@ -276,6 +417,44 @@ impl CtrlTask for CtrlPower {
     }
 }
 
+/// Run the `ac_command` or `bat_command`, killing it if it hasn't finished
+/// within `time_limit`
+async fn run_power_command(command: String, power_plugged: bool, time_limit: Duration) {
+    let name = if power_plugged { "AC" } else { "Battery" };
+    let mut prog = command.split_whitespace();
+    let mut cmd = match prog.next() {
+        Some(prog) => Command::new(prog),
+        None => return,
+    };
+    cmd.args(prog).kill_on_drop(true);
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("{name} power command error: {e}");
+            return;
+        }
+    };
+    match timeout(time_limit, child.wait()).await {
+        Ok(Ok(status)) if !status.success() => {
+            warn!("{name} power command exited with {status}");
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => error!("{name} power command error: {e}"),
+        Err(_) => {
+            warn!(
+                "{name} power command did not finish within {}s, killing it",
+                time_limit.as_secs()
+            );
+            child
+                .kill()
+                .await
+                .map_err(|e| error!("{name} power command could not be killed: {e}"))
+                .ok();
+        }
+    }
+}
+
 async fn do_nvidia_powerd_action(proxy: &SystemdProxy<'_>, ac_on: bool) {
     if let Ok(res) = proxy.get_unit_file_state(NVIDIA_POWERD).await {
         if res == UnitFileState::Enabled {
@@ -295,3 +474,29 @@ async fn do_nvidia_powerd_action(proxy: &SystemdProxy<'_>, ac_on: bool) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_charge_limit, clamp_charge_limit};
+    use crate::error::RogError;
+
+    #[test]
+    fn charge_limit_range() {
+        assert!(check_charge_limit(20).is_ok());
+        assert!(check_charge_limit(100).is_ok());
+        for limit in [0, 19, 101, 150, 255] {
+            assert!(matches!(
+                check_charge_limit(limit),
+                Err(RogError::ChargeLimit(l)) if l == limit
+            ));
+        }
+        assert_eq!(
+            check_charge_limit(150).unwrap_err().to_string(),
+            "Invalid charging limit, not in range 20-100%: 150"
+        );
+
+        assert_eq!(clamp_charge_limit(0), 20);
+        assert_eq!(clamp_charge_limit(80), 80);
+        assert_eq!(clamp_charge_limit(150), 100);
+    }
+}
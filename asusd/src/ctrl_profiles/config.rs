@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use config_traits::{StdConfig, StdConfigLoad};
@@ -14,12 +15,38 @@ const CONFIG_FAN_FILE: &str = "fan_curves.ron";
 pub struct ProfileConfig {
     /// For restore on boot
     pub active_profile: Profile,
+    /// Switch to `Performance` while the CPU stays hot, and back to `Balanced`
+    /// once it has cooled down
+    #[serde(default)]
+    pub auto_profile_enabled: bool,
+    /// Temperature (C) at or above which `Performance` is selected
+    #[serde(default = "default_auto_profile_temp_high")]
+    pub auto_profile_temp_high: u8,
+    /// Temperature (C) at or below which `Balanced` is restored
+    #[serde(default = "default_auto_profile_temp_low")]
+    pub auto_profile_temp_low: u8,
+    /// Profile to switch to on every daemon start. If `None` the last
+    /// `active_profile` is restored instead
+    #[serde(default)]
+    pub boot_profile: Option<Profile>,
+}
+
+fn default_auto_profile_temp_high() -> u8 {
+    85
+}
+
+fn default_auto_profile_temp_low() -> u8 {
+    70
 }
 
 impl StdConfig for ProfileConfig {
     fn new() -> Self {
         Self {
             active_profile: Profile::Balanced,
+            auto_profile_enabled: false,
+            auto_profile_temp_high: default_auto_profile_temp_high(),
+            auto_profile_temp_low: default_auto_profile_temp_low(),
+            boot_profile: None,
         }
     }
 
@@ -39,6 +66,10 @@ pub struct FanCurveConfig {
     pub balanced: Vec<CurveData>,
     pub performance: Vec<CurveData>,
     pub quiet: Vec<CurveData>,
+    /// User saved curve sets, keyed by name, that can be swapped in for each
+    /// profile
+    #[serde(default)]
+    pub named: BTreeMap<Profile, BTreeMap<String, Vec<CurveData>>>,
 }
 
 impl StdConfig for FanCurveConfig {
@@ -1,17 +1,29 @@
+use std::time::{Duration, Instant};
+
 use config_traits::{StdConfig, StdConfigLoad};
 use log::{info, warn};
 use rog_platform::platform::AsusPlatform;
-use rog_platform::supported::PlatformProfileFunctions;
+use rog_platform::supported::{PlatformProfileFunctions, UnsupportedReason};
 use rog_profiles::error::ProfileError;
+use rog_profiles::fan_curve_set::CurveData;
 use rog_profiles::{FanCurveProfiles, Profile};
 
 use super::config::{FanCurveConfig, ProfileConfig};
 use crate::error::RogError;
-use crate::GetSupported;
+use crate::{Devices, GetSupported};
 
 // TODO: macro wrapper for warn/info/error log macros to add module name
 const MOD_NAME: &str = "CtrlPlatformProfile";
 
+/// How often the CPU temperature is sampled for automatic profile switching
+pub(super) const AUTO_PROFILE_INTERVAL: Duration = Duration::from_secs(5);
+/// How many samples in a row must cross a threshold before switching
+const AUTO_PROFILE_SUSTAIN: u8 = 3;
+/// How long a fan curve preview stays applied without being saved
+pub(super) const FAN_CURVE_PREVIEW_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the fans run at full speed for `fan_boost_max`
+pub(super) const FAN_BOOST_DURATION: Duration = Duration::from_secs(10);
+
 pub struct FanCurves {
     config_file: FanCurveConfig,
     profiles: FanCurveProfiles,
@@ -37,18 +49,61 @@ impl FanCurves {
     pub fn profiles_mut(&mut self) -> &mut FanCurveProfiles {
         &mut self.profiles
     }
+
+    /// Store the current curves of `profile` under `name`, replacing any
+    /// existing set with the same name
+    pub fn save_named_curves(&mut self, profile: Profile, name: &str) -> Result<(), ProfileError> {
+        let curves = self.profiles.get_fan_curves_for(profile).to_vec();
+        for curve in &curves {
+            curve.check_temperatures()?;
+        }
+        self.config_file
+            .named
+            .entry(profile)
+            .or_default()
+            .insert(name.to_owned(), curves);
+        Ok(())
+    }
+
+    /// Replace the curves of `profile` with the named set
+    pub fn load_named_curves(&mut self, profile: Profile, name: &str) -> Result<(), ProfileError> {
+        let curves = self
+            .config_file
+            .named
+            .get(&profile)
+            .and_then(|named| named.get(name))
+            .ok_or_else(|| ProfileError::NotFound(format!("fan curve {name} for {profile}")))?
+            .clone();
+        for curve in curves {
+            self.profiles.save_fan_curve(curve, profile)?;
+        }
+        Ok(())
+    }
+
+    pub fn named_curves(&self, profile: Profile) -> Vec<String> {
+        self.config_file
+            .named
+            .get(&profile)
+            .map(|named| named.keys().cloned().collect())
+            .unwrap_or_default()
+    }
 }
 
 pub struct CtrlPlatformProfile {
     pub profile_config: ProfileConfig,
     pub fan_curves: Option<FanCurves>,
     pub platform: AsusPlatform,
+    /// When an unsaved fan curve preview was written to the device, if one is
+    /// active. Any write of the stored curves ends the preview.
+    pub(super) fan_curve_preview: Option<Instant>,
+    /// If the fans are being run at full speed by `fan_boost_max`
+    pub(super) fan_boost: bool,
 }
 
 impl GetSupported for CtrlPlatformProfile {
     type A = PlatformProfileFunctions;
 
-    fn get_supported() -> Self::A {
+    fn get_supported(_devices: &Devices) -> Self::A {
         if !Profile::is_platform_profile_supported() {
             warn!(
                 "platform_profile kernel interface not found, your laptop does not support this, \
@@ -70,11 +125,32 @@ impl GetSupported for CtrlPlatformProfile {
             fans: res.unwrap_or_default(),
         }
     }
+
+    fn unsupported_reasons(supported: &Self::A, _devices: &Devices) -> Vec<UnsupportedReason> {
+        let mut reasons = Vec::new();
+        if !supported.platform_profile {
+            reasons.push(UnsupportedReason::new(
+                "platform_profile.platform_profile",
+                "/sys/firmware/acpi/platform_profile was not found. It needs a newer kernel or \
+                 is not available on this laptop",
+            ));
+        }
+        if supported.fans.is_empty() {
+            let reason = if FanCurveProfiles::get_device().is_ok() {
+                "The asus_custom_fan_curve hwmon device has no fans"
+            } else {
+                "The asus_custom_fan_curve hwmon device was not found. It needs a newer kernel or \
+                 is not available on this laptop"
+            };
+            reasons.push(UnsupportedReason::new("platform_profile.fans", reason));
+        }
+        reasons
+    }
 }
 
 impl CtrlPlatformProfile {
-    pub fn new(config: ProfileConfig) -> Result<Self, RogError> {
-        let platform = AsusPlatform::new()?;
+    pub fn new(config: ProfileConfig, devices: &Devices) -> Result<Self, RogError> {
+        let platform = devices.platform()?;
         if platform.has_platform_profile() || platform.has_throttle_thermal_policy() {
             info!("{MOD_NAME}: Device has profile control available");
 
@@ -82,6 +158,8 @@ impl CtrlPlatformProfile {
                 profile_config: config,
                 fan_curves: None,
                 platform,
+                fan_curve_preview: None,
+                fan_boost: false,
             };
             if FanCurveProfiles::get_device().is_ok() {
                 info!("{MOD_NAME}: Device has fan curves available");
@@ -143,8 +221,37 @@ impl CtrlPlatformProfile {
         }
     }
 
+    /// Set the platform_profile, activate the matching fan curve and save
+    pub(super) fn set_active_profile(&mut self, profile: Profile) {
+        self.switch_profile(profile);
+        self.save_config();
+    }
+
+    /// Set the platform_profile and activate the matching fan curve without
+    /// saving
+    fn switch_profile(&mut self, profile: Profile) {
+        Profile::set_profile(profile)
+            .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
+            .ok();
+        self.profile_config.active_profile = profile;
+        self.write_profile_curve_to_platform()
+            .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+            .ok();
+    }
+
+    /// Apply the configured `boot_profile`, if any, without saving. `None`
+    /// leaves the restored `active_profile` in place. Returns the profile that
+    /// was applied.
+    pub(super) fn apply_boot_profile(&mut self) -> Option<Profile> {
+        let profile = self.profile_config.boot_profile?;
+        info!("{MOD_NAME}: applying boot profile {profile}");
+        self.switch_profile(profile);
+        Some(profile)
+    }
+
     /// Set the curve for the active profile active
     pub(super) fn write_profile_curve_to_platform(&mut self) -> Result<(), RogError> {
+        self.fan_curve_preview = None;
         if let Some(curves) = &mut self.fan_curves {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
                 curves.profiles_mut().write_profile_curve_to_platform(
@@ -156,6 +263,43 @@ impl CtrlPlatformProfile {
         Ok(())
     }
 
+    /// Write a curve to the device for the active profile without storing it.
+    /// Returns the time the preview started, which identifies it for the
+    /// auto-revert.
+    pub(super) fn preview_fan_curve(&mut self, curve: &CurveData) -> Result<Instant, RogError> {
+        curve.check_temperatures()?;
+        if self.fan_curves.is_none() {
+            return Err(RogError::NotSupported);
+        }
+        let mut device = FanCurveProfiles::get_device()?;
+        curve.write_to_device(&mut device)?;
+        let started = Instant::now();
+        self.fan_curve_preview = Some(started);
+        Ok(started)
+    }
+
+    /// Run every fan of the active profile at full speed without storing the
+    /// curves, such as to blow out dust. This works the same as a preview, so
+    /// any write of the stored curves ends it. Returns the time the boost
+    /// started, which identifies it for the restore.
+    pub(super) fn start_fan_boost(&mut self) -> Result<Instant, RogError> {
+        let curves = match &self.fan_curves {
+            Some(curves) => curves,
+            None => return Err(RogError::NotSupported),
+        };
+        let mut device = FanCurveProfiles::get_device()?;
+        for curve in curves
+            .profiles()
+            .get_fan_curves_for(self.profile_config.active_profile)
+        {
+            curve.full_speed().write_to_device(&mut device)?;
+        }
+        let started = Instant::now();
+        self.fan_curve_preview = Some(started);
+        self.fan_boost = true;
+        Ok(started)
+    }
+
     pub(super) fn set_active_curve_to_defaults(&mut self) -> Result<(), RogError> {
         if let Some(curves) = self.fan_curves.as_mut() {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
@@ -169,3 +313,154 @@ impl CtrlPlatformProfile {
         Ok(())
     }
 }
+
+/// Hysteresis state for temperature based profile switching
+#[derive(Debug, Default)]
+pub(super) struct AutoProfile {
+    /// Consecutive samples past the relevant threshold
+    count: u8,
+    /// `Performance` was selected by this, not by the user
+    boosted: bool,
+}
+
+impl AutoProfile {
+    /// Feed a temperature sample, returns the profile to switch to if any.
+    /// Only a profile that was boosted by this is dropped back to `Balanced`,
+    /// and any manual change while boosted cancels the boost.
+    pub(super) fn update(
+        &mut self,
+        temp: u8,
+        high: u8,
+        low: u8,
+        active: Profile,
+    ) -> Option<Profile> {
+        // Keep a gap between the thresholds so there is always hysteresis
+        let low = low.min(high.saturating_sub(1));
+        if self.boosted && active != Profile::Performance {
+            self.boosted = false;
+            self.count = 0;
+        }
+
+        let crossed = if self.boosted {
+            temp <= low
+        } else {
+            temp >= high && active != Profile::Performance
+        };
+        if !crossed {
+            self.count = 0;
+            return None;
+        }
+
+        self.count += 1;
+        if self.count < AUTO_PROFILE_SUSTAIN {
+            return None;
+        }
+        self.count = 0;
+        self.boosted = !self.boosted;
+        Some(if self.boosted {
+            Profile::Performance
+        } else {
+            Profile::Balanced
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rog_platform::platform::AsusPlatform;
+    use rog_profiles::fan_curve_set::CurveData;
+    use rog_profiles::Profile;
+
+    use config_traits::StdConfig;
+
+    use super::{AutoProfile, CtrlPlatformProfile, FanCurves};
+    use crate::ctrl_profiles::config::{FanCurveConfig, ProfileConfig};
+
+    #[test]
+    fn named_curves_switch() {
+        let quiet = CurveData::from_str("30:1,40:2,50:3,60:4,70:5,80:6,90:7,100:8").unwrap();
+        let loud = CurveData::from_str("30:10,40:20,50:30,60:40,70:50,80:60,90:70,100:80").unwrap();
+        let mut curves = FanCurves {
+            config_file: FanCurveConfig::default(),
+            profiles: Default::default(),
+        };
+        curves.profiles.balanced = vec![quiet.clone()];
+
+        curves
+            .save_named_curves(Profile::Balanced, "quiet")
+            .unwrap();
+        curves.profiles.balanced = vec![loud];
+        curves.save_named_curves(Profile::Balanced, "loud").unwrap();
+        assert_eq!(
+            curves.named_curves(Profile::Balanced),
+            vec!["loud", "quiet"]
+        );
+        assert!(curves.named_curves(Profile::Quiet).is_empty());
+
+        curves
+            .load_named_curves(Profile::Balanced, "quiet")
+            .unwrap();
+        assert_eq!(curves.profiles.balanced[0].pwm, quiet.pwm);
+        assert!(curves.load_named_curves(Profile::Balanced, "none").is_err());
+
+        curves.profiles.balanced[0].temp[2] = 20;
+        assert!(curves.save_named_curves(Profile::Balanced, "bad").is_err());
+        assert_eq!(curves.named_curves(Profile::Balanced).len(), 2);
+    }
+
+    #[test]
+    fn auto_profile_hysteresis() {
+        let mut auto = AutoProfile::default();
+        let mut active = Profile::Balanced;
+        // A single spike does nothing
+        assert_eq!(auto.update(90, 85, 70, active), None);
+        assert_eq!(auto.update(60, 85, 70, active), None);
+        // Sustained heat boosts
+        assert_eq!(auto.update(90, 85, 70, active), None);
+        assert_eq!(auto.update(90, 85, 70, active), None);
+        assert_eq!(auto.update(90, 85, 70, active), Some(Profile::Performance));
+        active = Profile::Performance;
+        // Between the thresholds stays boosted
+        for _ in 0..5 {
+            assert_eq!(auto.update(78, 85, 70, active), None);
+        }
+        assert_eq!(auto.update(65, 85, 70, active), None);
+        assert_eq!(auto.update(65, 85, 70, active), None);
+        assert_eq!(auto.update(65, 85, 70, active), Some(Profile::Balanced));
+        active = Profile::Balanced;
+        assert_eq!(auto.update(65, 85, 70, active), None);
+
+        // A user chosen Performance is never dropped
+        let mut auto = AutoProfile::default();
+        for _ in 0..5 {
+            assert_eq!(auto.update(50, 85, 70, Profile::Performance), None);
+        }
+        // A manual change while boosted cancels the boost
+        for _ in 0..3 {
+            auto.update(90, 85, 70, Profile::Quiet);
+        }
+        for _ in 0..5 {
+            assert_eq!(auto.update(50, 85, 70, Profile::Quiet), None);
+        }
+    }
+
+    #[test]
+    fn boot_profile() {
+        let mut ctrl = CtrlPlatformProfile {
+            profile_config: ProfileConfig::new(),
+            fan_curves: None,
+            platform: AsusPlatform::simulated(),
+            fan_curve_preview: None,
+            fan_boost: false,
+        };
+        ctrl.profile_config.active_profile = Profile::Quiet;
+        assert_eq!(ctrl.apply_boot_profile(), None);
+        assert_eq!(ctrl.profile_config.active_profile, Profile::Quiet);
+
+        ctrl.profile_config.boot_profile = Some(Profile::Performance);
+        assert_eq!(ctrl.apply_boot_profile(), Some(Profile::Performance));
+        assert_eq!(ctrl.profile_config.active_profile, Profile::Performance);
+    }
+}
@@ -4,14 +4,19 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use config_traits::StdConfig;
 use log::{error, info, warn};
-use rog_profiles::fan_curve_set::CurveData;
+use rog_platform::hwmon::read_temperature;
+use rog_profiles::fan_curve_set::{export_curves, import_curves, CurveData};
 use rog_profiles::{FanCurvePU, FanCurveProfiles, Profile};
+use tokio::time::sleep;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::export::futures_util::StreamExt;
 use zbus::fdo::Error;
 use zbus::{dbus_interface, Connection, SignalContext};
 
-use super::controller::CtrlPlatformProfile;
+use super::controller::{
+    AutoProfile, CtrlPlatformProfile, AUTO_PROFILE_INTERVAL, FAN_BOOST_DURATION,
+    FAN_CURVE_PREVIEW_TIMEOUT,
+};
 use crate::error::RogError;
 use crate::CtrlTask;
 
@@ -26,7 +31,8 @@ pub struct ProfileZbus(pub Arc<Mutex<CtrlPlatformProfile>>);
 
 #[dbus_interface(name = "org.asuslinux.Daemon")]
 impl ProfileZbus {
-    /// Fetch profile names
+    /// Fetch the profiles the platform supports, ordered Quiet, Balanced,
+    /// Performance. Some laptops do not have Quiet.
     fn profiles(&mut self) -> zbus::fdo::Result<Vec<Profile>> {
         if let Ok(profiles) = Profile::get_profile_names() {
             return Ok(profiles);
@@ -36,20 +42,39 @@ impl ProfileZbus {
         ))
     }
 
-    /// Toggle to next platform_profile. Names provided by `Profiles`.
-    /// If fan-curves are supported will also activate a fan curve for profile.
-    async fn next_profile(&mut self, #[zbus(signal_context)] ctxt: SignalContext<'_>) {
+    /// The display names of `profiles`, in the same order
+    fn profile_names(&mut self) -> zbus::fdo::Result<Vec<String>> {
+        Ok(self.profiles()?.iter().map(|p| p.to_string()).collect())
+    }
+
+    /// Toggle to next platform_profile in the order Quiet, Balanced,
+    /// Performance, skipping any not provided by `Profiles`. Returns the new
+    /// profile. If fan-curves are supported will also activate a fan curve for
+    /// profile.
+    async fn next_profile(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<Profile> {
         let mut ctrl = self.0.lock().await;
-        let next = Profile::get_next_profile(ctrl.profile_config.active_profile);
+        ctrl.profile_config.read();
+        let available = Profile::get_profile_names().map_err(|e| {
+            warn!("{MOD_NAME}: get_profile_names, {}", e);
+            RogError::from(e)
+        })?;
+        let next = Profile::next_available(ctrl.profile_config.active_profile, &available);
         Profile::set_profile(next)
             .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
             .ok();
         ctrl.profile_config.active_profile = next;
+        ctrl.write_profile_curve_to_platform()
+            .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+            .ok();
         ctrl.save_config();
 
         Self::notify_profile(&ctxt, ctrl.profile_config.active_profile)
             .await
             .ok();
+        Ok(next)
     }
 
     /// Fetch the active profile name
@@ -59,6 +84,20 @@ impl ProfileZbus {
         Ok(ctrl.profile_config.active_profile)
     }
 
+    /// Fetch the profile the firmware is currently enforcing. This can differ
+    /// from `active_profile` if changed outside of asusd
+    async fn active_profile_hardware(&self) -> zbus::fdo::Result<Profile> {
+        let ctrl = self.0.lock().await;
+        let profile = ctrl.platform.get_platform_profile().map_err(|e| {
+            warn!("{MOD_NAME}: get_platform_profile, {}", e);
+            RogError::from(e)
+        })?;
+        Ok(Profile::from_str(&profile).map_err(|e| {
+            warn!("{MOD_NAME}: active_profile_hardware, {}", e);
+            RogError::from(e)
+        })?)
+    }
+
     /// Set this platform_profile name as active
     async fn set_active_profile(
         &self,
@@ -68,21 +107,71 @@ impl ProfileZbus {
         let mut ctrl = self.0.lock().await;
         // Read first just incase the user has modified the config before calling this
         ctrl.profile_config.read();
-        Profile::set_profile(profile)
-            .map_err(|e| warn!("{MOD_NAME}: set_profile, {}", e))
-            .ok();
-        ctrl.profile_config.active_profile = profile;
-        ctrl.write_profile_curve_to_platform()
-            .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
-            .ok();
-
-        ctrl.save_config();
+        ctrl.set_active_profile(profile);
 
         Self::notify_profile(&ctxt, ctrl.profile_config.active_profile)
             .await
             .ok();
     }
 
+    /// Enable or disable switching to `Performance` while the CPU temperature
+    /// stays at or above `temp_high`, and back to `Balanced` once it stays at
+    /// or below `temp_low`
+    async fn set_auto_profile(
+        &mut self,
+        enabled: bool,
+        temp_high: u8,
+        temp_low: u8,
+    ) -> zbus::fdo::Result<()> {
+        if temp_low >= temp_high {
+            return Err(Error::InvalidArgs(
+                "temp_low must be lower than temp_high".to_owned(),
+            ));
+        }
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.auto_profile_enabled = enabled;
+        ctrl.profile_config.auto_profile_temp_high = temp_high;
+        ctrl.profile_config.auto_profile_temp_low = temp_low;
+        ctrl.profile_config.write();
+        Ok(())
+    }
+
+    /// Get the automatic profile switching state as (enabled, temp_high,
+    /// temp_low)
+    async fn auto_profile(&self) -> (bool, u8, u8) {
+        let ctrl = self.0.lock().await;
+        (
+            ctrl.profile_config.auto_profile_enabled,
+            ctrl.profile_config.auto_profile_temp_high,
+            ctrl.profile_config.auto_profile_temp_low,
+        )
+    }
+
+    /// Set the profile applied every time the daemon starts. An empty string
+    /// clears it so the last active profile is restored instead.
+    async fn set_boot_profile(&mut self, profile: &str) -> zbus::fdo::Result<()> {
+        let profile = if profile.trim().is_empty() {
+            None
+        } else {
+            Some(Profile::from_str(profile).map_err(RogError::from)?)
+        };
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        ctrl.profile_config.boot_profile = profile;
+        ctrl.profile_config.write();
+        Ok(())
+    }
+
+    /// Get the profile applied on daemon start, or an empty string if none
+    async fn boot_profile(&self) -> String {
+        let ctrl = self.0.lock().await;
+        ctrl.profile_config
+            .boot_profile
+            .map(|p| p.to_string())
+            .unwrap_or_default()
+    }
+
     /// Set all fan curves for a profile to enabled status. Will also activate a
     /// fan curve if in the same profile mode
     async fn set_fan_curves_enabled(
@@ -148,6 +237,10 @@ impl ProfileZbus {
     /// Set the fan curve for the specified profile.
     /// Will also activate the fan curve if the user is in the same mode.
     async fn set_fan_curve(&self, profile: Profile, curve: CurveData) -> zbus::fdo::Result<()> {
+        curve.check_temperatures().map_err(|e| {
+            warn!("{MOD_NAME}: set_fan_curve, {}", e);
+            RogError::from(e)
+        })?;
         let mut ctrl = self.0.lock().await;
         ctrl.profile_config.read();
         if let Some(curves) = &mut ctrl.fan_curves {
@@ -166,6 +259,196 @@ impl ProfileZbus {
         Ok(())
     }
 
+    /// Write a fan curve to the device without saving it, so that it can be
+    /// tried out. Only the active profile can be previewed. The saved curve is
+    /// restored by `revert_fan_curve`, or automatically after 30 seconds unless
+    /// the curve is saved with `set_fan_curve`.
+    async fn preview_fan_curve(&self, profile: Profile, curve: CurveData) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if profile != ctrl.profile_config.active_profile {
+            return Err(Error::InvalidArgs(format!(
+                "Only the active profile {} can be previewed",
+                ctrl.profile_config.active_profile
+            )));
+        }
+        let started = ctrl.preview_fan_curve(&curve).map_err(|e| {
+            warn!("{MOD_NAME}: preview_fan_curve, {}", e);
+            match e {
+                RogError::NotSupported => Error::Failed(UNSUPPORTED_MSG.to_owned()),
+                e => e.into(),
+            }
+        })?;
+
+        let ctrl = self.0.clone();
+        tokio::spawn(async move {
+            sleep(FAN_CURVE_PREVIEW_TIMEOUT).await;
+            let mut lock = ctrl.lock().await;
+            if lock.fan_curve_preview == Some(started) {
+                info!("{MOD_NAME}: fan curve preview was not saved, reverting");
+                lock.write_profile_curve_to_platform()
+                    .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+                    .ok();
+            }
+        });
+        Ok(())
+    }
+
+    /// Restore the saved fan curves after `preview_fan_curve`. Does nothing if
+    /// the profile is not active.
+    async fn revert_fan_curve(&self, profile: Profile) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if profile != ctrl.profile_config.active_profile {
+            return Ok(());
+        }
+        ctrl.write_profile_curve_to_platform().map_err(|e| {
+            warn!("{MOD_NAME}: revert_fan_curve, {}", e);
+            e
+        })?;
+        Ok(())
+    }
+
+    /// Run the fans at full speed for 10 seconds to clear out dust, then
+    /// restore the stored fan curves. `notify_fan_boost_ended` is emitted once
+    /// the curves are restored. Fails if a boost is already running.
+    async fn fan_boost_max(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if ctrl.fan_boost {
+            return Err(Error::Failed("A fan boost is already running".to_owned()));
+        }
+        let started = ctrl.start_fan_boost().map_err(|e| {
+            warn!("{MOD_NAME}: fan_boost_max, {}", e);
+            match e {
+                RogError::NotSupported => Error::Failed(UNSUPPORTED_MSG.to_owned()),
+                e => e.into(),
+            }
+        })?;
+        info!("{MOD_NAME}: running fans at full speed");
+
+        let ctrl = self.0.clone();
+        let ctxt = ctxt.to_owned();
+        tokio::spawn(async move {
+            sleep(FAN_BOOST_DURATION).await;
+            let mut lock = ctrl.lock().await;
+            lock.fan_boost = false;
+            // Already restored if the curves were written during the boost
+            if lock.fan_curve_preview == Some(started) {
+                info!("{MOD_NAME}: fan boost ended, restoring fan curves");
+                lock.write_profile_curve_to_platform()
+                    .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+                    .ok();
+            }
+            drop(lock);
+            Self::notify_fan_boost_ended(&ctxt)
+                .await
+                .map_err(|e| warn!("{MOD_NAME}: notify_fan_boost_ended, {}", e))
+                .ok();
+        });
+        Ok(())
+    }
+
+    /// Save the current fan curves of the profile under `name` so they can be
+    /// switched back to later with `set_named_fan_curve`
+    async fn save_named_fan_curve(&self, profile: Profile, name: String) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        if let Some(curves) = &mut ctrl.fan_curves {
+            curves.save_named_curves(profile, &name).map_err(|e| {
+                warn!("{MOD_NAME}: save_named_fan_curve, {}", e);
+                RogError::from(e)
+            })?;
+        } else {
+            return Err(Error::Failed(UNSUPPORTED_MSG.to_owned()));
+        }
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// Switch the profile to a previously saved named fan curve. Will also
+    /// activate the fan curve if the user is in the same mode.
+    async fn set_named_fan_curve(&self, profile: Profile, name: String) -> zbus::fdo::Result<()> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        if let Some(curves) = &mut ctrl.fan_curves {
+            curves.load_named_curves(profile, &name).map_err(|e| {
+                warn!("{MOD_NAME}: set_named_fan_curve, {}", e);
+                RogError::from(e)
+            })?;
+        } else {
+            return Err(Error::Failed(UNSUPPORTED_MSG.to_owned()));
+        }
+        if profile == ctrl.profile_config.active_profile {
+            ctrl.write_profile_curve_to_platform()
+                .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+                .ok();
+        }
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// Export the fan curves of the profile as portable `fan=temp:pwm,...`
+    /// text, one line per fan, for sharing with `import_fan_curve`
+    async fn export_fan_curve(&self, profile: Profile) -> zbus::fdo::Result<String> {
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        if let Some(curves) = &ctrl.fan_curves {
+            return Ok(export_curves(curves.profiles().get_fan_curves_for(profile)));
+        }
+        Err(Error::Failed(UNSUPPORTED_MSG.to_owned()))
+    }
+
+    /// Import fan curves made by `export_fan_curve` in to the profile. Every
+    /// fan in the data must exist on this laptop, and the enabled state of
+    /// each curve is kept. Will also activate the fan curve if the user is in
+    /// the same mode.
+    async fn import_fan_curve(&self, profile: Profile, data: String) -> zbus::fdo::Result<()> {
+        let imported = import_curves(&data).map_err(|e| {
+            warn!("{MOD_NAME}: import_fan_curve, {}", e);
+            Error::InvalidArgs(e.to_string())
+        })?;
+        let mut ctrl = self.0.lock().await;
+        ctrl.profile_config.read();
+        if let Some(curves) = &mut ctrl.fan_curves {
+            let profiles = curves.profiles_mut();
+            let mut checked = Vec::with_capacity(imported.len());
+            for mut curve in imported {
+                if let Some(existing) = profiles.get_fan_curve_for(&profile, curve.fan) {
+                    curve.enabled = existing.enabled;
+                    checked.push(curve);
+                } else {
+                    return Err(Error::InvalidArgs(format!(
+                        "This laptop has no {:?} fan curve",
+                        curve.fan
+                    )));
+                }
+            }
+            for curve in checked {
+                profiles
+                    .save_fan_curve(curve, profile)
+                    .map_err(|err| Error::Failed(err.to_string()))?;
+            }
+        } else {
+            return Err(Error::Failed(UNSUPPORTED_MSG.to_owned()));
+        }
+        if profile == ctrl.profile_config.active_profile {
+            ctrl.write_profile_curve_to_platform()
+                .map_err(|e| warn!("{MOD_NAME}: write_profile_curve_to_platform, {}", e))
+                .ok();
+        }
+        ctrl.save_config();
+        Ok(())
+    }
+
+    /// List the names of the saved fan curves for the profile
+    async fn list_fan_curves(&self, profile: Profile) -> zbus::fdo::Result<Vec<String>> {
+        let ctrl = self.0.lock().await;
+        if let Some(curves) = &ctrl.fan_curves {
+            return Ok(curves.named_curves(profile));
+        }
+        Err(Error::Failed(UNSUPPORTED_MSG.to_owned()))
+    }
+
     /// Reset the stored (self) and device curve to the defaults of the
     /// platform.
     ///
@@ -208,6 +491,10 @@ impl ProfileZbus {
     #[dbus_interface(signal)]
     async fn notify_profile(signal_ctxt: &SignalContext<'_>, profile: Profile) -> zbus::Result<()> {
     }
+
+    /// Emitted when a `fan_boost_max` has ended and the fan curves restored
+    #[dbus_interface(signal)]
+    async fn notify_fan_boost_ended(signal_ctxt: &SignalContext<'_>) -> zbus::Result<()> {}
 }
 
 #[async_trait]
@@ -224,6 +511,38 @@ impl CtrlTask for ProfileZbus {
     }
 
     async fn create_tasks(&self, signal_ctxt: SignalContext<'static>) -> Result<(), RogError> {
+        let ctrl = self.0.clone();
+        let sig_ctx = signal_ctxt.clone();
+        tokio::spawn(async move {
+            let mut auto = AutoProfile::default();
+            loop {
+                sleep(AUTO_PROFILE_INTERVAL).await;
+                let mut lock = ctrl.lock().await;
+                if !lock.profile_config.auto_profile_enabled {
+                    auto = AutoProfile::default();
+                    continue;
+                }
+                let temp = match read_temperature() {
+                    Ok(temp) => temp,
+                    Err(e) => {
+                        warn!("{MOD_NAME}: read_temperature, {e}");
+                        continue;
+                    }
+                };
+                let active = lock.profile_config.active_profile;
+                if let Some(profile) = auto.update(
+                    temp,
+                    lock.profile_config.auto_profile_temp_high,
+                    lock.profile_config.auto_profile_temp_low,
+                    active,
+                ) {
+                    info!("{MOD_NAME}: CPU at {temp}C, switching profile to {profile}");
+                    lock.set_active_profile(profile);
+                    Self::notify_profile(&sig_ctx, profile).await.ok();
+                }
+            }
+        });
+
         let ctrl = self.0.clone();
         let sig_ctx = signal_ctxt.clone();
         let watch = self
@@ -315,6 +634,9 @@ impl crate::Reloadable for ProfileZbus {
     /// Fetch the active profile and use that to set all related components up
     async fn reload(&mut self) -> Result<(), RogError> {
         let mut ctrl = self.0.lock().await;
+        if ctrl.apply_boot_profile().is_some() {
+            ctrl.save_config();
+        }
         let active = ctrl.profile_config.active_profile;
         if let Some(curves) = &mut ctrl.fan_curves {
             if let Ok(mut device) = FanCurveProfiles::get_device() {
@@ -1,24 +1,108 @@
 use async_trait::async_trait;
-use serde_derive::{Deserialize, Serialize};
-use zbus::zvariant::Type;
-use zbus::{dbus_interface, Connection};
+use rog_platform::supported::{DeviceNodes, SupportedDetails};
+use zbus::{dbus_interface, Connection, ObjectServer};
 
+use crate::ctrl_anime::trait_impls::CtrlAnimeZbus;
 use crate::ctrl_anime::CtrlAnime;
 use crate::ctrl_aura::controller::CtrlKbdLed;
+use crate::ctrl_aura::trait_impls::CtrlKbdLedZbus;
 use crate::ctrl_platform::CtrlPlatform;
 use crate::ctrl_power::CtrlPower;
 use crate::ctrl_profiles::controller::CtrlPlatformProfile;
-use crate::GetSupported;
+use crate::{CtrlTask, Devices, GetSupported};
 
-#[derive(Serialize, Deserialize, Debug, Type)]
-pub struct SupportedFunctions(rog_platform::supported::SupportedFunctions);
+#[derive(Debug)]
+pub struct SupportedFunctions {
+    functions: rog_platform::supported::SupportedFunctions,
+    /// Used to look for the missing devices when giving reasons
+    devices: Devices,
+}
+
+impl SupportedFunctions {
+    /// Collect the reason for each unsupported feature from its controller
+    fn detailed(&self) -> SupportedDetails {
+        let functions = &self.functions;
+        let devices = &self.devices;
+        let mut reasons = CtrlAnime::unsupported_reasons(&functions.anime_ctrl, devices);
+        reasons.append(&mut CtrlPower::unsupported_reasons(
+            &functions.charge_ctrl,
+            devices,
+        ));
+        reasons.append(&mut CtrlPlatformProfile::unsupported_reasons(
+            &functions.platform_profile,
+            devices,
+        ));
+        reasons.append(&mut CtrlKbdLed::unsupported_reasons(
+            &functions.keyboard_led,
+            devices,
+        ));
+        reasons.append(&mut CtrlPlatform::unsupported_reasons(
+            &functions.rog_bios_ctrl,
+            devices,
+        ));
+        SupportedDetails {
+            functions: functions.clone(),
+            reasons,
+        }
+    }
+}
 
 #[dbus_interface(name = "org.asuslinux.Daemon")]
 impl SupportedFunctions {
     pub fn supported_functions(
         &self,
     ) -> zbus::fdo::Result<&rog_platform::supported::SupportedFunctions> {
-        Ok(&self.0)
+        Ok(&self.functions)
+    }
+
+    /// The full supported functions tree serialised as JSON, for scripting
+    pub fn supported_functions_json(&self) -> zbus::fdo::Result<String> {
+        serde_json::to_string_pretty(&self.functions)
+            .map_err(|e| zbus::fdo::Error::Failed(format!("supported_functions_json: {e}")))
+    }
+
+    /// `supported_functions` along with the reason each unsupported feature is
+    /// not available, such as a missing kernel interface
+    fn supported_functions_detailed(&self) -> zbus::fdo::Result<SupportedDetails> {
+        Ok(self.detailed())
+    }
+
+    /// The device nodes the controllers are using, for bug reports. Nodes
+    /// that were not found are reported as `not found`.
+    pub async fn device_nodes(
+        &self,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> zbus::fdo::Result<DeviceNodes> {
+        let mut nodes = DeviceNodes::default();
+        if let Ok(iface) = server
+            .interface::<_, CtrlPlatform>(CtrlPlatform::zbus_path())
+            .await
+        {
+            nodes.platform = iface.get().await.device_path();
+        }
+        if let Ok(iface) = server
+            .interface::<_, CtrlAnimeZbus>(CtrlAnimeZbus::zbus_path())
+            .await
+        {
+            nodes.anime = iface.get().await.0.lock().await.device_path();
+        }
+        if let Ok(iface) = server
+            .interface::<_, CtrlKbdLedZbus>(CtrlKbdLedZbus::zbus_path())
+            .await
+        {
+            let zbus = iface.get().await;
+            let ctrl = zbus.0.lock().await;
+            nodes.keyboard_led = ctrl.led_node.path();
+            nodes.keyboard_brightness = ctrl.kd_brightness.path().display().to_string();
+        }
+        Ok(nodes)
+    }
+
+    /// The dbus API version as `(major, minor)`. Clients should check this
+    /// before using other methods.
+    #[dbus_interface(out_args("major", "minor"))]
+    fn api_version(&self) -> (u32, u32) {
+        rog_dbus::DBUS_API_VERSION
     }
 
     #[dbus_interface(out_args("answer", "question"))]
@@ -37,13 +121,16 @@ impl crate::ZbusRun for SupportedFunctions {
 impl GetSupported for SupportedFunctions {
     type A = SupportedFunctions;
 
-    fn get_supported() -> Self::A {
-        Self(rog_platform::supported::SupportedFunctions {
-            anime_ctrl: CtrlAnime::get_supported(),
-            keyboard_led: CtrlKbdLed::get_supported(),
-            charge_ctrl: CtrlPower::get_supported(),
-            platform_profile: CtrlPlatformProfile::get_supported(),
-            rog_bios_ctrl: CtrlPlatform::get_supported(),
-        })
+    fn get_supported(devices: &Devices) -> Self::A {
+        Self {
+            functions: rog_platform::supported::SupportedFunctions {
+                anime_ctrl: CtrlAnime::get_supported(devices),
+                keyboard_led: CtrlKbdLed::get_supported(devices),
+                charge_ctrl: CtrlPower::get_supported(devices),
+                platform_profile: CtrlPlatformProfile::get_supported(devices),
+                rog_bios_ctrl: CtrlPlatform::get_supported(devices),
+            },
+            devices: devices.clone(),
+        }
     }
 }
@@ -1,6 +1,8 @@
 use std::env;
 use std::error::Error;
+use std::future::Future;
 use std::io::Write;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -12,20 +14,30 @@ use asusd::ctrl_anime::trait_impls::CtrlAnimeZbus;
 use asusd::ctrl_anime::CtrlAnime;
 use asusd::ctrl_aura::controller::CtrlKbdLed;
 use asusd::ctrl_aura::trait_impls::CtrlKbdLedZbus;
-use asusd::ctrl_platform::CtrlPlatform;
+use asusd::ctrl_platform::{CtrlPlatform, LiveControllers};
 use asusd::ctrl_power::CtrlPower;
 use asusd::ctrl_profiles::config::ProfileConfig;
 use asusd::ctrl_profiles::controller::CtrlPlatformProfile;
 use asusd::ctrl_profiles::trait_impls::ProfileZbus;
 use asusd::ctrl_supported::SupportedFunctions;
-use asusd::{print_board_info, CtrlTask, GetSupported, Reloadable, ZbusRun};
+use asusd::{print_board_info, CtrlTask, Devices, GetSupported, Reloadable, ZbusRun};
 use config_traits::{StdConfig, StdConfigLoad, StdConfigLoad2};
 use log::{error, info, warn};
 use rog_aura::aura_detection::LaptopLedData;
+use rog_aura::LedBrightness;
 use rog_dbus::DBUS_NAME;
 use rog_profiles::Profile;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
 use tokio::time::sleep;
-use zbus::SignalContext;
+use zbus::{Interface, SignalContext};
+
+/// Removes an interface from the object server at the given path
+type RemoveFn = fn(Connection, &'static str) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Every interface added to the object server, in the order added, so that
+/// they can be removed again on shutdown
+type Registry = Vec<(&'static str, RemoveFn)>;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -40,8 +52,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some(val) => val == "1",
         None => false,
     };
+    // Simulate the hardware so that asusd can be run on any machine
+    let dry_run = env::args().any(|arg| arg == "--dry-run");
 
-    if !is_service {
+    if !is_service && !dry_run {
         println!("asusd schould be only run from the right systemd service");
         println!(
             "do not run in your terminal, if you need an logs please use journalctl -b -u asusd"
@@ -57,13 +71,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!(" rog-profiles v{}", rog_profiles::VERSION);
     info!("rog-platform v{}", rog_platform::VERSION);
 
-    start_daemon().await?;
+    let devices = if dry_run {
+        warn!("--dry-run: hardware is simulated, nothing will be written to the laptop");
+        Devices::simulated()
+    } else {
+        Devices::Hardware
+    };
+
+    start_daemon(devices).await?;
     Ok(())
 }
 
 /// The actual main loop for the daemon
-async fn start_daemon() -> Result<(), Box<dyn Error>> {
-    let supported = SupportedFunctions::get_supported();
+async fn start_daemon(devices: Devices) -> Result<(), Box<dyn Error>> {
+    let supported = SupportedFunctions::get_supported(&devices);
     print_board_info();
     println!("{:?}", supported.supported_functions());
 
@@ -71,94 +92,172 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     let mut connection = Connection::system().await?;
 
     let config = Config::new().load();
+    let apply_delay = Duration::from_millis(config.startup_apply_delay_ms);
+    if !apply_delay.is_zero() {
+        info!(
+            "Delaying startup apply by {}ms",
+            config.startup_apply_delay_ms
+        );
+    }
+    asusd::set_event_debounce(config.event_debounce_ms);
     let config = Arc::new(Mutex::new(config));
 
+    let mut registry = Registry::new();
     supported.add_to_server(&mut connection).await;
+    registry.push((
+        "/org/asuslinux/Supported",
+        remove_from_server::<SupportedFunctions>,
+    ));
+
+    // The profile and keyboard controllers are created first so that
+    // CtrlPlatform can compare their live state against the hardware
+    let profiles = if Profile::is_platform_profile_supported() {
+        let profile_config = ProfileConfig::new().load();
+        match CtrlPlatformProfile::new(profile_config, &devices) {
+            Ok(ctrl) => Some(Arc::new(Mutex::new(ctrl))),
+            Err(err) => {
+                error!("Profile control: {}", err);
+                None
+            }
+        }
+    } else {
+        warn!("platform_profile support not found");
+        None
+    };
 
-    match CtrlPlatform::new(config.clone()) {
+    // Keyboard brightness changes are sent to the AniMe controller
+    let (kbd_brightness_tx, kbd_brightness_rx) = watch::channel(LedBrightness::default());
+    let laptop = LaptopLedData::get_data();
+    // CtrlKbdLed deviates from the config pattern above due to requiring a keyboard
+    // detection first
+    let aura = match CtrlKbdLed::new(laptop, kbd_brightness_tx, &devices) {
+        Ok(ctrl) => Some(Arc::new(Mutex::new(ctrl))),
+        Err(err) => {
+            error!("Keyboard control: {}", err);
+            None
+        }
+    };
+
+    let live = LiveControllers {
+        aura: aura.clone(),
+        profiles: profiles.clone(),
+    };
+    let sig_ctx = CtrlPlatform::signal_context(&connection)?;
+    match CtrlPlatform::new(config.clone(), sig_ctx.clone(), &devices, live) {
         Ok(ctrl) => {
-            let sig_ctx = CtrlPlatform::signal_context(&connection)?;
-            start_tasks(ctrl, &mut connection, sig_ctx).await?;
+            start_tasks(ctrl, &mut connection, &mut registry, sig_ctx, apply_delay).await?;
         }
         Err(err) => {
             error!("CtrlPlatform: {}", err);
         }
     }
 
-    match CtrlPower::new(config.clone()) {
+    match CtrlPower::new(config.clone(), &connection, &devices) {
         Ok(ctrl) => {
             let sig_ctx = CtrlPower::signal_context(&connection)?;
-            start_tasks(ctrl, &mut connection, sig_ctx).await?;
+            start_tasks(ctrl, &mut connection, &mut registry, sig_ctx, apply_delay).await?;
         }
         Err(err) => {
             error!("CtrlPower: {}", err);
         }
     }
 
-    if Profile::is_platform_profile_supported() {
-        let profile_config = ProfileConfig::new().load();
-        match CtrlPlatformProfile::new(profile_config) {
-            Ok(ctrl) => {
-                let zbus = ProfileZbus(Arc::new(Mutex::new(ctrl)));
-                let sig_ctx = ProfileZbus::signal_context(&connection)?;
-                start_tasks(zbus, &mut connection, sig_ctx).await?;
-            }
-            Err(err) => {
-                error!("Profile control: {}", err);
-            }
-        }
-    } else {
-        warn!("platform_profile support not found");
+    if let Some(ctrl) = profiles {
+        let zbus = ProfileZbus(ctrl);
+        let sig_ctx = ProfileZbus::signal_context(&connection)?;
+        start_tasks(zbus, &mut connection, &mut registry, sig_ctx, apply_delay).await?;
     }
 
-    match CtrlAnime::new(AnimeConfig::new().load()) {
+    match CtrlAnime::new(AnimeConfig::new().load(), kbd_brightness_rx, &devices) {
         Ok(ctrl) => {
             let zbus = CtrlAnimeZbus(Arc::new(Mutex::new(ctrl)));
             let sig_ctx = CtrlAnimeZbus::signal_context(&connection)?;
-            start_tasks(zbus, &mut connection, sig_ctx).await?;
+            start_tasks(zbus, &mut connection, &mut registry, sig_ctx, apply_delay).await?;
         }
         Err(err) => {
             info!("AniMe control: {}", err);
         }
     }
 
-    let laptop = LaptopLedData::get_data();
-    // CtrlKbdLed deviates from the config pattern above due to requiring a keyboard
-    // detection first
-    match CtrlKbdLed::new(laptop) {
-        Ok(ctrl) => {
-            let zbus = CtrlKbdLedZbus(Arc::new(Mutex::new(ctrl)));
-            let sig_ctx = CtrlKbdLedZbus::signal_context(&connection)?;
-            start_tasks(zbus, &mut connection, sig_ctx).await?;
-        }
-        Err(err) => {
-            error!("Keyboard control: {}", err);
-        }
+    if let Some(ctrl) = aura {
+        let zbus = CtrlKbdLedZbus(ctrl);
+        let sig_ctx = CtrlKbdLedZbus::signal_context(&connection)?;
+        start_tasks(zbus, &mut connection, &mut registry, sig_ctx, apply_delay).await?;
     }
 
     // Request dbus name after finishing initalizing all functions
     connection.request_name(DBUS_NAME).await?;
 
-    loop {
-        // This is just a blocker to idle and ensure the reator reacts
-        sleep(Duration::from_millis(1000)).await;
+    // Idles until systemd stops the service, or Ctrl+C when run by hand
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+    shutdown(connection, registry).await;
+    Ok(())
+}
+
+/// Release the well-known name and remove all interfaces before exiting so
+/// that a quick restart does not race a stale registration. The socket itself
+/// is closed when the process exits, as spawned tasks still hold clones of
+/// the connection.
+async fn shutdown(connection: Connection, registry: Registry) {
+    info!("Shutting down");
+    connection
+        .release_name(DBUS_NAME)
+        .await
+        .map_err(|err| warn!("release_name {}: {}", DBUS_NAME, err))
+        .ok();
+    for (path, remove) in registry.into_iter().rev() {
+        remove(connection.clone(), path).await;
     }
 }
 
+fn remove_from_server<T: Interface>(
+    connection: Connection,
+    path: &'static str,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        match connection.object_server().remove::<T, _>(path).await {
+            Ok(_) => info!("{}: removed from server", path),
+            Err(err) => warn!("{}: remove_from_server {}", path, err),
+        }
+    })
+}
+
+/// Apply the stored state with `reload` and add the controller to the
+/// server. With an `apply_delay` the interface is added straight away and the
+/// state is applied once the delay has passed.
 async fn start_tasks<T>(
     mut zbus: T,
     connection: &mut Connection,
+    registry: &mut Registry,
     signal_ctx: SignalContext<'static>,
+    apply_delay: Duration,
 ) -> Result<(), Box<dyn Error>>
 where
-    T: ZbusRun + Reloadable + CtrlTask + Clone,
+    T: ZbusRun + Reloadable + CtrlTask + Interface + Clone,
 {
     let task = zbus.clone();
 
-    zbus.reload()
-        .await
-        .unwrap_or_else(|err| warn!("Controller error: {}", err));
+    if apply_delay.is_zero() {
+        zbus.reload()
+            .await
+            .unwrap_or_else(|err| warn!("Controller error: {}", err));
+    } else {
+        let mut reload = zbus.clone();
+        tokio::spawn(async move {
+            sleep(apply_delay).await;
+            reload
+                .reload()
+                .await
+                .unwrap_or_else(|err| warn!("Controller error: {}", err));
+        });
+    }
     zbus.add_to_server(connection).await;
+    registry.push((T::zbus_path(), remove_from_server::<T>));
 
     task.create_tasks(signal_ctx).await.ok();
     Ok(())
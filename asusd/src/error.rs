@@ -27,15 +27,27 @@ pub enum RogError {
     Io(std::io::Error),
     Zbus(zbus::Error),
     ChargeLimit(u8),
+    PowerLimit(String, u8, u8, u8),
     AuraEffectNotSupported,
+    AuraEffectInvalid(String),
+    AuraConfigInvalid(String),
     NoAuraKeyboard,
     NoAuraNode,
+    /// Neither a ROG USB keyboard nor a TUF `kbd_rgb_mode` node was found
+    KeyboardNotFound,
+    /// The `asus::kbd_backlight` brightness node is missing
+    BrightnessNodeMissing,
+    /// The running kernel lacks a required interface, the string names it
+    KernelTooOld(String),
+    /// The named mode is not supported, with the list of modes that are
+    AuraModeNotFound(String, Vec<String>),
     Anime(AnimeError),
     Platform(PlatformError),
     SystemdUnitAction(String),
     SystemdUnitWaitTimeout(String),
     Command(String, std::io::Error),
     ParseRon(ron::Error),
+    UnsafeGpuState(String),
 }
 
 impl fmt::Display for RogError {
@@ -68,9 +80,31 @@ impl fmt::Display for RogError {
             RogError::ChargeLimit(value) => {
                 write!(f, "Invalid charging limit, not in range 20-100%: {}", value)
             }
+            RogError::PowerLimit(name, value, min, max) => {
+                write!(
+                    f,
+                    "Invalid {}, not in range {}-{}: {}",
+                    name, min, max, value
+                )
+            }
             RogError::AuraEffectNotSupported => write!(f, "Aura effect not supported"),
+            RogError::AuraEffectInvalid(reason) => write!(f, "Aura effect not valid: {}", reason),
+            RogError::AuraConfigInvalid(reason) => write!(f, "Aura config not valid: {}", reason),
             RogError::NoAuraKeyboard => write!(f, "No supported Aura keyboard"),
             RogError::NoAuraNode => write!(f, "No Aura keyboard node found"),
+            RogError::KeyboardNotFound => write!(f, "No Aura keyboard controller found"),
+            RogError::BrightnessNodeMissing => {
+                write!(f, "Keyboard brightness node asus::kbd_backlight not found")
+            }
+            RogError::KernelTooOld(detail) => {
+                write!(f, "Kernel is too old, please upgrade: {}", detail)
+            }
+            RogError::AuraModeNotFound(name, valid) => write!(
+                f,
+                "Not found: mode {}, valid modes are: {}",
+                name,
+                valid.join(", ")
+            ),
             RogError::Anime(deets) => write!(f, "AniMe Matrix error: {}", deets),
             RogError::Platform(deets) => write!(f, "Asus Platform error: {}", deets),
             RogError::SystemdUnitAction(action) => {
@@ -85,6 +119,7 @@ impl fmt::Display for RogError {
             }
             RogError::Command(func, error) => write!(f, "Command exec error: {}: {}", func, error),
             RogError::ParseRon(error) => write!(f, "Parse config error: {}", error),
+            RogError::UnsafeGpuState(detail) => write!(f, "Refused unsafe GPU change: {}", detail),
         }
     }
 }
@@ -130,6 +165,12 @@ impl From<ron::Error> for RogError {
 impl From<RogError> for zbus::fdo::Error {
     #[inline]
     fn from(err: RogError) -> Self {
-        zbus::fdo::Error::Failed(format!("{}", err))
+        match err {
+            RogError::KeyboardNotFound
+            | RogError::BrightnessNodeMissing
+            | RogError::KernelTooOld(_) => zbus::fdo::Error::NotSupported(format!("{}", err)),
+            RogError::AuraModeNotFound(..) => zbus::fdo::Error::InvalidArgs(format!("{}", err)),
+            _ => zbus::fdo::Error::Failed(format!("{}", err)),
+        }
     }
 }
@@ -18,13 +18,21 @@ pub mod ctrl_supported;
 pub mod error;
 
 use std::future::Future;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use dmi_id::DMIID;
 use futures_lite::stream::StreamExt;
 use log::{debug, info, warn};
 use logind_zbus::manager::ManagerProxy;
+use rog_platform::error::PlatformError;
+use rog_platform::hid_raw::HidRaw;
+use rog_platform::keyboard_led::KeyboardLed;
+use rog_platform::platform::AsusPlatform;
+use rog_platform::power::AsusPower;
+use rog_platform::supported::UnsupportedReason;
+use rog_platform::usb_raw::USBRaw;
 use tokio::time::sleep;
 use zbus::zvariant::ObjectPath;
 use zbus::{CacheProperties, Connection, SignalContext};
@@ -95,12 +103,190 @@ macro_rules! task_watch_item {
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How long the power or lid state must be stable before the sys event
+/// closures are run. Set from the `event_debounce_ms` config option.
+static EVENT_DEBOUNCE_MS: AtomicU64 = AtomicU64::new(1000);
+
+pub fn set_event_debounce(millis: u64) {
+    EVENT_DEBOUNCE_MS.store(millis, Ordering::Relaxed);
+}
+
+fn event_debounce() -> Duration {
+    Duration::from_millis(EVENT_DEBOUNCE_MS.load(Ordering::Relaxed))
+}
+
+/// Where the controllers find their devices. With `--dry-run` every
+/// controller is given the same simulated devices, so a value written by one
+/// is read back by the others.
+#[derive(Debug, Clone)]
+pub enum Devices {
+    Hardware,
+    Simulated {
+        platform: AsusPlatform,
+        power: AsusPower,
+        kbd_led: KeyboardLed,
+    },
+}
+
+impl Devices {
+    pub fn simulated() -> Self {
+        Self::Simulated {
+            platform: AsusPlatform::simulated(),
+            power: AsusPower::simulated(),
+            kbd_led: KeyboardLed::simulated(),
+        }
+    }
+
+    pub fn is_simulated(&self) -> bool {
+        matches!(self, Self::Simulated { .. })
+    }
+
+    /// The ASUS platform device
+    pub fn platform(&self) -> rog_platform::error::Result<AsusPlatform> {
+        match self {
+            Self::Hardware => AsusPlatform::new(),
+            Self::Simulated { platform, .. } => Ok(platform.clone()),
+        }
+    }
+
+    /// The battery and mains supply
+    pub fn power(&self) -> rog_platform::error::Result<AsusPower> {
+        match self {
+            Self::Hardware => AsusPower::new(),
+            Self::Simulated { power, .. } => Ok(power.clone()),
+        }
+    }
+
+    /// The `asus::kbd_backlight` nodes
+    pub fn kbd_led(&self) -> rog_platform::error::Result<KeyboardLed> {
+        match self {
+            Self::Hardware => KeyboardLed::new(),
+            Self::Simulated { kbd_led, .. } => Ok(kbd_led.clone()),
+        }
+    }
+
+    /// The hidraw node for `id_product`. Every product is found when
+    /// simulated.
+    pub fn hidraw(&self, id_product: &str) -> rog_platform::error::Result<HidRaw> {
+        match self {
+            Self::Hardware => HidRaw::new(id_product),
+            Self::Simulated { .. } => Ok(HidRaw::simulated(id_product)),
+        }
+    }
+
+    /// The raw USB device `id_product`. This can not be simulated, so it is
+    /// never found when simulated and the hidraw node is used instead.
+    pub fn usb(&self, id_product: u16) -> rog_platform::error::Result<USBRaw> {
+        match self {
+            Self::Hardware => USBRaw::new(id_product),
+            Self::Simulated { .. } => Err(PlatformError::NotSupported),
+        }
+    }
+}
+
+/// Tracks a polled bool state and only reports a change once the new state
+/// has held for the debounce window
+struct Debounced {
+    name: &'static str,
+    last: bool,
+    pending: Option<(bool, Instant)>,
+}
+
+impl Debounced {
+    fn new(name: &'static str, last: bool) -> Self {
+        Self {
+            name,
+            last,
+            pending: None,
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Returns the new state if it is now considered stable
+    fn update(&mut self, next: bool, now: Instant, window: Duration) -> Option<bool> {
+        if next == self.last {
+            if self.pending.take().is_some() {
+                info!(
+                    "{}: ignored change that reverted within {}ms",
+                    self.name,
+                    window.as_millis()
+                );
+            }
+            return None;
+        }
+        match self.pending {
+            Some((pending, since)) if pending == next => {
+                if now.duration_since(since) < window {
+                    return None;
+                }
+            }
+            _ => {
+                if !window.is_zero() {
+                    self.pending = Some((next, now));
+                    return None;
+                }
+            }
+        }
+        self.pending = None;
+        self.last = next;
+        Some(next)
+    }
+}
+
 pub fn print_board_info() {
     let dmi = DMIID::new().unwrap_or_default();
     info!("Product family: {}", dmi.product_family);
     info!("Board name: {}", dmi.board_name);
 }
 
+/// Run `on_change` each time the config file `file_name` in `CONFIG_PATH_BASE`
+/// is written. The directory is watched rather than the file because
+/// `StdConfig::write()` and most editors rename a new file over the old one,
+/// which would end a watch on the file itself.
+///
+/// asusd's own writes also trigger `on_change`, so it should check the file
+/// actually differs with `StdConfig::read_if_changed()`.
+pub fn watch_config_file<F, Fut>(file_name: String, mut on_change: F) -> Result<(), RogError>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let watch = inotify::Inotify::init()?;
+    watch
+        .watches()
+        .add(
+            CONFIG_PATH_BASE,
+            inotify::WatchMask::CLOSE_WRITE | inotify::WatchMask::MOVED_TO,
+        )
+        .map_err(|e| RogError::Path(CONFIG_PATH_BASE.to_owned(), e))?;
+    tokio::spawn(async move {
+        let mut buffer = [0; 1024];
+        let mut stream = match watch.into_event_stream(&mut buffer) {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Config watch of {file_name} failed: {e}");
+                return;
+            }
+        };
+        while let Some(event) = stream.next().await {
+            let is_config = match event {
+                Ok(event) => {
+                    event.name.as_deref().and_then(|name| name.to_str()) == Some(&file_name)
+                }
+                Err(_) => false,
+            };
+            if is_config {
+                debug!("Config file {file_name} was written");
+                on_change().await;
+            }
+        }
+    });
+    Ok(())
+}
+
 #[async_trait]
 pub trait Reloadable {
     async fn reload(&mut self) -> Result<(), RogError>;
@@ -219,23 +405,31 @@ pub trait CtrlTask {
             .expect("Controller could not create ManagerProxy");
 
         tokio::spawn(async move {
-            let mut last_power = manager.on_external_power().await.unwrap_or_default();
-            let mut last_lid = manager.lid_closed().await.unwrap_or_default();
+            let mut power = Debounced::new(
+                "on_external_power",
+                manager.on_external_power().await.unwrap_or_default(),
+            );
+            let mut lid =
+                Debounced::new("lid_closed", manager.lid_closed().await.unwrap_or_default());
             // need to loop on these as they don't emit signals
             loop {
+                let window = event_debounce();
                 if let Ok(next) = manager.on_external_power().await {
-                    if next != last_power {
-                        last_power = next;
+                    if let Some(next) = power.update(next, Instant::now(), window) {
                         on_external_power_change(next).await;
                     }
                 }
                 if let Ok(next) = manager.lid_closed().await {
-                    if next != last_lid {
-                        last_lid = next;
+                    if let Some(next) = lid.update(next, Instant::now(), window) {
                         on_lid_change(next).await;
                     }
                 }
-                sleep(Duration::from_secs(2)).await;
+                // Poll again sooner while a change is waiting to settle
+                if power.is_pending() || lid.is_pending() {
+                    sleep(window.min(Duration::from_secs(2))).await;
+                } else {
+                    sleep(Duration::from_secs(2)).await;
+                }
             }
         });
     }
@@ -244,5 +438,70 @@ pub trait CtrlTask {
 pub trait GetSupported {
     type A;
 
-    fn get_supported() -> Self::A;
+    fn get_supported(devices: &Devices) -> Self::A;
+
+    /// Why each feature in `supported` is unsupported, to be shown to the user
+    fn unsupported_reasons(_supported: &Self::A, _devices: &Devices) -> Vec<UnsupportedReason> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Debounced, Devices};
+
+    #[test]
+    fn debounce_power_flap() {
+        let window = Duration::from_millis(1000);
+        let start = Instant::now();
+        let mut power = Debounced::new("power", true);
+
+        // Unplugged then replugged before the window elapsed
+        assert_eq!(power.update(false, start, window), None);
+        assert!(power.is_pending());
+        assert_eq!(
+            power.update(true, start + Duration::from_millis(500), window),
+            None
+        );
+        assert!(!power.is_pending());
+
+        // Unplugged and stays that way
+        assert_eq!(power.update(false, start, window), None);
+        assert_eq!(
+            power.update(false, start + Duration::from_millis(500), window),
+            None
+        );
+        assert_eq!(
+            power.update(false, start + Duration::from_millis(1000), window),
+            Some(false)
+        );
+        assert_eq!(
+            power.update(false, start + Duration::from_millis(3000), window),
+            None
+        );
+
+        // No window means changes apply immediately
+        assert_eq!(power.update(true, start, Duration::ZERO), Some(true));
+    }
+
+    #[test]
+    fn simulated_devices_are_shared() {
+        let devices = Devices::simulated();
+        devices.kbd_led().unwrap().set_brightness(0).unwrap();
+        assert_eq!(devices.kbd_led().unwrap().get_brightness().unwrap(), 0);
+        assert_eq!(
+            devices.clone().kbd_led().unwrap().get_brightness().unwrap(),
+            0
+        );
+
+        // Another set of simulated devices has its own state
+        let other = Devices::simulated();
+        assert_eq!(other.kbd_led().unwrap().get_brightness().unwrap(), 2);
+
+        // Raw USB can not be simulated, hidraw is used instead
+        assert!(devices.usb(0x193b).is_err());
+        assert!(devices.hidraw("193b").is_ok());
+    }
 }
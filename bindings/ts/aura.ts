@@ -107,6 +107,18 @@ export interface AuraEffect {
 	direction: Direction;
 }
 
+/**
+ * Which `AuraEffect` fields a mode uses. Fields that are `false` are ignored
+ * by the keyboard for that mode.
+ */
+export interface AuraParameters {
+	zone: boolean;
+	colour1: boolean;
+	colour2: boolean;
+	speed: boolean;
+	direction: boolean;
+}
+
 /** The powerr zones this laptop supports */
 export enum PowerZones {
 	/** The logo on some laptop lids */
@@ -121,6 +133,22 @@ export enum PowerZones {
 	RearGlow = "RearGlow",
 }
 
+/**
+ * The detected keyboard and its physical layout, so that clients can pick
+ * the right editor without per-model assumptions
+ */
+export interface KeyboardInfo {
+	/** The USB product ID such as `19b6`, or `tuf` */
+	product_id: string;
+	device: AuraDevice;
+	/** The name of the per-key layout file, e.g `ga401` */
+	layout_name: string;
+	/** How many basic zones the keyboard has, 0 if not zoned */
+	zone_count: number;
+	per_key: boolean;
+	multizone: boolean;
+}
+
 export interface KbAuraPowerState {
 	zone: PowerZones;
 	boot: boolean;
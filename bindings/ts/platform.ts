@@ -45,6 +45,39 @@ export interface SupportedFunctions {
 	rog_bios_ctrl: RogBiosSupportedFunctions;
 }
 
+/**
+ * Why a feature is not supported. `feature` is the path of the flag in
+ * `SupportedFunctions`, such as `rog_bios_ctrl.panel_overdrive`.
+ */
+export interface UnsupportedReason {
+	feature: string;
+	reason: string;
+}
+
+/**
+ * `SupportedFunctions` along with the reason for each feature that is not
+ * supported
+ */
+export interface SupportedDetails {
+	functions: SupportedFunctions;
+	reasons: UnsupportedReason[];
+}
+
+/**
+ * The device nodes the daemon has claimed, for bug reports. Any that were not
+ * found are set to `NODE_NOT_FOUND`.
+ */
+export interface DeviceNodes {
+	/** The `hidraw` or `asus::kbd_backlight` node used for LED modes */
+	keyboard_led: string;
+	/** The `asus::kbd_backlight` node used for brightness */
+	keyboard_brightness: string;
+	/** The syspath of the `asus-nb-wmi` or `asus-armoury` platform device */
+	platform: string;
+	/** The `hidraw` or USB node of the AniMe Matrix */
+	anime: string;
+}
+
 export enum GpuMode {
 	Discrete = "Discrete",
 	Optimus = "Optimus",
@@ -56,3 +89,53 @@ export enum GpuMode {
 	NotSupported = "NotSupported",
 }
 
+/** Battery wear and charge as read from the `power_supply` battery */
+export interface BatteryHealth {
+	/** Current charge as a percentage */
+	capacity: number;
+	/** Charge cycles, `0` if the battery does not report them */
+	cycle_count: number;
+	/** Full capacity when new, in µWh or µAh depending on the battery */
+	design_capacity: number;
+	/** Full capacity now, in the same unit as `design_capacity` */
+	full_capacity: number;
+	/** `full_capacity` as a percentage of `design_capacity` */
+	health: number;
+}
+
+/** If the dGPU is powered, as read from its runtime power management */
+export enum DgpuPower {
+	Active = "Active",
+	Suspended = "Suspended",
+	/** Disabled with `dgpu_disable` or not found on the PCI bus */
+	Off = "Off",
+}
+
+/** Whether the laptop is running from mains power or the battery */
+export enum PowerSource {
+	Ac = "Ac",
+	Battery = "Battery",
+}
+
+/**
+ * The `throttle_thermal_policy` values. On most laptops this is tied to
+ * `platform_profile` in-kernel, but on some it can be set on its own.
+ */
+export enum ThermalPolicy {
+	Default = "Default",
+	Performance = "Performance",
+	Silent = "Silent",
+}
+
+/**
+ * The `mini_led_mode` levels. Older panels only support `Off` and
+ * `MultiZone`.
+ */
+export enum MiniLedLevel {
+	Off = "Off",
+	/** Local dimming across the panel zones */
+	MultiZone = "MultiZone",
+	/** Local dimming with a stronger contrast */
+	MultiZoneStrong = "MultiZoneStrong",
+}
+
@@ -37,8 +37,10 @@ where
     fn file_path(&self) -> PathBuf {
         let mut config = Self::config_dir();
         if !config.exists() {
-            create_dir(config.as_path())
-                .unwrap_or_else(|e| panic!("Could not create {:?} {e}", Self::config_dir()));
+            // Opening the file will fail later, and that can be handled there
+            create_dir(config.as_path()).unwrap_or_else(|e| {
+                error!("Could not create {:?} {e}", Self::config_dir());
+            });
         }
         config.push(self.file_name());
         let mut do_rename = !config.exists();
@@ -88,48 +90,94 @@ where
     /// does not exist it is created, including the directories the file
     /// resides in.
     fn file_open(&self) -> File {
+        self.try_file_open()
+            .unwrap_or_else(|e| panic!("Could not open {:?} {e}", self.file_path()))
+    }
+
+    /// As `file_open()` but returns the error instead of panicking
+    fn try_file_open(&self) -> std::io::Result<File> {
         OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
             .open(self.file_path())
-            .unwrap_or_else(|e| panic!("Could not open {:?} {e}", self.file_path()))
+            .map_err(|e| path_error(self.file_path(), e))
     }
 
     /// Open and parse the config file to self from ron format
     fn read(&mut self) {
-        if let Ok(data) = fs::read_to_string(self.file_path()) {
-            if data.is_empty() {
-                warn!("File is empty {:?}", self.file_path());
-            } else if let Ok(data) = ron::from_str(&data) {
-                *self = data;
-            } else {
-                warn!("Could not deserialise {:?}", self.file_path());
-            }
-        }
+        self.try_read().unwrap_or_else(|e| warn!("{e}"));
     }
 
-    /// Write the config file data to pretty ron format
-    fn write(&self) {
-        let mut file = match File::create(self.file_path()) {
+    /// As `read()` but returns the error. A missing or empty file is not an
+    /// error and leaves self unchanged.
+    fn try_read(&mut self) -> std::io::Result<()> {
+        let data = match fs::read_to_string(self.file_path()) {
             Ok(data) => data,
-            Err(e) => {
-                error!(
-                    "Couldn't overwrite config {:?}, error: {e}",
-                    self.file_path()
-                );
-                return;
-            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(path_error(self.file_path(), e)),
         };
-        let ron = match ron::ser::to_string_pretty(&self, PrettyConfig::new().depth_limit(4)) {
-            Ok(data) => data,
-            Err(e) => {
-                error!("Parse {:?} to RON failed, error: {e}", self.file_path());
-                return;
-            }
+        if data.is_empty() {
+            warn!("File is empty {:?}", self.file_path());
+            return Ok(());
+        }
+        *self = ron::from_str(&data).map_err(|e| {
+            path_error(
+                self.file_path(),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Parse the config file and return it only if it differs from self, such
+    /// as after it was edited by hand. Both are compared as serialised `ron`,
+    /// so a file written by `write()` from the same data is not a change.
+    fn read_if_changed(&self) -> std::io::Result<Option<Self>> {
+        let data =
+            fs::read_to_string(self.file_path()).map_err(|e| path_error(self.file_path(), e))?;
+        let other: Self = ron::from_str(&data).map_err(|e| {
+            path_error(
+                self.file_path(),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+            )
+        })?;
+        let to_ron = |config: &Self| {
+            ron::ser::to_string(config)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
         };
+        if to_ron(self)? == to_ron(&other)? {
+            return Ok(None);
+        }
+        Ok(Some(other))
+    }
+
+    /// Write the config file data to pretty ron format
+    fn write(&self) {
+        self.try_write()
+            .unwrap_or_else(|e| error!("Could not write config: {e}"));
+    }
+
+    /// As `write()` but returns the error. The data is written to
+    /// `<file>.tmp` first and then renamed over the config, so a crash part way
+    /// through leaves the previous config intact.
+    fn try_write(&self) -> std::io::Result<()> {
+        let path = self.file_path();
+        let ron =
+            ron::ser::to_string_pretty(&self, PrettyConfig::new().depth_limit(4)).map_err(|e| {
+                path_error(
+                    path.clone(),
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                )
+            })?;
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = File::create(&tmp).map_err(|e| path_error(tmp.clone(), e))?;
         file.write_all(ron.as_bytes())
-            .unwrap_or_else(|err| error!("Could not write config: {}", err));
+            .and_then(|_| file.sync_all())
+            .map_err(|e| path_error(tmp.clone(), e))?;
+        fs::rename(&tmp, &path).map_err(|e| path_error(path, e))
     }
 
     /// Renames the existing file to `<file>-old`
@@ -151,6 +199,11 @@ where
     }
 }
 
+/// Add the path to an IO error so that the logged error is useful
+fn path_error(path: PathBuf, err: std::io::Error) -> std::io::Error {
+    std::io::Error::new(err.kind(), format!("{path:?}: {err}"))
+}
+
 #[macro_export]
 macro_rules! std_config_load {
     ($trait_name:ident: $($generic:ident),*) => {
@@ -203,28 +256,7 @@ macro_rules! std_config_load {
                 let mut buf = String::new();
                 if let Ok(read_len) = file.read_to_string(&mut buf) {
                     if read_len != 0 {
-                        if let Ok(data) = ron::from_str(&buf) {
-                            self = data;
-                            log::info!("Parsed RON for {:?}", std::any::type_name::<Self>());
-                        } else if let Ok(data) = serde_json::from_str(&buf) {
-                            self = data;
-                            log::info!("Parsed JSON for {:?}", std::any::type_name::<Self>());
-                        } else if let Ok(data) = toml::from_str(&buf) {
-                            self = data;
-                            log::info!("Parsed TOML for {:?}", std::any::type_name::<Self>());
-                        } $(else if let Ok(data) = ron::from_str::<$generic>(&buf) {
-                            self = data.into();
-                            log::info!("New version failed, trying previous: Parsed RON for {:?}", std::any::type_name::<$generic>());
-                        } else if let Ok(data) = serde_json::from_str::<$generic>(&buf) {
-                            self = data.into();
-                            log::info!("New version failed, trying previous: Parsed JSON for {:?}", std::any::type_name::<$generic>());
-                        } else if let Ok(data) = toml::from_str::<$generic>(&buf) {
-                            self = data.into();
-                            log::info!("Newvious version failed, trying previous: Parsed TOML for {:?}", std::any::type_name::<$generic>());
-                        })* else {
-                            self.rename_file_old();
-                            self = Self::new();
-                        }
+                        self = self.parse_or_new(&buf);
                     } else {
                         error!("Config file {} zero read length", self.file_name());
                     }
@@ -232,6 +264,51 @@ macro_rules! std_config_load {
                 self.write();
                 self
             }
+
+            /// As `load()` but returns any error from opening, reading, or
+            /// writing the file instead of panicking or only logging it. A
+            /// config that can not be parsed is still renamed to `<file>-old`
+            /// and recreated.
+            fn try_load(mut self) -> std::io::Result<Self> {
+                let mut file = self.try_file_open()?;
+                let mut buf = String::new();
+                file.read_to_string(&mut buf)?;
+                if buf.is_empty() {
+                    error!("Config file {} zero read length", self.file_name());
+                } else {
+                    self = self.parse_or_new(&buf);
+                }
+                self.try_write()?;
+                Ok(self)
+            }
+
+            /// Parse the file contents from any of the supported formats and
+            /// versions, or rename the file and return `new()` if none match
+            fn parse_or_new(mut self, buf: &str) -> Self {
+                if let Ok(data) = ron::from_str(buf) {
+                    self = data;
+                    log::info!("Parsed RON for {:?}", std::any::type_name::<Self>());
+                } else if let Ok(data) = serde_json::from_str(buf) {
+                    self = data;
+                    log::info!("Parsed JSON for {:?}", std::any::type_name::<Self>());
+                } else if let Ok(data) = toml::from_str(buf) {
+                    self = data;
+                    log::info!("Parsed TOML for {:?}", std::any::type_name::<Self>());
+                } $(else if let Ok(data) = ron::from_str::<$generic>(buf) {
+                    self = data.into();
+                    log::info!("New version failed, trying previous: Parsed RON for {:?}", std::any::type_name::<$generic>());
+                } else if let Ok(data) = serde_json::from_str::<$generic>(buf) {
+                    self = data.into();
+                    log::info!("New version failed, trying previous: Parsed JSON for {:?}", std::any::type_name::<$generic>());
+                } else if let Ok(data) = toml::from_str::<$generic>(buf) {
+                    self = data.into();
+                    log::info!("Newvious version failed, trying previous: Parsed TOML for {:?}", std::any::type_name::<$generic>());
+                })* else {
+                    self.rename_file_old();
+                    self = Self::new();
+                }
+                self
+            }
         }
     };
 }
@@ -325,4 +402,88 @@ mod tests {
 
         impl crate::StdConfigLoad3<Old1, Old2, Old3> for Test {}
     }
+
+    #[test]
+    fn write_is_atomic() {
+        use crate::StdConfig;
+
+        #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+        struct Test {
+            value: u32,
+        }
+
+        impl StdConfig for Test {
+            fn new() -> Self {
+                Self { value: 0 }
+            }
+
+            fn file_name(&self) -> String {
+                "test.ron".to_owned()
+            }
+
+            fn config_dir() -> PathBuf {
+                std::env::temp_dir().join("config-traits-write-is-atomic")
+            }
+        }
+
+        std::fs::remove_dir_all(Test::config_dir()).ok();
+        Test { value: 1 }.try_write().unwrap();
+
+        // A crash while writing the next config leaves a partial temp file
+        let mut tmp = Test::new().file_path().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, "(val").unwrap();
+
+        let mut config = Test::new();
+        config.try_read().unwrap();
+        assert_eq!(config, Test { value: 1 });
+
+        Test { value: 2 }.try_write().unwrap();
+        config.try_read().unwrap();
+        assert_eq!(config, Test { value: 2 });
+        assert!(!PathBuf::from(tmp).exists());
+
+        std::fs::remove_dir_all(Test::config_dir()).ok();
+    }
+
+    #[test]
+    fn read_if_changed_ignores_own_writes() {
+        use crate::StdConfig;
+
+        #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+        struct Test {
+            value: u32,
+        }
+
+        impl StdConfig for Test {
+            fn new() -> Self {
+                Self { value: 0 }
+            }
+
+            fn file_name(&self) -> String {
+                "test.ron".to_owned()
+            }
+
+            fn config_dir() -> PathBuf {
+                std::env::temp_dir().join("config-traits-read-if-changed")
+            }
+        }
+
+        std::fs::remove_dir_all(Test::config_dir()).ok();
+        let config = Test { value: 1 };
+        config.try_write().unwrap();
+        assert_eq!(config.read_if_changed().unwrap(), None);
+
+        // Formatting differences are not a change
+        std::fs::write(config.file_path(), "(\n  value:   1,\n)\n").unwrap();
+        assert_eq!(config.read_if_changed().unwrap(), None);
+
+        std::fs::write(config.file_path(), "(value: 2)").unwrap();
+        assert_eq!(config.read_if_changed().unwrap(), Some(Test { value: 2 }));
+
+        std::fs::write(config.file_path(), "(val").unwrap();
+        assert!(config.read_if_changed().is_err());
+
+        std::fs::remove_dir_all(Test::config_dir()).ok();
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::str::FromStr;
 use std::thread::sleep;
@@ -10,6 +11,7 @@ use typeshare::typeshare;
 use zbus::zvariant::Type;
 
 use crate::error::{AnimeError, Result};
+use crate::image::AnimeImage;
 use crate::usb::{AnimAwake, AnimBooting, AnimShutdown, AnimSleeping, Brightness};
 use crate::{AnimTime, AnimeGif};
 
@@ -150,6 +152,53 @@ impl AnimeDataBuffer {
 
         Ok(Self { data, anime })
     }
+
+    /// Rotate the image 180 degrees, for when the display is seen upside down
+    /// such as with the laptop in tent mode. The display shape is not
+    /// symmetrical so LEDs with no LED at their rotated position are left off.
+    pub fn rotate_180(&mut self) {
+        // GA401 data has one leading byte before the first LED
+        let offset = usize::from(self.anime == AnimeType::GA401);
+        // Odd rows are offset by half an LED, so positions are kept in half LEDs
+        let keys: Vec<Option<(i32, i32)>> = AnimeImage::generate_image_positioning(self.anime)
+            .iter()
+            .map(|led| {
+                led.as_ref()
+                    .map(|led| ((led.x() * 2.0).round() as i32, led.y() as i32))
+            })
+            .collect();
+        let mut min = (i32::MAX, i32::MAX);
+        let mut max = (i32::MIN, i32::MIN);
+        for (x, y) in keys.iter().flatten() {
+            min = (min.0.min(*x), min.1.min(*y));
+            max = (max.0.max(*x), max.1.max(*y));
+        }
+        let index: HashMap<(i32, i32), usize> = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| key.map(|key| (key, i)))
+            .collect();
+
+        let mut rotated = vec![0u8; self.data.len()];
+        for (i, key) in keys.iter().enumerate() {
+            if let Some((x, y)) = key {
+                let (x, y) = (min.0 + max.0 - x, min.1 + max.1 - y);
+                // The rotated position can fall between two LEDs of a row
+                let source = index
+                    .get(&(x, y))
+                    .or_else(|| index.get(&(x + 1, y)))
+                    .or_else(|| index.get(&(x - 1, y)));
+                if let Some(source) = source {
+                    if let (Some(to), Some(from)) =
+                        (rotated.get_mut(i + offset), self.data.get(source + offset))
+                    {
+                        *to = *from;
+                    }
+                }
+            }
+        }
+        self.data = rotated;
+    }
 }
 
 /// The packets to be written to USB
@@ -273,3 +322,38 @@ pub fn run_animation(frames: &AnimeGif, callback: &dyn Fn(AnimeDataBuffer) -> Re
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::image::AnimeImage;
+    use crate::{AnimeDataBuffer, AnimeType};
+
+    #[test]
+    fn rotate_180() {
+        for anime in [AnimeType::GA401, AnimeType::GA402, AnimeType::GU604] {
+            let offset = usize::from(anime == AnimeType::GA401);
+            let leds = AnimeImage::generate_image_positioning(anime);
+            let row = |i: usize| leds[i].as_ref().map(|led| led.y() as u32);
+            let last_row = leds.iter().flatten().map(|led| led.y() as u32).max();
+
+            // Light the top row only
+            let mut buffer = AnimeDataBuffer::new(anime);
+            for (i, led) in leds.iter().enumerate() {
+                if row(i) == Some(0) && led.is_some() {
+                    buffer.data_mut()[i + offset] = 255;
+                }
+            }
+
+            buffer.rotate_180();
+            let lit: Vec<usize> = buffer
+                .data()
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b != 0)
+                .map(|(i, _)| i - offset)
+                .collect();
+            assert!(!lit.is_empty());
+            assert!(lit.iter().all(|i| row(*i) == last_row));
+        }
+    }
+}
@@ -1,13 +1,21 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
 use log::warn;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 #[cfg(feature = "dbus")]
 use zbus::zvariant::Type;
 
+use crate::error::Error;
+use crate::Colour;
+
 /// The `LedCode` used in setting up keyboard layouts is important because it
 /// determines the idexing for an RGB value in the final USB packets (for
 /// per-key addressable keyboards).
-#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "dbus", derive(Type), zvariant(signature = "s"))]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
 pub enum LedCode {
     VolUp,
     VolDown,
@@ -166,6 +174,27 @@ pub enum LedCode {
 }
 
 impl LedCode {
+    /// Find a key by its `LedCode` name such as `Return` or `W`. The first
+    /// letter may also be lowercase, and a few common names such as `Enter`
+    /// are accepted.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        match name.to_lowercase().as_str() {
+            "enter" => return Some(LedCode::Return),
+            "escape" => return Some(LedCode::Esc),
+            "delete" => return Some(LedCode::Del),
+            "space" => return Some(LedCode::Spacebar),
+            _ => {}
+        }
+        let parse = |n: String| serde_json::from_value::<LedCode>(serde_json::Value::String(n));
+        if let Ok(code) = parse(name.to_owned()) {
+            return Some(code);
+        }
+        let mut chars = name.chars();
+        let first = chars.next()?;
+        parse(first.to_uppercase().chain(chars).collect()).ok()
+    }
+
     pub fn is_placeholder(&self) -> bool {
         matches!(self, Self::Spacing | Self::Blocking)
     }
@@ -194,6 +223,31 @@ impl LedCode {
 #[typeshare]
 pub type UsbPackets = Vec<Vec<u8>>;
 
+/// A map of keys to the colour they should be set to
+pub type KeyMap = BTreeMap<LedCode, Colour>;
+
+/// Parse a keymap from JSON where each key name (as in `LedCode`) is mapped to
+/// a hex colour, for example:
+///
+/// ```json
+/// { "Esc": "ff0000", "W": "#00ff00", "A": "00ff00" }
+/// ```
+pub fn keymap_from_json(json: &str) -> Result<KeyMap, Error> {
+    let raw: BTreeMap<LedCode, String> = serde_json::from_str(json)?;
+    let mut map = KeyMap::new();
+    for (key, colour) in raw {
+        map.insert(key, Colour::from_str(colour.trim_start_matches('#'))?);
+    }
+    Ok(map)
+}
+
+/// Load a keymap from a JSON file. See `keymap_from_json` for the format.
+pub fn keymap_from_json_file(path: &Path) -> Result<KeyMap, Error> {
+    let buf = std::fs::read_to_string(path)
+        .map_err(|e| Error::IoPath(path.to_string_lossy().to_string(), e))?;
+    keymap_from_json(&buf)
+}
+
 /// A `UsbPackets` contains all data to change the full set of keyboard
 /// key colours individually.
 ///
@@ -288,14 +342,17 @@ impl LedUsbPackets {
         init
     }
 
-    /// Set the RGB colour of an `LedCode`
+    /// Set the RGB colour of an `LedCode`. Returns `false` if the key has no
+    /// position in the packets.
     #[inline]
-    pub fn set(&mut self, key: LedCode, r: u8, g: u8, b: u8) {
+    pub fn set(&mut self, key: LedCode, r: u8, g: u8, b: u8) -> bool {
         if let Some(c) = self.rgb_for_led_code(key) {
             c[0] = r;
             c[1] = g;
             c[2] = b;
+            return true;
         }
+        false
     }
 
     /// Indexes in to `UsbPackets` at the correct row and column
@@ -490,9 +547,21 @@ impl From<LedUsbPackets> for UsbPackets {
     }
 }
 
+impl From<&KeyMap> for LedUsbPackets {
+    /// Create per-key packets from the keymap. Keys not in the map are off.
+    fn from(map: &KeyMap) -> Self {
+        let mut packets = Self::new_per_key();
+        for (key, colour) in map {
+            packets.set(*key, colour.r, colour.g, colour.b);
+        }
+        packets
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::advanced::{LedCode, LedUsbPackets, UsbPackets};
+    use crate::advanced::{keymap_from_json, LedCode, LedUsbPackets, UsbPackets};
+    use crate::Colour;
 
     macro_rules! colour_check_zoned {
         ($zone:expr, $pkt_idx_start:expr) => {
@@ -577,4 +646,25 @@ mod tests {
         assert_eq!(pkt[7][14], 0xff); // M
         assert_eq!(pkt[7][15], 0x00); // M
     }
+
+    #[test]
+    fn keymap_json_to_packet_check() {
+        let map = keymap_from_json(r##"{ "D": "ff0000", "M": "#00ff00" }"##).unwrap();
+        assert_eq!(
+            map.get(&LedCode::D),
+            Some(&Colour {
+                r: 0xff,
+                g: 0x00,
+                b: 0x00
+            })
+        );
+
+        let pkt: UsbPackets = LedUsbPackets::from(&map).into();
+        assert_eq!(&pkt[5][30..33], &[0xff, 0x00, 0x00]); // D
+        assert_eq!(&pkt[7][12..15], &[0x00, 0xff, 0x00]); // M
+        assert_eq!(&pkt[4][33..36], &[0x00, 0x00, 0x00]); // O
+
+        assert!(keymap_from_json(r#"{ "NotAKey": "ff0000" }"#).is_err());
+        assert!(keymap_from_json(r#"{ "D": "fff" }"#).is_err());
+    }
 }
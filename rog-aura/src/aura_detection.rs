@@ -140,6 +140,35 @@ impl LedSupportFile {
     }
 }
 
+/// The detected keyboard and its physical layout, so that clients can pick
+/// the right editor without per-model assumptions
+#[typeshare]
+#[derive(Type, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyboardInfo {
+    /// The USB product ID such as `19b6`, or `tuf`
+    pub product_id: String,
+    pub device: AuraDevice,
+    /// The name of the per-key layout file, e.g `ga401`
+    pub layout_name: String,
+    /// How many basic zones the keyboard has, 0 if not zoned
+    pub zone_count: u32,
+    pub per_key: bool,
+    pub multizone: bool,
+}
+
+impl KeyboardInfo {
+    pub fn new(device: AuraDevice, data: &LaptopLedData) -> Self {
+        Self {
+            product_id: <&str>::from(device).to_owned(),
+            device,
+            layout_name: data.layout_name.clone(),
+            zone_count: data.basic_zones.len() as u32,
+            per_key: matches!(data.advanced_type, AdvancedAuraType::PerKey),
+            multizone: !data.basic_zones.is_empty(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::OpenOptions;
@@ -148,12 +177,36 @@ mod tests {
 
     use ron::ser::PrettyConfig;
 
-    use super::LaptopLedData;
+    use super::{KeyboardInfo, LaptopLedData};
     use crate::advanced::LedCode;
     use crate::aura_detection::{LedSupportFile, PowerZones};
+    use crate::usb::AuraDevice;
     // use crate::zoned::Zone;
     use crate::{AdvancedAuraType, AuraModeNum, AuraZone};
 
+    #[test]
+    fn keyboard_info_from_data() {
+        let data = LaptopLedData {
+            board_name: "GA401Q".to_owned(),
+            layout_name: "ga401q".to_owned(),
+            basic_modes: vec![AuraModeNum::Static],
+            basic_zones: vec![
+                AuraZone::Key1,
+                AuraZone::Key2,
+                AuraZone::Key3,
+                AuraZone::Key4,
+            ],
+            advanced_type: AdvancedAuraType::None,
+            power_zones: vec![PowerZones::Keyboard],
+        };
+        let info = KeyboardInfo::new(AuraDevice::X1866, &data);
+        assert_eq!(info.product_id, "1866");
+        assert_eq!(info.layout_name, "ga401q");
+        assert_eq!(info.zone_count, 4);
+        assert!(info.multizone);
+        assert!(!info.per_key);
+    }
+
     #[test]
     fn check_data_parse() {
         let led = LaptopLedData {
@@ -37,6 +37,35 @@ impl From<u32> for LedBrightness {
     }
 }
 
+/// Percentages map to the nearest level:
+///
+/// | Percent | Level | As percent |
+/// |---------|-------|------------|
+/// | 0-16    | Off   | 0          |
+/// | 17-49   | Low   | 33         |
+/// | 50-83   | Med   | 67         |
+/// | 84-100  | High  | 100        |
+impl LedBrightness {
+    /// The nearest level to a percentage, anything over 100 is `High`
+    pub fn from_percent(pct: u8) -> Self {
+        match pct {
+            0..=16 => LedBrightness::Off,
+            17..=49 => LedBrightness::Low,
+            50..=83 => LedBrightness::Med,
+            _ => LedBrightness::High,
+        }
+    }
+
+    pub fn to_percent(self) -> u8 {
+        match self {
+            LedBrightness::Off => 0,
+            LedBrightness::Low => 33,
+            LedBrightness::Med => 67,
+            LedBrightness::High => 100,
+        }
+    }
+}
+
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
 #[derive(Debug, Clone, PartialEq, Eq, Copy, Deserialize, Serialize)]
@@ -66,6 +95,50 @@ impl FromStr for Colour {
     }
 }
 
+/// Per-channel gamma of `(r, g, b)` that leaves colours unchanged
+pub const GAMMA_NONE: (f32, f32, f32) = (1.0, 1.0, 1.0);
+
+/// Apply a gamma correction to a single colour channel. `0` and `255` are
+/// always unchanged, a `gamma` above `1.0` darkens the values between.
+pub fn gamma_correct(value: u8, gamma: f32) -> u8 {
+    (255.0 * (value as f32 / 255.0).powf(gamma)).round() as u8
+}
+
+impl Colour {
+    /// Parse a `#RRGGBB` or `RRGGBB` hex colour. Unlike `from_str` this
+    /// requires exactly six hex digits.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::ParseColour);
+        }
+        Colour::from_str(hex)
+    }
+
+    /// The colour scaled to a brightness level, `High` is unchanged and
+    /// `Off` is black
+    pub fn scaled(self, brightness: LedBrightness) -> Self {
+        let level = brightness as u16;
+        let scale = |c: u8| (c as u16 * level / LedBrightness::High as u16) as u8;
+        Self {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
+        }
+    }
+
+    /// The colour with a per-channel `(r, g, b)` gamma correction applied, to
+    /// correct for the colour cast of a keyboard
+    pub fn gamma_corrected(self, gamma: (f32, f32, f32)) -> Self {
+        Self {
+            r: gamma_correct(self.r, gamma.0),
+            g: gamma_correct(self.g, gamma.1),
+            b: gamma_correct(self.b, gamma.2),
+        }
+    }
+}
+
 impl From<&[f32; 3]> for Colour {
     fn from(c: &[f32; 3]) -> Self {
         Self {
@@ -253,7 +326,7 @@ impl From<u8> for AuraModeNum {
 /// Base effects have no zoning, while multizone is 1-4
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type), zvariant(signature = "s"))]
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum AuraZone {
     /// Used if keyboard has no zones, or if setting all
     #[default]
@@ -354,6 +427,11 @@ impl Default for AuraEffect {
     }
 }
 
+/// Which `AuraEffect` fields a mode uses. Fields that are `false` are ignored
+/// by the keyboard for that mode.
+#[typeshare]
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct AuraParameters {
     pub zone: bool,
     pub colour1: bool,
@@ -382,6 +460,27 @@ impl AuraParameters {
 }
 
 impl AuraEffect {
+    /// A copy of the effect with its colours scaled to `brightness`. Used to
+    /// dim a single zone, such as the lightbar, as the keyboard brightness
+    /// applies to every zone.
+    pub fn with_brightness(&self, brightness: LedBrightness) -> Self {
+        Self {
+            colour1: self.colour1.scaled(brightness),
+            colour2: self.colour2.scaled(brightness),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the effect with the `(r, g, b)` gamma correction applied to
+    /// its colours
+    pub fn with_gamma(&self, gamma: (f32, f32, f32)) -> Self {
+        Self {
+            colour1: self.colour1.gamma_corrected(gamma),
+            colour2: self.colour2.gamma_corrected(gamma),
+            ..self.clone()
+        }
+    }
+
     /// A helper to provide detail on what effects have which parameters, e.g
     /// the static factory mode accepts only one colour.
     pub const fn allowed_parameters(mode: AuraModeNum) -> AuraParameters {
@@ -452,7 +551,67 @@ impl From<&AuraEffect> for Vec<u8> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Speed, LED_MSG_LEN};
+    use crate::{
+        gamma_correct, AuraEffect, AuraModeNum, AuraZone, Colour, Direction, LedBrightness, Speed,
+        GAMMA_NONE, LED_MSG_LEN,
+    };
+
+    #[test]
+    fn effect_with_brightness() {
+        let effect = AuraEffect {
+            mode: AuraModeNum::Breathe,
+            zone: AuraZone::BarLeft,
+            colour1: Colour {
+                r: 0xff,
+                g: 0x90,
+                b: 0,
+            },
+            colour2: Colour {
+                r: 0x30,
+                g: 0,
+                b: 0xff,
+            },
+            ..Default::default()
+        };
+        let high = effect.with_brightness(LedBrightness::High);
+        assert_eq!(high.colour1, effect.colour1);
+        assert_eq!(high.colour2, effect.colour2);
+
+        let low = effect.with_brightness(LedBrightness::Low);
+        assert_eq!(low.colour1, Colour { r: 85, g: 48, b: 0 });
+        assert_eq!(low.colour2, Colour { r: 16, g: 0, b: 85 });
+        assert_eq!(low.mode, AuraModeNum::Breathe);
+        assert_eq!(low.zone, AuraZone::BarLeft);
+
+        let off = effect.with_brightness(LedBrightness::Off);
+        assert_eq!(off.colour1, Colour { r: 0, g: 0, b: 0 });
+        assert_eq!(off.colour2, Colour { r: 0, g: 0, b: 0 });
+    }
+
+    #[test]
+    fn brightness_percent() {
+        for (pct, level) in [
+            (0, LedBrightness::Off),
+            (16, LedBrightness::Off),
+            (17, LedBrightness::Low),
+            (49, LedBrightness::Low),
+            (50, LedBrightness::Med),
+            (83, LedBrightness::Med),
+            (84, LedBrightness::High),
+            (100, LedBrightness::High),
+            (255, LedBrightness::High),
+        ] {
+            assert_eq!(LedBrightness::from_percent(pct), level, "{pct}%");
+        }
+        for level in [
+            LedBrightness::Off,
+            LedBrightness::Low,
+            LedBrightness::Med,
+            LedBrightness::High,
+        ] {
+            assert_eq!(LedBrightness::from_percent(level.to_percent()), level);
+        }
+    }
 
     #[test]
     fn check_led_static_packet() {
@@ -577,4 +736,50 @@ mod tests {
         ];
         assert_eq!(<[u8; LED_MSG_LEN]>::from(&st)[..9], capture[..9]);
     }
+
+    #[test]
+    fn colour_from_hex() {
+        let colour = Colour {
+            r: 0xff,
+            g: 0x80,
+            b: 0x0a,
+        };
+        assert_eq!(Colour::from_hex("#ff800a").unwrap(), colour);
+        assert_eq!(Colour::from_hex("FF800A").unwrap(), colour);
+        assert_eq!(Colour::from_hex(" #ff800a\n").unwrap(), colour);
+
+        for bad in ["", "#", "ff800", "#ff800a0", "ff80zz", "##ff800a", "ff800é"] {
+            assert!(Colour::from_hex(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn colour_gamma() {
+        for gamma in [0.5, 1.0, 2.2] {
+            assert_eq!(gamma_correct(0, gamma), 0);
+            assert_eq!(gamma_correct(255, gamma), 255);
+        }
+        for value in 0..=255 {
+            assert_eq!(gamma_correct(value, 1.0), value);
+        }
+        // 128/255 = 0.502, squared is 0.252 or 64.25
+        assert_eq!(gamma_correct(128, 2.0), 64);
+        // sqrt(0.502) is 0.709 or 180.7
+        assert_eq!(gamma_correct(128, 0.5), 181);
+
+        let colour = Colour {
+            r: 128,
+            g: 128,
+            b: 128,
+        };
+        assert_eq!(colour.gamma_corrected(GAMMA_NONE), colour);
+        assert_eq!(
+            colour.gamma_corrected((2.0, 1.0, 0.5)),
+            Colour {
+                r: 64,
+                g: 128,
+                b: 181,
+            }
+        );
+    }
 }
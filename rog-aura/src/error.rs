@@ -6,9 +6,11 @@ pub enum Error {
     ParseSpeed,
     ParseDirection,
     ParseBrightness,
+    ParseKeyboardTarget,
     IoPath(String, std::io::Error),
     Ron(ron::Error),
     RonParse(ron::error::SpannedError),
+    Json(serde_json::Error),
 }
 
 impl fmt::Display for Error {
@@ -19,9 +21,11 @@ impl fmt::Display for Error {
             Error::ParseSpeed => write!(f, "Could not parse speed"),
             Error::ParseDirection => write!(f, "Could not parse direction"),
             Error::ParseBrightness => write!(f, "Could not parse brightness"),
+            Error::ParseKeyboardTarget => write!(f, "Could not parse keyboard target"),
             Error::IoPath(path, io) => write!(f, "IO Error: {path}, {io}"),
             Error::Ron(e) => write!(f, "RON Parse Error: {e}"),
             Error::RonParse(e) => write!(f, "RON Parse Error: {e}"),
+            Error::Json(e) => write!(f, "JSON Parse Error: {e}"),
         }
     }
 }
@@ -39,3 +43,9 @@ impl From<ron::error::SpannedError> for Error {
         Self::RonParse(e)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
@@ -277,6 +277,24 @@ impl KeyLayout {
         width
     }
 
+    /// The keys of the row holding `F1` and of any rows above it, such as the
+    /// media keys. Empty if the layout has no function row.
+    pub fn function_row(&self) -> Vec<LedCode> {
+        let mut keys = Vec::new();
+        for r in &self.key_rows {
+            keys.extend(
+                r.row
+                    .iter()
+                    .map(|(key, _)| *key)
+                    .filter(|key| !key.is_placeholder() && !key.is_lightbar_zone()),
+            );
+            if r.row.iter().any(|(key, _)| *key == LedCode::F1) {
+                return keys;
+            }
+        }
+        Vec::new()
+    }
+
     /// Find a layout matching the name in `LaptopLedData` in the provided dir
     pub fn find_layout(led_data: LaptopLedData, mut data_path: PathBuf) -> Result<Self, Error> {
         // TODO: locales
@@ -458,8 +476,39 @@ mod tests {
     use std::io::Read;
     use std::path::PathBuf;
 
+    use crate::advanced::LedCode;
     use crate::aura_detection::LedSupportFile;
-    use crate::layouts::KeyLayout;
+    use crate::layouts::{KeyLayout, KeyRow};
+
+    #[test]
+    fn function_row_from_layout() {
+        let mut layout = KeyLayout::default_layout();
+        let row = layout.function_row();
+        assert_eq!(row.len(), 13);
+        assert_eq!(row[0], LedCode::Esc);
+        assert_eq!(row[12], LedCode::F12);
+
+        layout.key_rows.insert(
+            0,
+            KeyRow::new(
+                0.1,
+                0.1,
+                vec![
+                    (LedCode::LightbarLeft, "regular".to_owned()),
+                    (LedCode::Blocking, "regular".to_owned()),
+                    (LedCode::VolUp, "regular".to_owned()),
+                ],
+            ),
+        );
+        let row = layout.function_row();
+        assert_eq!(row.len(), 14);
+        assert_eq!(row[0], LedCode::VolUp);
+
+        layout
+            .key_rows
+            .retain(|r| !r.row.iter().any(|k| k.0 == LedCode::F1));
+        assert!(layout.function_row().is_empty());
+    }
 
     #[test]
     fn check_parse_all() {
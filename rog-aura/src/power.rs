@@ -101,11 +101,21 @@ pub struct AuraPower {
     pub keyboard: KbAuraPowerState,
     pub logo: KbAuraPowerState,
     pub lightbar: KbAuraPowerState,
+    #[serde(default = "AuraPower::default_lid")]
     pub lid: KbAuraPowerState,
+    #[serde(default = "AuraPower::default_rear_glow")]
     pub rear_glow: KbAuraPowerState,
 }
 
 impl AuraPower {
+    fn default_lid() -> KbAuraPowerState {
+        Self::new_all_on().lid
+    }
+
+    fn default_rear_glow() -> KbAuraPowerState {
+        Self::new_all_on().rear_glow
+    }
+
     pub fn new_all_on() -> Self {
         Self {
             keyboard: KbAuraPowerState {
@@ -342,3 +352,32 @@ fn check_0x19b6_control_bytes_binary_rep() {
     let out = to_binary_string(&byte1);
     assert_eq!(out, "11111111, 00011110, 00001111, 00001111");
 }
+
+#[cfg(feature = "dbus")]
+#[test]
+fn power_dbus_round_trip() {
+    let power = AuraPower {
+        keyboard: KbAuraPowerState {
+            zone: PowerZones::Keyboard,
+            boot: true,
+            awake: true,
+            sleep: false,
+            shutdown: true,
+        },
+        ..AuraPower::new_all_on()
+    };
+    let msg = zbus::Message::method(
+        None::<&str>,
+        None::<&str>,
+        "/org/asuslinux/Aura",
+        None::<&str>,
+        "SetLedPower",
+        &(crate::usb::AuraPowerDev {
+            rog: power.clone(),
+            ..Default::default()
+        },),
+    )
+    .unwrap();
+    let (dev,): (crate::usb::AuraPowerDev,) = msg.body().unwrap();
+    assert_eq!(dev.rog, power);
+}
@@ -1,11 +1,13 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::ops::{BitAnd, BitOr};
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 #[cfg(feature = "dbus")]
 use zbus::zvariant::Type;
 
+use crate::error::Error;
 use crate::power::AuraPower;
 
 pub const LED_INIT1: [u8; 2] = [0x5d, 0xb9];
@@ -114,6 +116,63 @@ impl Debug for AuraDevice {
     }
 }
 
+/// Which keyboard an LED method addresses when more than one is connected, such
+/// as the laptop keyboard and an external ROG keyboard. Sent over dbus as
+/// `"primary"`, `"all"` or a product ID such as `"1866"`.
+#[cfg_attr(feature = "dbus", derive(Type), zvariant(signature = "s"))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KeyboardTarget {
+    /// The laptop keyboard
+    #[default]
+    Primary,
+    Product(AuraDevice),
+    All,
+}
+
+impl FromStr for KeyboardTarget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "" | "primary" => Ok(Self::Primary),
+            "all" => Ok(Self::All),
+            s => match AuraDevice::from(s) {
+                AuraDevice::Unknown => Err(Error::ParseKeyboardTarget),
+                prod => Ok(Self::Product(prod)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for KeyboardTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Primary => write!(f, "primary"),
+            Self::Product(prod) => write!(f, "{}", <&str>::from(*prod)),
+            Self::All => write!(f, "all"),
+        }
+    }
+}
+
+impl Serialize for KeyboardTarget {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyboardTarget {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 /// This struct is intended as a helper to pass args to generic dbus interface
 #[typeshare]
 #[cfg_attr(feature = "dbus", derive(Type))]
@@ -215,7 +274,34 @@ impl BitAnd<AuraDevRog1> for AuraDevRog1 {
 
 #[cfg(test)]
 mod tests {
-    use super::AuraDevRog1;
+    use std::str::FromStr;
+
+    use super::{AuraDevRog1, AuraDevice, KeyboardTarget};
+
+    #[test]
+    fn keyboard_target_strings() {
+        for (s, target) in [
+            ("primary", KeyboardTarget::Primary),
+            ("all", KeyboardTarget::All),
+            ("1866", KeyboardTarget::Product(AuraDevice::X1866)),
+            ("19b6", KeyboardTarget::Product(AuraDevice::X19b6)),
+        ] {
+            assert_eq!(KeyboardTarget::from_str(s).unwrap(), target);
+            assert_eq!(target.to_string(), s);
+            let json = serde_json::to_string(&target).unwrap();
+            assert_eq!(json, format!("\"{s}\""));
+            assert_eq!(
+                serde_json::from_str::<KeyboardTarget>(&json).unwrap(),
+                target
+            );
+        }
+        assert_eq!(
+            KeyboardTarget::from_str("0x1866").unwrap(),
+            KeyboardTarget::Product(AuraDevice::X1866)
+        );
+        assert!(KeyboardTarget::from_str("keyboard").is_err());
+        assert!(serde_json::from_str::<KeyboardTarget>("\"unknown\"").is_err());
+    }
 
     #[test]
     fn check_0x1866_control_bytes() {
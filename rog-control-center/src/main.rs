@@ -96,6 +96,25 @@ fn main() -> Result<()> {
         }
     };
 
+    // A daemon without `api_version` predates it and is too old
+    let compatible = dbus
+        .proxies()
+        .supported()
+        .api_version()
+        .map_err(|e| format!("asusd is too old, it does not report a dbus API version: {e}"))
+        .and_then(rog_dbus::check_api_version);
+    if let Err(e) = compatible {
+        error!("{e}");
+        eframe::run_native(
+            "ROG Control Center",
+            native_options.clone(),
+            Box::new(move |_| Box::new(AppErrorShow::new(e))),
+        )
+        .map_err(|e| error!("{e}"))
+        .ok();
+        return Ok(());
+    }
+
     // Startup
     let mut config = Config::load()?;
     let running_in_bg = Arc::new(AtomicBool::new(config.startup_in_background));
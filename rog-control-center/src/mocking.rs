@@ -85,6 +85,10 @@ impl Bios {
         Ok(GpuMode::Optimus)
     }
 
+    pub fn gpu_mux_mode_pending(&self) -> Result<bool> {
+        Ok(false)
+    }
+
     pub fn panel_od(&self) -> Result<bool> {
         Ok(true)
     }
@@ -116,6 +120,10 @@ impl Profile {
         Ok(rog_profiles::Profile::Performance)
     }
 
+    pub fn active_profile_hardware(&self) -> Result<rog_profiles::Profile> {
+        Ok(rog_profiles::Profile::Performance)
+    }
+
     pub fn enabled_fan_profiles(&self) -> Result<Vec<rog_profiles::Profile>> {
         Ok(vec![
             rog_profiles::Profile::Performance,
@@ -187,6 +195,10 @@ impl Led {
         Ok(1)
     }
 
+    pub fn last_effect_change(&self) -> Result<(u64, String)> {
+        Ok((0, "none".to_owned()))
+    }
+
     pub fn led_powered(&self) -> Result<AuraPowerDev> {
         Ok(AuraPowerDev {
             tuf: vec![],
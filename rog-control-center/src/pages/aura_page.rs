@@ -1,5 +1,5 @@
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use egui::Color32;
 use rog_aura::{AuraEffect, AuraModeNum};
@@ -75,6 +75,15 @@ impl RogApp {
                 &mut states.aura,
                 colour,
             );
+
+            let (time, source) = &states.aura.last_change;
+            if *time > 0 {
+                let ago = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|now| now.as_secs().saturating_sub(*time))
+                    .unwrap_or_default();
+                ui.label(format!("Lighting last changed by {source}, {ago}s ago"));
+            }
         });
 
         // Only do repaint request if on this page
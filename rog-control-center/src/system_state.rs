@@ -45,7 +45,11 @@ impl BiosState {
                 false
             },
             dedicated_gfx: if supported.rog_bios_ctrl.gpu_mux {
-                GpuMode::from(dbus.proxies().rog_bios().gpu_mux_mode()?)
+                dbus.proxies()
+                    .rog_bios()
+                    .gpu_mux_mode()
+                    .map(GpuMode::from_mux)
+                    .unwrap_or(GpuMode::Error)
             } else {
                 GpuMode::NotSupported
             },
@@ -70,10 +74,17 @@ impl BiosState {
 pub struct ProfilesState {
     pub list: Vec<Profile>,
     pub current: Profile,
+    /// The profile the firmware is enforcing, may differ from `current`
+    pub hardware: Profile,
 }
 
 impl ProfilesState {
     pub fn new(supported: &SupportedFunctions, dbus: &RogDbusClientBlocking<'_>) -> Result<Self> {
+        let current = if supported.platform_profile.platform_profile {
+            dbus.proxies().profile().active_profile()?
+        } else {
+            Profile::Balanced
+        };
         Ok(Self {
             list: if supported.platform_profile.platform_profile {
                 let mut list = dbus.proxies().profile().profiles()?;
@@ -82,8 +93,13 @@ impl ProfilesState {
             } else {
                 vec![]
             },
-            current: if supported.platform_profile.platform_profile {
-                dbus.proxies().profile().active_profile()?
+            current,
+            // Only informational, so an older daemon without it isn't an error
+            hardware: if supported.platform_profile.platform_profile {
+                dbus.proxies()
+                    .profile()
+                    .active_profile_hardware()
+                    .unwrap_or(current)
             } else {
                 Profile::Balanced
             },
@@ -147,6 +163,8 @@ pub struct AuraState {
     pub enabled: AuraPowerDev,
     /// Brightness from 0-3
     pub bright: i16,
+    /// Seconds since the UNIX epoch and source of the last lighting change
+    pub last_change: (u64, String),
     pub wave_red: [u8; 22],
     pub wave_green: [u8; 22],
     pub wave_blue: [u8; 22],
@@ -168,6 +186,11 @@ impl AuraState {
             },
             enabled: dbus.proxies().led().led_power().unwrap_or_default(),
             bright: dbus.proxies().led().led_brightness().unwrap_or_default(),
+            last_change: dbus
+                .proxies()
+                .led()
+                .last_effect_change()
+                .unwrap_or_default(),
             wave_red: [0u8; 22],
             wave_green: [0u8; 22],
             wave_blue: [0u8; 22],
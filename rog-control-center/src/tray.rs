@@ -325,7 +325,7 @@ impl ROGTray {
 
         let mut reboot_required = false;
         if let Ok(mode) = gfx_dbus.gpu_mux_mode() {
-            let mode = match GpuMode::from(mode) {
+            let mode = match GpuMode::from_mux(mode) {
                 GpuMode::Discrete => GfxMode::AsusMuxDgpu,
                 _ => GfxMode::Hybrid,
             };
@@ -405,7 +405,7 @@ impl ROGTray {
         if self.gfx_proxy_is_active {
             // Add a supergfxctl specific menu
             self.menu_add_supergfx(supported_gfx, current_gfx_mode);
-        } else if supported.rog_bios_ctrl.gpu_mux {
+        } else if supported.rog_bios_ctrl.gpu_mux_switchable {
             self.menu_add_mux(current_gfx_mode);
         }
         self.menu_update();
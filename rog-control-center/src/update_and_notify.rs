@@ -294,6 +294,35 @@ pub fn start_notifications(
     );
     // notify!(do_thermal_notif(&out.profile), lock);
 
+    let page_states1 = page_states.clone();
+    tokio::spawn(async move {
+        let conn = zbus::Connection::system()
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_profile: {e}");
+                e
+            })
+            .unwrap();
+        let proxy = ProfileProxy::new(&conn)
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_profile: {e}");
+                e
+            })
+            .unwrap();
+        if let Ok(mut p) = proxy.receive_notify_profile().await {
+            while p.next().await.is_some() {
+                // The signal carries the requested profile, fetch what is enforced
+                if let Ok(profile) = proxy.active_profile_hardware().await {
+                    if let Ok(mut lock) = page_states1.lock() {
+                        lock.profiles.hardware = profile;
+                        lock.set_notified();
+                    }
+                }
+            }
+        };
+    });
+
     // LED notif
     recv_notif!(
         LedProxy,
@@ -336,6 +365,65 @@ pub fn start_notifications(
         };
     });
 
+    let page_states1 = page_states.clone();
+    tokio::spawn(async move {
+        let conn = zbus::Connection::system()
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_brightness: {e}");
+                e
+            })
+            .unwrap();
+        let proxy = LedProxy::new(&conn)
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_brightness: {e}");
+                e
+            })
+            .unwrap();
+        if let Ok(mut p) = proxy.receive_notify_brightness().await {
+            info!("Started zbus signal thread: receive_notify_brightness");
+            while let Some(e) = p.next().await {
+                if let Ok(out) = e.args() {
+                    let last_change = proxy.last_effect_change().await.unwrap_or_default();
+                    if let Ok(mut lock) = page_states1.lock() {
+                        lock.aura.bright = out.brightness as i16;
+                        lock.aura.last_change = last_change;
+                        lock.set_notified();
+                    }
+                }
+            }
+        };
+    });
+
+    let page_states1 = page_states.clone();
+    tokio::spawn(async move {
+        let conn = zbus::Connection::system()
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_led: {e}");
+                e
+            })
+            .unwrap();
+        let proxy = LedProxy::new(&conn)
+            .await
+            .map_err(|e| {
+                error!("zbus signal: receive_notify_led: {e}");
+                e
+            })
+            .unwrap();
+        if let Ok(mut p) = proxy.receive_notify_led().await {
+            info!("Started zbus signal thread: receive_notify_led last_effect_change");
+            while p.next().await.is_some() {
+                let last_change = proxy.last_effect_change().await.unwrap_or_default();
+                if let Ok(mut lock) = page_states1.lock() {
+                    lock.aura.last_change = last_change;
+                    lock.set_notified();
+                }
+            }
+        };
+    });
+
     let page_states1 = page_states.clone();
     tokio::spawn(async move {
         let conn = zbus::Connection::system()
@@ -383,13 +471,13 @@ pub fn start_notifications(
 
         let mut actual_mux_mode = GpuMode::Error;
         if let Ok(mode) = proxy.gpu_mux_mode().await {
-            actual_mux_mode = GpuMode::from(mode);
+            actual_mux_mode = GpuMode::from_mux(mode);
         }
 
         info!("Started zbus signal thread: receive_notify_gpu_mux_mode");
         while let Some(e) = proxy.receive_gpu_mux_mode_changed().await.next().await {
             if let Ok(out) = e.get().await {
-                let mode = GpuMode::from(out);
+                let mode = GpuMode::from_mux(out);
                 if mode == actual_mux_mode {
                     continue;
                 }
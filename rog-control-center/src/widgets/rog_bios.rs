@@ -6,7 +6,14 @@ use rog_profiles::Profile;
 use crate::system_state::SystemState;
 
 pub fn platform_profile(states: &mut SystemState, ui: &mut Ui) {
-    ui.heading("Platform profile");
+    if states.profiles.current == states.profiles.hardware {
+        ui.heading("Platform profile");
+    } else {
+        ui.heading(format!(
+            "Platform profile (requested {:?}, active {:?})",
+            states.profiles.current, states.profiles.hardware
+        ));
+    }
 
     let mut changed = false;
     let mut item = |p: Profile, ui: &mut Ui| {
@@ -34,6 +41,14 @@ pub fn platform_profile(states: &mut SystemState, ui: &mut Ui) {
                 states.error = Some(err.to_string());
             })
             .ok();
+        if let Ok(profile) = states
+            .asus_dbus
+            .proxies()
+            .profile()
+            .active_profile_hardware()
+        {
+            states.profiles.hardware = profile;
+        }
     };
 }
 
@@ -112,18 +127,25 @@ pub fn rog_bios_group(supported: &SupportedFunctions, states: &mut SystemState,
             .ok();
     }
 
-    if supported.rog_bios_ctrl.gpu_mux {
+    // Hide on MUX-less laptops where the value is read-only
+    if supported.rog_bios_ctrl.gpu_mux_switchable {
         let mut changed = false;
         let mut dedicated_gfx = states.bios.dedicated_gfx;
 
-        let mut reboot_required = false;
-        if let Ok(mode) = states.asus_dbus.proxies().rog_bios().gpu_mux_mode() {
-            reboot_required = GpuMode::from(mode) != states.bios.dedicated_gfx;
-        }
+        let reboot_required = states
+            .asus_dbus
+            .proxies()
+            .rog_bios()
+            .gpu_mux_mode_pending()
+            .unwrap_or_default();
 
         ui.group(|ui| {
             ui.vertical(|ui| {
                 ui.horizontal_wrapped(|ui| ui.label("GPU MUX mode"));
+                if matches!(dedicated_gfx, GpuMode::Error | GpuMode::NotSupported) {
+                    ui.horizontal_wrapped(|ui| ui.label("MUX unavailable"));
+                    return;
+                }
                 ui.horizontal_wrapped(|ui| ui.label("NOTE: Value does not change until rebooted"));
                 ui.horizontal_wrapped(|ui| {
                     changed = ui
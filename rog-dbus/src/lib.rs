@@ -17,6 +17,29 @@ use zbus::{blocking, Connection, Result};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The dbus API version as `(major, minor)`. Bump the major when a method is
+/// removed or changes signature, and the minor when methods are added.
+pub const DBUS_API_VERSION: (u32, u32) = (1, 0);
+
+/// Check the API version reported by the daemon against `DBUS_API_VERSION`.
+/// The error says whether the daemon or the client needs updating.
+pub fn check_api_version(daemon: (u32, u32)) -> std::result::Result<(), String> {
+    let (major, minor) = DBUS_API_VERSION;
+    if daemon.0 < major || (daemon.0 == major && daemon.1 < minor) {
+        return Err(format!(
+            "asusd is too old: it provides dbus API {}.{}, this client requires {major}.{minor}",
+            daemon.0, daemon.1
+        ));
+    }
+    if daemon.0 > major {
+        return Err(format!(
+            "asusd is too new: it provides dbus API {}.{}, this client supports {major}.x",
+            daemon.0, daemon.1
+        ));
+    }
+    Ok(())
+}
+
 pub struct DbusProxiesBlocking<'a> {
     anime: zbus_anime::AnimeProxyBlocking<'a>,
     charge: zbus_power::PowerProxyBlocking<'a>,
@@ -154,3 +177,27 @@ impl<'a> RogDbusClient<'a> {
         &self.proxies
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_api_version, DBUS_API_VERSION};
+
+    #[test]
+    fn api_version_compatibility() {
+        let (major, minor) = DBUS_API_VERSION;
+        assert!(check_api_version((major, minor)).is_ok());
+        // Additions the client doesn't know about are fine
+        assert!(check_api_version((major, minor + 1)).is_ok());
+        assert!(check_api_version((major + 1, 0))
+            .unwrap_err()
+            .contains("too new"));
+        if minor > 0 {
+            assert!(check_api_version((major, minor - 1))
+                .unwrap_err()
+                .contains("too old"));
+        }
+        assert!(check_api_version((major - 1, minor + 5))
+            .unwrap_err()
+            .contains("too old"));
+    }
+}
@@ -11,6 +11,9 @@ trait Anime {
     /// Set the global base brightness
     fn set_brightness(&self, bright: Brightness) -> zbus::Result<()>;
 
+    /// Set the global base brightness as a number from 0 (off) to 3 (high)
+    fn set_brightness_level(&self, level: u8) -> zbus::Result<()>;
+
     /// Set whether the AniMe will show boot, suspend, or off animations
     fn set_builtins_enabled(&self, enabled: bool) -> zbus::Result<()>;
 
@@ -35,6 +38,32 @@ trait Anime {
     /// SetOffWhenUnplugged method
     fn set_off_when_unplugged(&self, enabled: bool) -> zbus::Result<()>;
 
+    /// Set if the display brightness is scaled with the keyboard brightness
+    fn set_follows_kbd_brightness(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// Get if the display brightness is scaled with the keyboard brightness
+    fn follows_kbd_brightness(&self) -> zbus::Result<bool>;
+
+    /// Rotate everything written to the display by 180 degrees
+    fn set_flip(&self, flip: bool) -> zbus::Result<()>;
+
+    /// Get if the display is rotated by 180 degrees
+    fn flip(&self) -> zbus::Result<bool>;
+
+    /// Write a frame to the display straight away. Returns `false` if the
+    /// frame was dropped because frames are being sent too fast
+    fn write_frame(&self, data: &[u8]) -> zbus::Result<bool>;
+
+    /// Write a boot animation to the display one frame at a time in the
+    /// background, cancelling any upload still running
+    fn upload_boot_animation(&self, frames: &[Vec<u8>]) -> zbus::Result<()>;
+
+    /// The length of a frame for `write_frame`
+    fn frame_length(&self) -> zbus::Result<u32>;
+
+    /// The width and height of the display as used for diagonal images
+    fn matrix_dimensions(&self) -> zbus::Result<(u32, u32)>;
+
     /// Writes a data stream of length. Will force system thread to exit until
     /// it is restarted
     fn write(&self, input: AnimeDataBuffer) -> zbus::Result<()>;
@@ -45,4 +74,8 @@ trait Anime {
     /// NotifyDeviceState signal
     #[dbus_proxy(signal)]
     fn notify_device_state(&self, data: AnimeDeviceState) -> zbus::Result<()>;
+
+    /// Sent after each frame of `upload_boot_animation` is written
+    #[dbus_proxy(signal)]
+    fn notify_upload_progress(&self, written: u32, total: u32) -> zbus::Result<()>;
 }
@@ -22,9 +22,10 @@
 
 use std::collections::BTreeMap;
 
-use rog_aura::advanced::UsbPackets;
-use rog_aura::usb::AuraPowerDev;
-use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+use rog_aura::advanced::{KeyMap, UsbPackets};
+use rog_aura::aura_detection::KeyboardInfo;
+use rog_aura::usb::{AuraDevice, AuraPowerDev, KeyboardTarget};
+use rog_aura::{AuraEffect, AuraModeNum, AuraParameters, AuraZone, Colour, LedBrightness, Speed};
 use zbus::blocking::Connection;
 use zbus::{dbus_proxy, Result};
 
@@ -44,15 +45,79 @@ trait Led {
     /// Toggle to next led brightness
     fn next_led_brightness(&self) -> zbus::Result<()>;
 
+    /// Turn the backlight off, or back on at the level it was at before being
+    /// turned off. Returns the new brightness.
+    fn toggle_backlight(&self) -> zbus::Result<LedBrightness>;
+
     /// Toggle to previous led brightness
     fn prev_led_brightness(&self) -> zbus::Result<()>;
 
     /// SetBrightness method
     fn set_brightness(&self, brightness: LedBrightness) -> zbus::Result<()>;
 
+    /// Set the brightness as a percentage, rounded to the nearest level
+    fn set_brightness_percent(&self, pct: u8) -> zbus::Result<()>;
+
+    /// The brightness as a percentage: 0, 33, 67, or 100
+    fn brightness_percent(&self) -> zbus::Result<u8>;
+
+    /// Check an effect can be applied without applying it. The error has the
+    /// reason if it can not.
+    fn validate_effect(&self, effect: &AuraEffect) -> zbus::Result<()>;
+
+    /// Dump the full LED config as JSON for backup
+    fn export_config(&self) -> zbus::Result<String>;
+
+    /// Restore a LED config from `export_config`. Rejected if the current mode
+    /// is not supported by this keyboard.
+    fn import_config(&self, json: &str) -> zbus::Result<()>;
+
+    /// Read the config file again and apply it, the same as at startup
+    fn reload(&self) -> zbus::Result<()>;
+
     /// SetLedMode method
     fn set_led_mode(&self, effect: &AuraEffect) -> zbus::Result<()>;
 
+    /// Set a single colour breathe that is in phase across all zones
+    fn set_synced_breathe(&self, colour: &Colour, speed: &Speed) -> zbus::Result<()>;
+
+    /// Set a static colour from a `#RRGGBB` or `RRGGBB` hex string
+    fn set_static_hex(&self, hex: &str) -> zbus::Result<()>;
+
+    /// Change only the speed of the current mode, keeping its colours
+    fn set_speed(&self, speed: &Speed) -> zbus::Result<()>;
+
+    /// The product ID of every keyboard, the primary laptop keyboard first
+    fn keyboards(&self) -> zbus::Result<Vec<AuraDevice>>;
+
+    /// Set the mode on "primary", "all" or a keyboard by product ID
+    fn set_led_mode_target(&self, target: KeyboardTarget, effect: &AuraEffect) -> zbus::Result<()>;
+
+    /// Set the brightness on "primary", "all" or a keyboard by product ID
+    fn set_brightness_target(
+        &self,
+        target: KeyboardTarget,
+        brightness: LedBrightness,
+    ) -> zbus::Result<()>;
+
+    /// Set a random effect from the supported modes and return it
+    fn random_effect(&self) -> zbus::Result<AuraEffect>;
+
+    /// Set the brightness of a single zone, such as the lightbar
+    fn set_zone_brightness(&self, zone: AuraZone, level: LedBrightness) -> zbus::Result<()>;
+
+    /// Set the per-channel gamma correction, `1.0` is no change
+    fn set_colour_gamma(&self, r: f32, g: f32, b: f32) -> zbus::Result<()>;
+
+    /// The per-channel `(r, g, b)` gamma correction
+    fn colour_gamma(&self) -> zbus::Result<(f32, f32, f32)>;
+
+    /// Set the primary colour of every builtin mode that uses one
+    fn set_global_colour(&self, colour: Colour) -> zbus::Result<()>;
+
+    /// Set the mode by its human readable name, e.g "static" or "rainbow"
+    fn set_led_mode_by_name(&self, name: &str) -> zbus::Result<()>;
+
     fn set_led_power(&self, options: AuraPowerDev, enabled: bool) -> zbus::Result<()>;
 
     /// On machine that have some form of either per-key keyboard or per-zone
@@ -60,23 +125,104 @@ trait Led {
     /// nested `Vec<Vec<8>>` where `Vec<u8>` is a raw USB packet
     fn direct_addressing_raw(&self, data: UsbPackets) -> zbus::fdo::Result<()>;
 
+    /// Blink a single zone for a number of seconds so it can be identified,
+    /// then restore its prior effect
+    fn identify_zone(&self, zone: AuraZone, seconds: u8) -> zbus::Result<()>;
+
+    /// Flash the keyboard a colour for a number of milliseconds, then restore
+    /// the current mode
+    fn flash(&self, colour: Colour, millis: u64) -> zbus::Result<()>;
+
+    /// Start the software effect which colours the keyboard by temperature
+    fn start_thermal_effect(&self) -> zbus::Result<()>;
+
+    /// Stop the thermal effect and restore the previous effect
+    fn stop_thermal_effect(&self) -> zbus::Result<()>;
+
+    /// Step through every supported mode, showing each for
+    /// `seconds_per_mode`, then restore the previous effect
+    fn demo_cycle(&self, seconds_per_mode: u8) -> zbus::Result<()>;
+
+    /// Stop the demo and restore the previous effect
+    fn stop_demo(&self) -> zbus::Result<()>;
+
+    /// List every connected ASUS USB LED device as `(product_id, devnode)`
+    fn list_aura_devices(&self) -> zbus::Result<Vec<(String, String)>>;
+
+    /// Set the colour of a single key on per-key keyboards by its name
+    fn set_key_colour(&self, key: &str, colour: Colour) -> zbus::Result<()>;
+
+    /// Set the colour of only the function row and media keys on per-key
+    /// keyboards
+    fn set_fn_row_colour(&self, colour: Colour) -> zbus::Result<()>;
+
+    /// Set the colour of each key on per-key keyboards
+    fn set_keymap(&self, map: KeyMap) -> zbus::Result<()>;
+
+    /// Reset the keyboard to solid white at full brightness with all LED zones
+    /// enabled, ignoring the stored config. Only stored if `save` is true.
+    fn safe_state(&self, save: bool) -> zbus::Result<()>;
+
+    /// Reset the LED config to the defaults for this keyboard and apply it
+    fn reset_aura_defaults(&self) -> zbus::Result<()>;
+
+    /// When the keyboard lighting was last changed, as seconds since the UNIX
+    /// epoch, and what triggered it
+    fn last_effect_change(&self) -> zbus::Result<(u64, String)>;
+
+    /// Return if the keyboard brightness and mode follow the platform profile
+    fn led_follows_profile(&self) -> zbus::Result<bool>;
+
+    /// Set if the keyboard brightness and mode change with the platform
+    /// profile
+    fn set_led_follows_profile(&self, enabled: bool) -> zbus::Result<()>;
+
     /// NotifyLed signal
     #[dbus_proxy(signal)]
     fn notify_led(&self, data: AuraEffect) -> zbus::Result<()>;
 
+    /// NotifyBrightness signal, emitted when the brightness is changed outside
+    /// of asusd such as by the keyboard backlight key
+    #[dbus_proxy(signal)]
+    fn notify_brightness(&self, brightness: LedBrightness) -> zbus::Result<()>;
+
     #[dbus_proxy(signal)]
     fn notify_power_states(&self, data: AuraPowerDev) -> zbus::Result<()>;
 
+    /// Emitted when the LED config file was edited and reloaded
+    #[dbus_proxy(signal)]
+    fn notify_config_reloaded(&self) -> zbus::Result<()>;
+
+    /// The exact byte read from the brightness node, errors if it can not be
+    /// read
+    fn brightness_raw(&self) -> zbus::Result<u8>;
+
     /// LedBrightness property
     #[dbus_proxy(property)]
     fn led_brightness(&self) -> zbus::Result<i16>;
 
+    /// The keyboard product ID, device type and layout details
+    fn keyboard_info(&self) -> zbus::Result<KeyboardInfo>;
+
     /// LedMode property
     fn led_mode(&self) -> zbus::Result<AuraModeNum>;
 
+    /// Supported modes paired with their human readable name
+    fn mode_names(&self) -> zbus::Result<Vec<(AuraModeNum, String)>>;
+
+    /// Which effect fields each supported mode uses
+    fn mode_capabilities(&self) -> zbus::Result<BTreeMap<AuraModeNum, AuraParameters>>;
+
     /// LedModes property
     fn led_modes(&self) -> zbus::Result<BTreeMap<AuraModeNum, AuraEffect>>;
 
+    /// Remove the per-zone effects for a mode, reverting it to the standard
+    /// builtin
+    fn clear_multizone(&self, mode: AuraModeNum) -> zbus::Result<()>;
+
+    /// The multizone effects currently set
+    fn led_multizone(&self) -> zbus::Result<BTreeMap<AuraModeNum, Vec<AuraEffect>>>;
+
     // As property doesn't work for AuraPowerDev (complexity of serialization?)
     // #[dbus_proxy(property)]
     fn led_power(&self) -> zbus::Result<AuraPowerDev>;
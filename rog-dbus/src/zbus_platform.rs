@@ -20,7 +20,9 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
-use rog_platform::platform::GpuMode;
+use rog_platform::platform::{
+    DgpuPower, GpuMode, GpuStatus, MiniLedLevel, PptLimits, ThermalPolicy,
+};
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -31,6 +33,12 @@ trait RogBios {
     /// SupportedProperties method
     fn supported_properties(&self) -> zbus::Result<Vec<String>>;
 
+    /// ConfigMatchesHardware method
+    fn config_matches_hardware(&self) -> zbus::Result<Vec<(String, bool)>>;
+
+    /// Get all of the PPT and Nvidia power limits applied
+    fn power_limits(&self) -> zbus::Result<PptLimits>;
+
     /// DgpuDisable property
     #[dbus_proxy(property)]
     fn dgpu_disable(&self) -> zbus::Result<bool>;
@@ -39,16 +47,55 @@ trait RogBios {
     #[dbus_proxy(property)]
     fn egpu_enable(&self) -> zbus::Result<bool>;
 
+    /// Enable or disable the eGPU after checking the dGPU state. Errors if the
+    /// change is unsafe.
+    fn set_egpu_safe(&self, enable: bool) -> zbus::Result<()>;
+
     /// GpuMuxMode property
     #[dbus_proxy(property)]
     fn gpu_mux_mode(&self) -> zbus::Result<u8>;
     fn set_gpu_mux_mode(&self, value: GpuMode) -> zbus::Result<()>;
 
+    /// The MUX, dGPU, and eGPU state in one read
+    fn gpu_status(&self) -> zbus::Result<GpuStatus>;
+
+    /// If the dGPU is powered, suspended, or off
+    fn dgpu_power(&self) -> zbus::Result<DgpuPower>;
+
+    /// If a new GPU MUX mode has been set and a reboot is required
+    #[dbus_proxy(property)]
+    fn gpu_mux_mode_pending(&self) -> zbus::Result<bool>;
+
+    /// If the GPU MUX can be switched, MUX-less laptops may not be
+    fn gpu_mux_switchable(&self) -> zbus::Result<bool>;
+
     /// MiniLedMode property
     #[dbus_proxy(property)]
     fn mini_led_mode(&self) -> zbus::Result<bool>;
     fn set_mini_led_mode(&self, value: bool) -> zbus::Result<()>;
 
+    /// The mini-LED levels the panel supports, empty if none
+    fn mini_led_levels(&self) -> zbus::Result<Vec<MiniLedLevel>>;
+
+    /// The current mini-LED level
+    fn mini_led_level(&self) -> zbus::Result<MiniLedLevel>;
+
+    /// Set and store the mini-LED level
+    fn set_mini_led_level(&self, level: MiniLedLevel) -> zbus::Result<()>;
+
+    /// Read `throttle_thermal_policy` directly
+    fn thermal_policy(&self) -> zbus::Result<ThermalPolicy>;
+
+    /// Write `throttle_thermal_policy` directly, independent of the profile
+    fn set_thermal_policy(&self, policy: ThermalPolicy) -> zbus::Result<()>;
+
+    /// Reset the platform settings to their defaults and apply them. The
+    /// charge limit is kept.
+    fn reset_platform_defaults(&self) -> zbus::Result<()>;
+
+    /// Read the config file again and apply it, the same as at startup
+    fn reload(&self) -> zbus::Result<()>;
+
     /// NvDynamicBoost property
     #[dbus_proxy(property)]
     fn nv_dynamic_boost(&self) -> zbus::Result<u8>;
@@ -93,4 +140,12 @@ trait RogBios {
     #[dbus_proxy(property)]
     fn ppt_platform_sppt(&self) -> zbus::Result<u8>;
     fn set_ppt_platform_sppt(&self, value: u8) -> zbus::Result<()>;
+
+    /// Emitted when the GPU MUX, dGPU, or eGPU state changes
+    #[dbus_proxy(signal)]
+    fn notify_gpu_status(&self, status: GpuStatus) -> zbus::Result<()>;
+
+    /// Emitted when `asusd.ron` was edited and reloaded
+    #[dbus_proxy(signal)]
+    fn notify_config_reloaded(&self) -> zbus::Result<()>;
 }
@@ -20,6 +20,7 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
+use rog_platform::power::{BatteryHealth, PowerSource};
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -33,9 +34,24 @@ trait Power {
     /// MainsOnline method
     fn mains_online(&self) -> zbus::Result<bool>;
 
+    /// The charge, cycle count and wear of the battery
+    fn battery_health(&self) -> zbus::Result<BatteryHealth>;
+
+    /// Whether the laptop is on AC or battery
+    fn power_source(&self) -> zbus::Result<PowerSource>;
+
     /// set_charge_control_end_threshold method
     fn set_charge_control_end_threshold(&self, limit: u8) -> zbus::Result<()>;
 
+    /// Charge to 100% once, the charge limit is restored when AC is unplugged
+    fn charge_to_full_once(&self) -> zbus::Result<()>;
+
+    /// Stop `nvidia-powerd` while on battery, applied immediately if on battery
+    fn set_disable_nvidia_powerd_on_battery(&self, disable: bool) -> zbus::Result<()>;
+
+    /// If `nvidia-powerd` is stopped while on battery
+    fn disable_nvidia_powerd_on_battery(&self) -> zbus::Result<bool>;
+
     /// NotifyCharge signal
     #[dbus_proxy(signal)]
     fn notify_charge_control_end_threshold(&self, limit: u8) -> zbus::Result<u8>;
@@ -43,4 +59,8 @@ trait Power {
     /// NotifyMainsOnline signal
     #[dbus_proxy(signal)]
     fn notify_mains_online(&self, on: bool) -> zbus::Result<()>;
+
+    /// Sent when the laptop changes between AC and battery
+    #[dbus_proxy(signal)]
+    fn notify_power_source(&self, source: PowerSource) -> zbus::Result<()>;
 }
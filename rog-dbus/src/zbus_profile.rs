@@ -35,16 +35,37 @@ trait Profile {
     /// Fetch the active profile name
     fn active_profile(&self) -> zbus::Result<Profile>;
 
+    /// Fetch the profile the firmware is currently enforcing
+    fn active_profile_hardware(&self) -> zbus::Result<Profile>;
+
     /// Toggle to next platform_profile. Names provided by `Profiles`.
     /// If fan-curves are supported will also activate a fan curve for profile.
-    fn next_profile(&self) -> zbus::Result<()>;
+    /// Returns the new profile.
+    fn next_profile(&self) -> zbus::Result<Profile>;
 
-    /// Fetch profile names
+    /// Fetch the profiles the platform supports
     fn profiles(&self) -> zbus::Result<Vec<Profile>>;
 
+    /// The display names of `profiles`, in the same order
+    fn profile_names(&self) -> zbus::Result<Vec<String>>;
+
     /// Set this platform_profile name as active
     fn set_active_profile(&self, profile: Profile) -> zbus::Result<()>;
 
+    /// Enable or disable temperature based switching between Performance and
+    /// Balanced
+    fn set_auto_profile(&self, enabled: bool, temp_high: u8, temp_low: u8) -> zbus::Result<()>;
+
+    /// Get the automatic profile switching state as (enabled, temp_high,
+    /// temp_low)
+    fn auto_profile(&self) -> zbus::Result<(bool, u8, u8)>;
+
+    /// Set the profile applied on daemon start, an empty string clears it
+    fn set_boot_profile(&self, profile: &str) -> zbus::Result<()>;
+
+    /// Get the profile applied on daemon start, or an empty string if none
+    fn boot_profile(&self) -> zbus::Result<String>;
+
     /// Set a profile fan curve enabled status. Will also activate a fan curve.
     fn set_fan_curves_enabled(&self, profile: Profile, enabled: bool) -> zbus::Result<()>;
 
@@ -61,6 +82,32 @@ trait Profile {
     /// currently in if profile == None. Will also activate the fan curve.
     fn set_fan_curve(&self, profile: Profile, curve: CurveData) -> zbus::Result<()>;
 
+    /// Write a fan curve to the device for the active profile without saving
+    /// it. Reverted after 30 seconds unless saved with `set_fan_curve`.
+    fn preview_fan_curve(&self, profile: Profile, curve: CurveData) -> zbus::Result<()>;
+
+    /// Export the fan curves of a profile as portable `fan=temp:pwm,...` text
+    fn export_fan_curve(&self, profile: Profile) -> zbus::Result<String>;
+
+    /// Import fan curves made by `export_fan_curve` in to a profile
+    fn import_fan_curve(&self, profile: Profile, data: &str) -> zbus::Result<()>;
+
+    /// Restore the saved fan curves after `preview_fan_curve`
+    fn revert_fan_curve(&self, profile: Profile) -> zbus::Result<()>;
+
+    /// Run the fans at full speed for 10 seconds to clear out dust
+    fn fan_boost_max(&self) -> zbus::Result<()>;
+
+    /// Save the current fan curves of the profile under `name`
+    fn save_named_fan_curve(&self, profile: Profile, name: &str) -> zbus::Result<()>;
+
+    /// Switch the profile to a previously saved named fan curve. Will also
+    /// activate the fan curve.
+    fn set_named_fan_curve(&self, profile: Profile, name: &str) -> zbus::Result<()>;
+
+    /// List the names of the saved fan curves for the profile
+    fn list_fan_curves(&self, profile: Profile) -> zbus::Result<Vec<String>>;
+
     /// Reset the stored (self) and device curve to the defaults of the
     /// platform.
     ///
@@ -78,4 +125,8 @@ trait Profile {
     /// NotifyProfile signal
     #[dbus_proxy(signal)]
     async fn notify_profile(&self, profile: Profile) -> zbus::Result<Profile>;
+
+    /// Emitted when a fan boost has ended and the fan curves restored
+    #[dbus_proxy(signal)]
+    fn notify_fan_boost_ended(&self) -> zbus::Result<()>;
 }
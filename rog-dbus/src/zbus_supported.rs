@@ -20,7 +20,7 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
-use rog_platform::supported::SupportedFunctions;
+use rog_platform::supported::{DeviceNodes, SupportedDetails, SupportedFunctions};
 use zbus::dbus_proxy;
 
 #[dbus_proxy(
@@ -30,4 +30,17 @@ use zbus::dbus_proxy;
 trait Supported {
     /// SupportedFunctions method
     fn supported_functions(&self) -> zbus::Result<SupportedFunctions>;
+
+    /// The full supported functions tree serialised as JSON
+    fn supported_functions_json(&self) -> zbus::Result<String>;
+
+    /// The supported functions with the reason each unsupported one is not
+    /// available
+    fn supported_functions_detailed(&self) -> zbus::Result<SupportedDetails>;
+
+    /// The device nodes the daemon is using, unfound nodes are `not found`
+    fn device_nodes(&self) -> zbus::Result<DeviceNodes>;
+
+    /// The dbus API version of the daemon as `(major, minor)`
+    fn api_version(&self) -> zbus::Result<(u32, u32)>;
 }
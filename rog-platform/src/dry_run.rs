@@ -0,0 +1,159 @@
+//! Simulated hardware for running asusd on machines without ASUS hardware,
+//! such as for development or CI. Devices created with their `simulated()`
+//! constructor log what would be written instead of touching sysfs or
+//! hidraw. Written values are kept in memory and read back by the getters,
+//! and attributes that have not been written return a plausible default.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use log::info;
+
+use crate::error::{PlatformError, Result};
+
+/// How many raw writes are kept per simulated device
+const WRITES_KEPT: usize = 64;
+
+#[derive(Debug, Default)]
+struct State {
+    values: BTreeMap<String, String>,
+    writes: VecDeque<Vec<u8>>,
+}
+
+/// The in-memory state of one simulated device. Clones of a device share its
+/// state, separate devices never do.
+#[derive(Debug, Default, Clone)]
+pub struct Simulated(Arc<Mutex<State>>);
+
+impl PartialEq for Simulated {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Simulated {}
+
+impl PartialOrd for Simulated {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Arc::as_ptr(&self.0).partial_cmp(&Arc::as_ptr(&other.0))
+    }
+}
+
+/// A value to return for attributes that have not been written yet
+fn default_value(attr: &str) -> &'static str {
+    match attr {
+        "charge_control_end_threshold" | "capacity" => "80",
+        "online" | "gpu_mux_mode" => "1",
+        "brightness" => "2",
+        "max_brightness" => "3",
+        "platform_profile" => "balanced",
+        "platform_profile_choices" => "quiet balanced performance",
+        "available_mini_led_mode" => "0 1 2",
+        "power/runtime_status" => "suspended",
+        _ => "0",
+    }
+}
+
+impl Simulated {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Log and store the value that would be written to `attr`
+    pub fn write(&self, attr: &str, value: String) -> Result<()> {
+        info!("dry-run: write {attr} = {value}");
+        self.state().values.insert(attr.to_owned(), value);
+        Ok(())
+    }
+
+    /// Log and keep the bytes that would be written to the raw `device`
+    pub fn write_bytes(&self, device: &str, message: &[u8]) {
+        info!("dry-run: write {device} {message:02x?}");
+        let mut state = self.state();
+        if state.writes.len() == WRITES_KEPT {
+            state.writes.pop_front();
+        }
+        state.writes.push_back(message.to_vec());
+    }
+
+    /// The last raw writes, oldest first
+    pub fn written_bytes(&self) -> Vec<Vec<u8>> {
+        self.state().writes.iter().cloned().collect()
+    }
+
+    pub fn read_string(&self, attr: &str) -> Result<String> {
+        Ok(self
+            .state()
+            .values
+            .get(attr)
+            .cloned()
+            .unwrap_or_else(|| default_value(attr).to_owned()))
+    }
+
+    pub fn read_bool(&self, attr: &str) -> Result<bool> {
+        Ok(self.read_string(attr)?.trim() != "0")
+    }
+
+    pub fn read_u8(&self, attr: &str) -> Result<u8> {
+        self.read_string(attr)?
+            .trim()
+            .parse::<u8>()
+            .map_err(|_| PlatformError::ParseNum)
+    }
+
+    pub fn read_u8_array(&self, attr: &str) -> Result<Vec<u8>> {
+        self.read_string(attr)?
+            .split_whitespace()
+            .map(|n| n.parse::<u8>().map_err(|_| PlatformError::ParseNum))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_values_read_back() {
+        let sim = Simulated::default();
+        assert_eq!(sim.read_u8("test_u8").unwrap(), 0);
+        assert_eq!(sim.read_u8("charge_control_end_threshold").unwrap(), 80);
+
+        sim.write("test_u8", 7.to_string()).unwrap();
+        assert_eq!(sim.read_u8("test_u8").unwrap(), 7);
+
+        sim.write("test_bool", 1.to_string()).unwrap();
+        assert!(sim.read_bool("test_bool").unwrap());
+
+        sim.write("test_array", "1 2 3".to_owned()).unwrap();
+        assert_eq!(sim.read_u8_array("test_array").unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn devices_do_not_share_values() {
+        let first = Simulated::default();
+        let second = Simulated::default();
+        first.write("brightness", 0.to_string()).unwrap();
+        assert_eq!(first.read_u8("brightness").unwrap(), 0);
+        assert_eq!(second.read_u8("brightness").unwrap(), 2);
+
+        // A clone is the same device
+        assert_eq!(first.clone().read_u8("brightness").unwrap(), 0);
+        assert_eq!(first.clone(), first);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn written_bytes_are_kept() {
+        let sim = Simulated::default();
+        assert!(sim.written_bytes().is_empty());
+        for n in 0..=WRITES_KEPT {
+            sim.write_bytes("test", &[n as u8]);
+        }
+        let writes = sim.written_bytes();
+        assert_eq!(writes.len(), WRITES_KEPT);
+        assert_eq!(writes[0], vec![1]);
+        assert_eq!(writes[WRITES_KEPT - 1], vec![WRITES_KEPT as u8]);
+    }
+}
@@ -6,6 +6,8 @@ pub type Result<T> = std::result::Result<T, PlatformError>;
 pub enum PlatformError {
     ParseVendor,
     ParseNum,
+    /// An unknown dGPU runtime power state
+    ParsePower(String),
     Udev(String, std::io::Error),
     USB(rusb::Error),
     Path(String, std::io::Error),
@@ -19,6 +21,8 @@ pub enum PlatformError {
     Io(std::io::Error),
     NoAuraKeyboard,
     NoAuraNode,
+    /// The write succeeded but reading back shows the value was not applied
+    WriteNotApplied(String),
 }
 
 impl fmt::Display for PlatformError {
@@ -27,6 +31,7 @@ impl fmt::Display for PlatformError {
         match self {
             PlatformError::ParseVendor => write!(f, "Parse gfx vendor error"),
             PlatformError::ParseNum => write!(f, "Parse number error"),
+            PlatformError::ParsePower(state) => write!(f, "Unknown dGPU power state: {}", state),
             PlatformError::Udev(deets, error) => write!(f, "udev {}: {}", deets, error),
             PlatformError::USB(error) => write!(f, "usb {}", error),
             PlatformError::Path(path, error) => write!(f, "Path {}: {}", path, error),
@@ -45,6 +50,9 @@ impl fmt::Display for PlatformError {
             PlatformError::IoPath(path, detail) => write!(f, "{} {}", path, detail),
             PlatformError::NoAuraKeyboard => write!(f, "No supported Aura keyboard"),
             PlatformError::NoAuraNode => write!(f, "No Aura keyboard node found"),
+            PlatformError::WriteNotApplied(attr) => {
+                write!(f, "Write to {} was not applied by the firmware", attr)
+            }
         }
     }
 }
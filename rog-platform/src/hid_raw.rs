@@ -1,16 +1,64 @@
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
 
 use log::{info, warn};
 
+use crate::dry_run::Simulated;
 use crate::error::{PlatformError, Result};
 
+/// How many times a write to the hidraw node is attempted before giving up
+pub const HID_WRITE_ATTEMPTS: u32 = 3;
+/// The wait after the first failed write, doubled after each further failure.
+/// With `HID_WRITE_ATTEMPTS` this is at most 10 + 20ms of blocking per write.
+const HID_WRITE_BACKOFF: Duration = Duration::from_millis(10);
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
-pub struct HidRaw(PathBuf);
+pub struct HidRaw {
+    path: PathBuf,
+    /// Log and keep writes instead of writing to the device
+    simulated: Option<Simulated>,
+}
 
 impl HidRaw {
+    /// The first hidraw node found for `id_product`, see `find_all`
     pub fn new(id_product: &str) -> Result<Self> {
+        match Self::find_all(&[id_product])?.into_iter().next() {
+            Some((_, path)) => {
+                info!("Using device at: {:?} for hidraw control", path);
+                Ok(Self {
+                    path,
+                    simulated: None,
+                })
+            }
+            None => Err(PlatformError::MissingFunction(format!(
+                "hidraw dev {} not found",
+                id_product
+            ))),
+        }
+    }
+
+    /// A simulated device for `--dry-run`. Writes are logged and kept, and
+    /// can be read back through `simulation()`.
+    pub fn simulated(id_product: &str) -> Self {
+        info!("dry-run: using a simulated hidraw device for {id_product}");
+        Self {
+            path: PathBuf::from(format!("dry-run-hidraw-{id_product}")),
+            simulated: Some(Simulated::default()),
+        }
+    }
+
+    /// The state of a device made with `simulated()`
+    pub fn simulation(&self) -> Option<&Simulated> {
+        self.simulated.as_ref()
+    }
+
+    /// Find every hidraw node whose USB parent matches one of `id_products`,
+    /// or a virtual uhid device for testing named with one. Returns the
+    /// product ID paired with the device node path, in udev scan order.
+    pub fn find_all(id_products: &[&str]) -> Result<Vec<(String, PathBuf)>> {
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
             PlatformError::Udev("enumerator failed".into(), err)
@@ -21,6 +69,7 @@ impl HidRaw {
             PlatformError::Udev("match_subsystem failed".into(), err)
         })?;
 
+        let mut found = Vec::new();
         for device in enumerator
             .scan_devices()
             .map_err(|e| PlatformError::IoPath("enumerator".to_owned(), e))?
@@ -31,41 +80,155 @@ impl HidRaw {
                     PlatformError::IoPath(device.devpath().to_string_lossy().to_string(), e)
                 })?
             {
-                if let Some(parent) = parent.attribute_value("idProduct") {
-                    if parent == id_product {
+                if let Some(id) = parent.attribute_value("idProduct") {
+                    let id = id.to_string_lossy();
+                    if id_products.contains(&id.as_ref()) {
                         if let Some(dev_node) = device.devnode() {
-                            info!("Using device at: {:?} for hidraw control", dev_node);
-                            return Ok(Self(dev_node.to_owned()));
+                            found.push((id.to_string(), dev_node.to_owned()));
                         }
                     }
                 }
             } else {
                 // Try to see if there is a virtual device created with uhid for testing
                 let dev_path = device.devpath().to_string_lossy();
-                if dev_path.contains("virtual") && dev_path.contains(&id_product.to_uppercase()) {
-                    if let Some(dev_node) = device.devnode() {
-                        info!(
-                            "Using device at: {:?} for <TODO: label control> control",
-                            dev_node
-                        );
-                        return Ok(Self(dev_node.to_owned()));
+                if dev_path.contains("virtual") {
+                    if let Some(id) = id_products
+                        .iter()
+                        .find(|id| dev_path.contains(&id.to_uppercase()))
+                    {
+                        if let Some(dev_node) = device.devnode() {
+                            found.push((id.to_string(), dev_node.to_owned()));
+                        }
                     }
                 }
             }
         }
-        Err(PlatformError::MissingFunction(format!(
-            "hidraw dev {} not found",
-            id_product
-        )))
+        Ok(found)
     }
 
+    /// The `/dev/hidraw*` node in use
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write `message` to the device, retrying a few times as the node can
+    /// be briefly busy. The retries sleep on the calling thread, blocking it
+    /// for at most 30ms in total, which is short enough to call from async
+    /// code.
     pub fn write_bytes(&self, message: &[u8]) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .open(&self.0)
-            .map_err(|e| PlatformError::IoPath(self.0.to_string_lossy().to_string(), e))?;
+        if let Some(sim) = &self.simulated {
+            sim.write_bytes(&self.path.to_string_lossy(), message);
+            return Ok(());
+        }
         // println!("write: {:02x?}", &message);
-        file.write_all(message)
-            .map_err(|e| PlatformError::IoPath(self.0.to_string_lossy().to_string(), e))
+        write_with_retry(|| OpenOptions::new().write(true).open(&self.path), message)
+            .map_err(|e| PlatformError::IoPath(self.path.to_string_lossy().to_string(), e))
+    }
+}
+
+/// Only a busy node (`EBUSY`) or a full queue (`EAGAIN`) is worth retrying,
+/// anything else will fail the same way again
+fn is_transient(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::ResourceBusy | std::io::ErrorKind::WouldBlock
+    )
+}
+
+/// Open and write up to `HID_WRITE_ATTEMPTS` times while the error is
+/// transient, returning the last error if every attempt fails
+fn write_with_retry<W, F>(mut open: F, message: &[u8]) -> std::io::Result<()>
+where
+    W: Write,
+    F: FnMut() -> std::io::Result<W>,
+{
+    let mut backoff = HID_WRITE_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let res = open().and_then(|mut w| w.write_all(message));
+        match res {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < HID_WRITE_ATTEMPTS && is_transient(&e) => {
+                warn!("hidraw write attempt {attempt} failed: {e}, retrying");
+                sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use super::{write_with_retry, HID_WRITE_ATTEMPTS};
+
+    /// Fails each write with `error` while `fails_left` is above zero
+    struct MockWriter {
+        error: io::ErrorKind,
+        fails_left: Rc<Cell<u32>>,
+        written: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl Write for MockWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fails_left.get() > 0 {
+                self.fails_left.set(self.fails_left.get() - 1);
+                return Err(io::Error::from(self.error));
+            }
+            self.written.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(error: io::ErrorKind, fails: u32) -> (io::Result<()>, u32, Vec<u8>) {
+        let fails_left = Rc::new(Cell::new(fails));
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let mut opens = 0;
+        let res = write_with_retry(
+            || {
+                opens += 1;
+                Ok(MockWriter {
+                    error,
+                    fails_left: fails_left.clone(),
+                    written: written.clone(),
+                })
+            },
+            &[0x5d, 0xb3],
+        );
+        let written = written.borrow().clone();
+        (res, opens, written)
+    }
+
+    #[test]
+    fn write_retries_then_succeeds() {
+        let (res, opens, written) = run(io::ErrorKind::ResourceBusy, 2);
+        assert!(res.is_ok());
+        assert_eq!(opens, 3);
+        assert_eq!(written, vec![0x5d, 0xb3]);
+    }
+
+    #[test]
+    fn write_gives_up_with_last_error() {
+        let (res, opens, written) = run(io::ErrorKind::WouldBlock, HID_WRITE_ATTEMPTS);
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(opens, HID_WRITE_ATTEMPTS);
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn write_fails_fast_on_other_errors() {
+        let (res, opens, written) = run(io::ErrorKind::PermissionDenied, 1);
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(opens, 1);
+        assert!(written.is_empty());
     }
 }
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use log::{debug, warn};
+
+use crate::error::{PlatformError, Result};
+
+/// The hwmon drivers that report a CPU or GPU package temperature as
+/// `temp1_input`
+const TEMPERATURE_SENSORS: [&str; 4] = ["k10temp", "coretemp", "zenpower", "amdgpu"];
+
+/// Find the `temp1_input` files of the CPU/GPU hwmon sensors. The paths can
+/// be kept and read with `read_temperature_from` to avoid a udev scan for
+/// each reading.
+pub fn temperature_inputs() -> Result<Vec<PathBuf>> {
+    let mut enumerator = udev::Enumerator::new().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("enumerator failed".into(), err)
+    })?;
+
+    enumerator.match_subsystem("hwmon").map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("match_subsystem failed".into(), err)
+    })?;
+
+    let mut inputs = Vec::new();
+    for device in enumerator.scan_devices().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("scan_devices failed".into(), err)
+    })? {
+        let name = match device.attribute_value("name") {
+            Some(name) => name.to_string_lossy().trim().to_owned(),
+            None => continue,
+        };
+        if !TEMPERATURE_SENSORS.contains(&name.as_str()) {
+            continue;
+        }
+        let input = device.syspath().join("temp1_input");
+        if input.exists() {
+            debug!("hwmon {name} temperature at {}", input.display());
+            inputs.push(input);
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(PlatformError::NotSupported);
+    }
+    Ok(inputs)
+}
+
+/// Read the hottest temperature of `inputs` in degrees celsius
+pub fn read_temperature_from(inputs: &[PathBuf]) -> Result<u8> {
+    let mut highest = None;
+    for input in inputs {
+        let value = std::fs::read_to_string(input)
+            .map_err(|e| PlatformError::IoPath(input.to_string_lossy().to_string(), e))?;
+        let millis = value
+            .trim()
+            .parse::<i32>()
+            .map_err(|_| PlatformError::ParseNum)?;
+        let temp = (millis / 1000).clamp(0, u8::MAX as i32) as u8;
+        if Some(temp) > highest {
+            highest = Some(temp);
+        }
+    }
+
+    highest.ok_or(PlatformError::NotSupported)
+}
+
+/// Read the hottest CPU/GPU temperature from hwmon in degrees celsius
+pub fn read_temperature() -> Result<u8> {
+    read_temperature_from(&temperature_inputs()?)
+}
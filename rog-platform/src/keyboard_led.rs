@@ -1,22 +1,62 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use log::{info, warn};
 
+use crate::dry_run::Simulated;
 use crate::error::{PlatformError, Result};
-use crate::{attr_u8, has_attr, set_attr_u8_array, to_device};
+use crate::{
+    get_attr_u8, get_attr_u8_array, has_attr, set_attr_u8_array, to_device, watch_attr,
+    write_attr_u8,
+};
 
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Clone)]
 pub struct KeyboardLed {
+    /// The primary node, `asus::kbd_backlight` or the lowest indexed one
     path: PathBuf,
+    /// Further `asus::kbd_backlight_N` nodes for separate keyboard regions
+    extra_paths: Vec<PathBuf>,
+    /// Log and simulate every read and write instead of using sysfs
+    simulated: Option<Simulated>,
 }
 
 impl KeyboardLed {
-    attr_u8!("brightness", path);
+    /// The primary `asus::kbd_backlight` node in use
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    has_attr!("brightness" path);
+
+    get_attr_u8!(
+        /// Read the brightness of the primary node
+        "brightness"
+        path
+    );
+
+    watch_attr!("brightness" path);
+
+    /// Set the brightness on every `asus::kbd_backlight` node
+    pub fn set_brightness(&self, value: u8) -> Result<()> {
+        if let Some(sim) = &self.simulated {
+            return sim.write("brightness", value.to_string());
+        }
+        for path in std::iter::once(&self.path).chain(self.extra_paths.iter()) {
+            write_attr_u8(&mut to_device(path)?, "brightness", value)?;
+        }
+        Ok(())
+    }
 
     has_attr!("kbd_rgb_mode" path);
 
+    get_attr_u8_array!(
+        /// Read back the last `kbd_rgb_mode` written. Most kernels only allow
+        /// it to be set, so this errors there.
+        "kbd_rgb_mode"
+        path
+    );
+
     set_attr_u8_array!(
-        /// kbd_rgb_mode can only be set, not read back
+        /// kbd_rgb_mode can only be set on most kernels, see `get_kbd_rgb_mode`
         "kbd_rgb_mode"
         path
     );
@@ -41,25 +81,122 @@ impl KeyboardLed {
         })?;
 
         enumerator
-            .match_sysname("asus::kbd_backlight")
+            .match_sysname("asus::kbd_backlight*")
             .map_err(|err| {
                 warn!("{}", err);
                 PlatformError::Udev("match_subsystem failed".into(), err)
             })?;
 
-        if let Some(device) = (enumerator.scan_devices().map_err(|err| {
-            warn!("{}", err);
-            PlatformError::Udev("scan_devices failed".into(), err)
-        })?)
-        .next()
-        {
-            info!("Found keyboard LED controls at {:?}", device.sysname());
+        let mut nodes: Vec<(String, PathBuf)> = enumerator
+            .scan_devices()
+            .map_err(|err| {
+                warn!("{}", err);
+                PlatformError::Udev("scan_devices failed".into(), err)
+            })?
+            .map(|device| {
+                info!("Found keyboard LED controls at {:?}", device.sysname());
+                (
+                    device.sysname().to_string_lossy().to_string(),
+                    device.syspath().to_owned(),
+                )
+            })
+            .collect();
+
+        if let Some((path, extra_paths)) = split_primary(&mut nodes) {
             return Ok(Self {
-                path: device.syspath().to_owned(),
+                path,
+                extra_paths,
+                simulated: None,
             });
         }
         Err(PlatformError::MissingFunction(
             "asus::kbd_backlight not found".into(),
         ))
     }
+
+    /// A simulated `asus::kbd_backlight` for `--dry-run`. See
+    /// `crate::dry_run::Simulated`.
+    pub fn simulated() -> Self {
+        info!("dry-run: using a simulated asus::kbd_backlight device");
+        Self {
+            path: PathBuf::from("dry-run-kbd_backlight"),
+            extra_paths: Vec::new(),
+            simulated: Some(Simulated::default()),
+        }
+    }
+}
+
+/// Find the `/dev/input/event*` nodes of every keyboard, for watching key
+/// presses
+pub fn keyboard_input_nodes() -> Result<Vec<PathBuf>> {
+    let mut enumerator = udev::Enumerator::new().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("enumerator failed".into(), err)
+    })?;
+
+    enumerator.match_subsystem("input").map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("match_subsystem failed".into(), err)
+    })?;
+
+    enumerator
+        .match_property("ID_INPUT_KEYBOARD", "1")
+        .map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_property failed".into(), err)
+        })?;
+
+    Ok(enumerator
+        .scan_devices()
+        .map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("scan_devices failed".into(), err)
+        })?
+        .filter(|device| device.sysname().to_string_lossy().starts_with("event"))
+        .filter_map(|device| device.devnode().map(|n| n.to_owned()))
+        .collect())
+}
+
+/// Sort the nodes by name so that `asus::kbd_backlight` (or else the lowest
+/// index) is the primary, returning it and the rest
+fn split_primary(nodes: &mut [(String, PathBuf)]) -> Option<(PathBuf, Vec<PathBuf>)> {
+    nodes.sort_by(|a, b| a.0.cmp(&b.0));
+    let ((_, primary), rest) = nodes.split_first()?;
+    Some((
+        primary.clone(),
+        rest.iter().map(|(_, p)| p.clone()).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::split_primary;
+
+    #[test]
+    fn primary_kbd_backlight_node() {
+        let node = |name: &str| (name.to_owned(), PathBuf::from(format!("/leds/{name}")));
+
+        let mut nodes = vec![
+            node("asus::kbd_backlight_2"),
+            node("asus::kbd_backlight"),
+            node("asus::kbd_backlight_1"),
+        ];
+        let (primary, rest) = split_primary(&mut nodes).unwrap();
+        assert_eq!(primary, PathBuf::from("/leds/asus::kbd_backlight"));
+        assert_eq!(
+            rest,
+            vec![
+                PathBuf::from("/leds/asus::kbd_backlight_1"),
+                PathBuf::from("/leds/asus::kbd_backlight_2")
+            ]
+        );
+
+        let mut nodes = vec![node("asus::kbd_backlight")];
+        let (_, rest) = split_primary(&mut nodes).unwrap();
+        assert!(rest.is_empty());
+
+        assert!(split_primary(&mut []).is_none());
+    }
 }
@@ -1,8 +1,10 @@
 //! This crate functions as a wrapper of all the relevant ASUS functionality
 //! on ROG, Strix, and TUF laptops.
 
+pub mod dry_run;
 pub mod error;
 pub mod hid_raw;
+pub mod hwmon;
 pub mod keyboard_led;
 pub(crate) mod macros;
 pub mod platform;
@@ -10,6 +12,8 @@ pub mod power;
 pub mod supported;
 pub mod usb_raw;
 
+use std::ffi::OsStr;
+use std::fmt::Display;
 use std::path::Path;
 
 use error::{PlatformError, Result};
@@ -62,6 +66,27 @@ pub fn write_attr_u8(device: &mut Device, attr: &str, value: u8) -> Result<()> {
         .map_err(|e| PlatformError::IoPath(attr.into(), e))
 }
 
+/// Write using `write` then read back using `read`, returning an error if the
+/// value read does not match. For attributes known to silently ignore writes
+/// on some firmware.
+pub fn write_verified<T>(
+    attr: &str,
+    value: T,
+    write: impl FnOnce(T) -> Result<()>,
+    read: impl FnOnce() -> Result<T>,
+) -> Result<()>
+where
+    T: PartialEq + Display + Copy,
+{
+    write(value)?;
+    let applied = read()?;
+    if applied != value {
+        log::warn!("{attr}: wrote {value} but read back {applied}");
+        return Err(PlatformError::WriteNotApplied(attr.to_owned()));
+    }
+    Ok(())
+}
+
 pub fn read_attr_u8_array(device: &Device, attr_name: &str) -> Result<Vec<u8>> {
     if let Some(value) = device.attribute_value(attr_name) {
         let tmp = value.to_string_lossy();
@@ -88,12 +113,17 @@ pub fn write_attr_u8_array(device: &mut Device, attr: &str, values: &[u8]) -> Re
 
 pub fn read_attr_string(device: &Device, attr_name: &str) -> Result<String> {
     if let Some(value) = device.attribute_value(attr_name) {
-        let tmp = value.to_string_lossy().to_string();
-        return Ok(tmp);
+        return Ok(attr_value_to_string(value));
     }
     Err(PlatformError::AttrNotFound(attr_name.to_owned()))
 }
 
+/// Text attributes can end with a newline or be padded, neither of which is
+/// part of the value
+fn attr_value_to_string(value: &OsStr) -> String {
+    value.to_string_lossy().trim().to_owned()
+}
+
 pub fn write_attr_string(device: &mut Device, attr: &str, value: &str) -> Result<()> {
     let tmp = value.trim();
     device
@@ -103,6 +133,75 @@ pub fn write_attr_string(device: &mut Device, attr: &str, value: &str) -> Result
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use crate::error::{PlatformError, Result};
+    use crate::{attr_value_to_string, write_verified};
+
+    /// A fake attribute which may silently ignore writes
+    struct FakeAttr {
+        value: Cell<u8>,
+        accepts: bool,
+    }
+
+    impl FakeAttr {
+        fn write(&self, v: u8) -> Result<()> {
+            if self.accepts {
+                self.value.set(v);
+            }
+            Ok(())
+        }
+
+        fn read(&self) -> Result<u8> {
+            Ok(self.value.get())
+        }
+    }
+
+    #[test]
+    fn write_verified_attr() {
+        let attr = FakeAttr {
+            value: Cell::new(0),
+            accepts: true,
+        };
+        assert!(write_verified("fake", 1, |v| attr.write(v), || attr.read()).is_ok());
+        assert_eq!(attr.value.get(), 1);
+
+        let attr = FakeAttr {
+            value: Cell::new(0),
+            accepts: false,
+        };
+        let err = write_verified("fake", 1, |v| attr.write(v), || attr.read()).unwrap_err();
+        assert!(matches!(err, PlatformError::WriteNotApplied(a) if a == "fake"));
+
+        // Failure to write is returned as is
+        let err = write_verified(
+            "fake",
+            true,
+            |_| Err(PlatformError::NotSupported),
+            || Ok(false),
+        )
+        .unwrap_err();
+        assert!(matches!(err, PlatformError::NotSupported));
+    }
+
+    #[test]
+    fn attr_string_is_trimmed() {
+        assert_eq!(attr_value_to_string(OsStr::new("balanced\n")), "balanced");
+        assert_eq!(
+            attr_value_to_string(OsStr::new(" quiet balanced performance \n")),
+            "quiet balanced performance"
+        );
+        assert_eq!(attr_value_to_string(OsStr::new("")), "");
+        // Invalid UTF-8 is replaced rather than failing the read
+        assert_eq!(
+            attr_value_to_string(OsStr::from_bytes(b"bal\xffanced\n")),
+            "bal\u{fffd}anced"
+        );
+    }
+
     #[test]
     fn check() {
         let data = [1, 2, 3, 4, 5];
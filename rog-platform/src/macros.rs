@@ -4,6 +4,9 @@ macro_rules! has_attr {
         concat_idents::concat_idents!(fn_name = has_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> bool {
+                if self.simulated.is_some() {
+                    return true;
+                }
                 match to_device(&self.$item) {
                     Ok(p) => $crate::has_attr(&p, $attr_name),
                     Err(_) => false,
@@ -19,6 +22,10 @@ macro_rules! watch_attr {
         concat_idents::concat_idents!(fn_name = monitor_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> Result<inotify::Inotify> {
+                if self.simulated.is_some() {
+                    // Nothing is watched so no change is ever seen
+                    return Ok(inotify::Inotify::init()?);
+                }
                 let mut path = self.$item.clone();
                 path.push($attr_name);
                 if let Some(path) = path.to_str() {
@@ -45,6 +52,9 @@ macro_rules! get_attr_bool {
         concat_idents::concat_idents!(fn_name = get_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> Result<bool> {
+                if let Some(sim) = &self.simulated {
+                    return sim.read_bool($attr_name);
+                }
                 $crate::read_attr_bool(&to_device(&self.$item)?, $attr_name)
             }
         });
@@ -57,6 +67,9 @@ macro_rules! set_attr_bool {
         concat_idents::concat_idents!(fn_name = set_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self, value: bool) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    return sim.write($attr_name, (value as u8).to_string());
+                }
                 $crate::write_attr_bool(&mut to_device(&self.$item)?, $attr_name, value)
             }
         });
@@ -73,12 +86,49 @@ macro_rules! attr_bool {
     };
 }
 
+/// As `set_attr_bool` but reads the attribute back after writing and errors
+/// if the value did not take
+#[macro_export]
+macro_rules! set_attr_bool_verified {
+    ($(#[$attr:meta])* $attr_name:literal $item:ident) => {
+        concat_idents::concat_idents!(fn_name = set_, $attr_name {
+            $(#[$attr])*
+            pub fn fn_name(&self, value: bool) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    return sim.write($attr_name, (value as u8).to_string());
+                }
+                $crate::write_verified(
+                    $attr_name,
+                    value,
+                    |v| $crate::write_attr_bool(&mut to_device(&self.$item)?, $attr_name, v),
+                    || $crate::read_attr_bool(&to_device(&self.$item)?, $attr_name),
+                )
+            }
+        });
+    };
+}
+
+/// For attributes that may silently ignore writes on some firmware. The
+/// setter reads back the value and errors if it was not applied.
+#[macro_export]
+macro_rules! attr_bool_verified {
+    ($(#[$attr:meta])* $attr_name:literal, $item:ident) => {
+        $crate::has_attr!($attr_name $item);
+        $crate::get_attr_bool!( $attr_name $item);
+        $crate::set_attr_bool_verified!($attr_name $item);
+        $crate::watch_attr!($attr_name $item);
+    };
+}
+
 #[macro_export]
 macro_rules! get_attr_u8 {
     ($(#[$attr:meta])* $attr_name:literal $item:ident) => {
         concat_idents::concat_idents!(fn_name = get_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> Result<u8> {
+                if let Some(sim) = &self.simulated {
+                    return sim.read_u8($attr_name);
+                }
                 $crate::read_attr_u8(&to_device(&self.$item)?, $attr_name)
             }
         });
@@ -92,6 +142,9 @@ macro_rules! set_attr_u8 {
         concat_idents::concat_idents!(fn_name = set_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self, value: u8) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    return sim.write($attr_name, value.to_string());
+                }
                 $crate::write_attr_u8(&mut to_device(&self.$item)?, $attr_name, value)
             }
         });
@@ -108,12 +161,49 @@ macro_rules! attr_u8 {
     };
 }
 
+/// As `set_attr_u8` but reads the attribute back after writing and errors if
+/// the value did not take
+#[macro_export]
+macro_rules! set_attr_u8_verified {
+    ($(#[$attr:meta])* $attr_name:literal $item:ident) => {
+        concat_idents::concat_idents!(fn_name = set_, $attr_name {
+            $(#[$attr])*
+            pub fn fn_name(&self, value: u8) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    return sim.write($attr_name, value.to_string());
+                }
+                $crate::write_verified(
+                    $attr_name,
+                    value,
+                    |v| $crate::write_attr_u8(&mut to_device(&self.$item)?, $attr_name, v),
+                    || $crate::read_attr_u8(&to_device(&self.$item)?, $attr_name),
+                )
+            }
+        });
+    };
+}
+
+/// For attributes that may silently ignore writes on some firmware. The
+/// setter reads back the value and errors if it was not applied.
+#[macro_export]
+macro_rules! attr_u8_verified {
+    ($(#[$attr:meta])* $attr_name:literal, $item:ident) => {
+        $crate::has_attr!($(#[$attr])* $attr_name $item);
+        $crate::get_attr_u8!($(#[$attr])* $attr_name $item);
+        $crate::set_attr_u8_verified!($(#[$attr])* $attr_name $item);
+        $crate::watch_attr!($(#[$attr])* $attr_name $item);
+    };
+}
+
 #[macro_export]
 macro_rules! get_attr_u8_array {
     ($(#[$attr:meta])* $attr_name:literal $item:ident) => {
         concat_idents::concat_idents!(fn_name = get_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> Result<Vec<u8>> {
+                if let Some(sim) = &self.simulated {
+                    return sim.read_u8_array($attr_name);
+                }
                 $crate::read_attr_u8_array(&to_device(&self.$item)?, $attr_name)
             }
         });
@@ -126,6 +216,10 @@ macro_rules! set_attr_u8_array {
         concat_idents::concat_idents!(fn_name = set_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self, values: &[u8]) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                    return sim.write($attr_name, values.join(" "));
+                }
                 $crate::write_attr_u8_array(&mut to_device(&self.$item)?, $attr_name, values)
             }
         });
@@ -148,6 +242,9 @@ macro_rules! get_attr_string {
         concat_idents::concat_idents!(fn_name = get_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self) -> Result<String> {
+                if let Some(sim) = &self.simulated {
+                    return sim.read_string($attr_name);
+                }
                 $crate::read_attr_string(&to_device(&self.$item)?, $attr_name)
             }
         });
@@ -160,18 +257,23 @@ macro_rules! set_attr_string {
         concat_idents::concat_idents!(fn_name = set_, $attr_name {
             $(#[$attr])*
             pub fn fn_name(&self, values: &str) -> Result<()> {
+                if let Some(sim) = &self.simulated {
+                    return sim.write($attr_name, values.trim().to_owned());
+                }
                 $crate::write_attr_string(&mut to_device(&self.$item)?, $attr_name, values)
             }
         });
     };
 }
 
+/// For text attributes such as `platform_profile`. The value read is trimmed
+/// of the trailing newline and any padding.
 #[macro_export]
 macro_rules! attr_string {
     ($(#[$attr:meta])* $attr_name:literal, $item:ident) => {
-        $crate::has_attr!($attr_name $item);
-        $crate::get_attr_string!($attr_name $item);
-        $crate::set_attr_string!($attr_name $item);
-        $crate::watch_attr!($attr_name $item);
+        $crate::has_attr!($(#[$attr])* $attr_name $item);
+        $crate::get_attr_string!($(#[$attr])* $attr_name $item);
+        $crate::set_attr_string!($(#[$attr])* $attr_name $item);
+        $crate::watch_attr!($(#[$attr])* $attr_name $item);
     };
 }
@@ -1,15 +1,20 @@
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
-use zbus::zvariant::Type;
+use zbus::zvariant::{Optional, Type};
 
+use crate::dry_run::Simulated;
 use crate::error::{PlatformError, Result};
 use crate::supported::PlatformSupportedFunctions;
-use crate::{attr_bool, attr_string, attr_u8, to_device};
+use crate::{
+    attr_bool, attr_bool_verified, attr_string, attr_u8, attr_u8_verified, get_attr_string,
+    get_attr_u8_array, has_attr, read_attr_u8, to_device, write_attr_u8,
+};
 
 /// The "platform" device provides access to things like:
 /// - `dgpu_disable`
@@ -23,18 +28,34 @@ use crate::{attr_bool, attr_string, attr_u8, to_device};
 pub struct AsusPlatform {
     path: PathBuf,
     pp_path: PathBuf,
+    /// Log and simulate every read and write instead of using sysfs
+    simulated: Option<Simulated>,
 }
 
 impl AsusPlatform {
+    /// The syspath of the platform device in use
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     attr_bool!("dgpu_disable", path);
 
     attr_bool!("egpu_enable", path);
 
-    attr_bool!("panel_od", path);
+    // Some firmware silently ignores writes to these
+    attr_bool_verified!("panel_od", path);
 
     attr_bool!("mini_led_mode", path);
 
-    attr_u8!("gpu_mux_mode", path);
+    has_attr!("available_mini_led_mode" path);
+
+    get_attr_u8_array!(
+        /// The values `mini_led_mode` accepts on panels with more than on/off
+        "available_mini_led_mode"
+        path
+    );
+
+    attr_u8_verified!("gpu_mux_mode", path);
 
     attr_u8!(
         /// This is technically the same as `platform_profile` since both are
@@ -49,6 +70,14 @@ impl AsusPlatform {
         pp_path
     );
 
+    has_attr!("platform_profile_choices" pp_path);
+
+    get_attr_string!(
+        /// The space separated `platform_profile` values the firmware accepts
+        "platform_profile_choices"
+        pp_path
+    );
+
     attr_u8!(
         /// Package Power Target total of CPU: PL1 on Intel, SPL on AMD.
         /// Shown on Intel+Nvidia or AMD+Nvidia based systems:
@@ -107,34 +136,93 @@ impl AsusPlatform {
     );
 
     pub fn new() -> Result<Self> {
-        let mut enumerator = udev::Enumerator::new().map_err(|err| {
-            warn!("{}", err);
-            PlatformError::Udev("enumerator failed".into(), err)
-        })?;
-        enumerator.match_subsystem("platform").map_err(|err| {
-            warn!("{}", err);
-            PlatformError::Udev("match_subsystem failed".into(), err)
-        })?;
-        enumerator.match_sysname("asus-nb-wmi").map_err(|err| {
-            warn!("{}", err);
-            PlatformError::Udev("match_subsystem failed".into(), err)
-        })?;
+        for sysname in PLATFORM_SYSNAMES {
+            if let Some(device) = find_platform_device(sysname)? {
+                info!(
+                    "Found platform support at {:?} using {sysname}",
+                    device.sysname()
+                );
+                return Ok(Self {
+                    path: device.syspath().to_owned(),
+                    pp_path: PathBuf::from_str("/sys/firmware/acpi").unwrap(),
+                    simulated: None,
+                });
+            }
+        }
+        Err(PlatformError::MissingFunction(format!(
+            "none of {} found",
+            PLATFORM_SYSNAMES.join(", ")
+        )))
+    }
+
+    /// A simulated platform device for `--dry-run` that has every attribute.
+    /// See `crate::dry_run::Simulated`.
+    pub fn simulated() -> Self {
+        info!("dry-run: using a simulated asus-nb-wmi device");
+        Self {
+            path: PathBuf::from("dry-run-asus-nb-wmi"),
+            pp_path: PathBuf::from("dry-run-acpi"),
+            simulated: Some(Simulated::default()),
+        }
+    }
+}
 
-        if let Some(device) = (enumerator.scan_devices().map_err(|err| {
+/// The sysnames the ASUS platform interface is exposed as, in the order they
+/// are tried. Newer kernels use `asus-armoury` on some models.
+const PLATFORM_SYSNAMES: [&str; 2] = ["asus-nb-wmi", "asus-armoury"];
+
+fn find_platform_device(sysname: &str) -> Result<Option<udev::Device>> {
+    let mut enumerator = udev::Enumerator::new().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("enumerator failed".into(), err)
+    })?;
+    enumerator.match_subsystem("platform").map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("match_subsystem failed".into(), err)
+    })?;
+    enumerator.match_sysname(sysname).map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("match_sysname failed".into(), err)
+    })?;
+
+    let device = enumerator
+        .scan_devices()
+        .map_err(|err| {
             warn!("{}", err);
             PlatformError::Udev("scan_devices failed".into(), err)
-        })?)
-        .next()
-        {
-            info!("Found platform support at {:?}", device.sysname());
-            return Ok(Self {
-                path: device.syspath().to_owned(),
-                pp_path: PathBuf::from_str("/sys/firmware/acpi").unwrap(),
-            });
+        })?
+        .next();
+    Ok(device)
+}
+
+impl AsusPlatform {
+    /// If the GPU MUX can actually be switched. Some MUX-less laptops expose
+    /// `gpu_mux_mode` as read-only.
+    pub fn gpu_mux_switchable(&self) -> bool {
+        if !self.has_gpu_mux_mode() {
+            return false;
+        }
+        if self.simulated.is_some() {
+            return true;
+        }
+        std::fs::metadata(self.path.join("gpu_mux_mode"))
+            .map(|m| m.permissions().mode() & 0o222 != 0)
+            .unwrap_or(false)
+    }
+
+    /// Read the MUX, dGPU, and eGPU state together
+    pub fn get_gpu_status(&self) -> GpuStatus {
+        GpuStatus {
+            mux_mode: if self.has_gpu_mux_mode() {
+                self.get_gpu_mux_mode()
+                    .map(GpuMode::from_mux)
+                    .unwrap_or(GpuMode::Error)
+            } else {
+                GpuMode::NotSupported
+            },
+            dgpu_disabled: self.has_dgpu_disable() && self.get_dgpu_disable().unwrap_or_default(),
+            egpu_enabled: self.has_egpu_enable() && self.get_egpu_enable().unwrap_or_default(),
         }
-        Err(PlatformError::MissingFunction(
-            "asus-nb-wmi not found".into(),
-        ))
     }
 }
 
@@ -144,6 +232,7 @@ impl Default for AsusPlatform {
             Self {
                 path: PathBuf::from_str("/this_shouldNeVErr_exisid").unwrap_unchecked(),
                 pp_path: PathBuf::from_str("/this_shouldNeVErr_exisid").unwrap_unchecked(),
+                simulated: None,
             }
         }
     }
@@ -154,10 +243,12 @@ impl From<AsusPlatform> for PlatformSupportedFunctions {
         PlatformSupportedFunctions {
             post_animation_sound: a.has_post_animation_sound(),
             gpu_mux: a.has_gpu_mux_mode(),
+            gpu_mux_switchable: a.gpu_mux_switchable(),
             panel_overdrive: a.has_panel_od(),
             dgpu_disable: a.has_dgpu_disable(),
             egpu_enable: a.has_egpu_enable(),
             mini_led_mode: a.has_mini_led_mode(),
+            mini_led_levels: a.mini_led_levels(),
             ppt_pl1_spl: a.has_ppt_pl1_spl(),
             ppt_pl2_sppt: a.has_ppt_pl2_sppt(),
             ppt_fppt: a.has_ppt_fppt(),
@@ -169,6 +260,191 @@ impl From<AsusPlatform> for PlatformSupportedFunctions {
     }
 }
 
+/// The PPT and Nvidia power limits applied by asusd. A limit that is not
+/// supported by the platform, or has not been set, is `None`. This is sent as
+/// `0` over dbus as no valid limit is below `5`.
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone)]
+pub struct PptLimits {
+    pub ppt_pl1_spl: Optional<u8>,
+    pub ppt_pl2_sppt: Optional<u8>,
+    pub ppt_fppt: Optional<u8>,
+    pub ppt_apu_sppt: Optional<u8>,
+    pub ppt_platform_sppt: Optional<u8>,
+    pub nv_dynamic_boost: Optional<u8>,
+    pub nv_temp_target: Optional<u8>,
+}
+
+/// The `throttle_thermal_policy` values. On most laptops this is tied to
+/// `platform_profile` in-kernel, but on some it can be set on its own.
+#[typeshare]
+#[repr(u8)]
+#[derive(Serialize, Deserialize, Default, Type, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ThermalPolicy {
+    #[default]
+    Default = 0,
+    Performance = 1,
+    Silent = 2,
+}
+
+impl TryFrom<u8> for ThermalPolicy {
+    type Error = PlatformError;
+
+    fn try_from(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(ThermalPolicy::Default),
+            1 => Ok(ThermalPolicy::Performance),
+            2 => Ok(ThermalPolicy::Silent),
+            _ => Err(PlatformError::ParseNum),
+        }
+    }
+}
+
+impl AsusPlatform {
+    pub fn get_thermal_policy(&self) -> Result<ThermalPolicy> {
+        ThermalPolicy::try_from(self.get_throttle_thermal_policy()?)
+    }
+
+    pub fn set_thermal_policy(&self, policy: ThermalPolicy) -> Result<()> {
+        self.set_throttle_thermal_policy(policy as u8)
+    }
+}
+
+/// The `mini_led_mode` levels. Older panels only support `Off` and
+/// `MultiZone`.
+#[typeshare]
+#[repr(u8)]
+#[derive(Serialize, Deserialize, Default, Type, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MiniLedLevel {
+    #[default]
+    Off,
+    /// Local dimming across the panel zones
+    MultiZone,
+    /// Local dimming with a stronger contrast
+    MultiZoneStrong,
+}
+
+impl From<u8> for MiniLedLevel {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => MiniLedLevel::Off,
+            2 => MiniLedLevel::MultiZoneStrong,
+            _ => MiniLedLevel::MultiZone,
+        }
+    }
+}
+
+impl AsusPlatform {
+    /// The mini-LED levels the panel supports, falling back to on/off if the
+    /// kernel does not list them. Empty if there is no mini-LED control.
+    pub fn mini_led_levels(&self) -> Vec<MiniLedLevel> {
+        if !self.has_mini_led_mode() {
+            return Vec::new();
+        }
+        if self.has_available_mini_led_mode() {
+            if let Ok(levels) = self.get_available_mini_led_mode() {
+                return levels.into_iter().map(MiniLedLevel::from).collect();
+            }
+        }
+        vec![MiniLedLevel::Off, MiniLedLevel::MultiZone]
+    }
+
+    pub fn get_mini_led_level(&self) -> Result<MiniLedLevel> {
+        if let Some(sim) = &self.simulated {
+            return sim.read_u8("mini_led_mode").map(MiniLedLevel::from);
+        }
+        read_attr_u8(&to_device(&self.path)?, "mini_led_mode").map(MiniLedLevel::from)
+    }
+
+    /// Write the level to `mini_led_mode`, erroring if the panel does not
+    /// support it
+    pub fn set_mini_led_level(&self, level: MiniLedLevel) -> Result<()> {
+        if !self.mini_led_levels().contains(&level) {
+            return Err(PlatformError::NotSupported);
+        }
+        if let Some(sim) = &self.simulated {
+            return sim.write("mini_led_mode", (level as u8).to_string());
+        }
+        write_attr_u8(&mut to_device(&self.path)?, "mini_led_mode", level as u8)
+    }
+}
+
+/// The GPU routing state. A dGPU or eGPU node the platform does not have is
+/// read as `false`, and a missing MUX as `GpuMode::NotSupported`.
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GpuStatus {
+    pub mux_mode: GpuMode,
+    pub dgpu_disabled: bool,
+    pub egpu_enabled: bool,
+}
+
+/// If the dGPU is powered, as read from its runtime power management
+#[typeshare]
+#[derive(Serialize, Deserialize, Default, Type, Debug, PartialEq, Eq, Clone, Copy)]
+#[zvariant(signature = "s")]
+pub enum DgpuPower {
+    Active,
+    Suspended,
+    /// Disabled with `dgpu_disable` or not found on the PCI bus
+    #[default]
+    Off,
+}
+
+impl FromStr for DgpuPower {
+    type Err = PlatformError;
+
+    /// Parse the `power/runtime_status` of the dGPU. `unsupported` means
+    /// runtime power management is off so the device is always powered.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "active" | "resuming" | "unsupported" => Ok(DgpuPower::Active),
+            "suspended" | "suspending" => Ok(DgpuPower::Suspended),
+            s => Err(PlatformError::ParsePower(s.to_owned())),
+        }
+    }
+}
+
+impl AsusPlatform {
+    /// Read if the dGPU is powered. The dGPU is the display controller that
+    /// did not boot the display, or `Off` if there isn't one.
+    pub fn get_dgpu_power(&self) -> Result<DgpuPower> {
+        if self.has_dgpu_disable() && self.get_dgpu_disable().unwrap_or_default() {
+            return Ok(DgpuPower::Off);
+        }
+        if let Some(sim) = &self.simulated {
+            return DgpuPower::from_str(&sim.read_string("power/runtime_status")?);
+        }
+
+        let mut enumerator = udev::Enumerator::new().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("enumerator failed".into(), err)
+        })?;
+        enumerator.match_subsystem("pci").map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_subsystem failed".into(), err)
+        })?;
+
+        for device in enumerator.scan_devices().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("scan_devices failed".into(), err)
+        })? {
+            let is_display = device
+                .attribute_value("class")
+                .map(|class| class.to_string_lossy().starts_with("0x03"))
+                .unwrap_or(false);
+            let boot_vga = device
+                .attribute_value("boot_vga")
+                .map(|b| b.to_string_lossy().trim() == "1")
+                .unwrap_or(false);
+            if is_display && !boot_vga {
+                if let Some(status) = device.attribute_value("power/runtime_status") {
+                    return DgpuPower::from_str(&status.to_string_lossy());
+                }
+            }
+        }
+        Ok(DgpuPower::Off)
+    }
+}
+
 #[typeshare]
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Default, Type, Debug, PartialEq, Eq, Clone, Copy)]
@@ -193,7 +469,7 @@ impl From<u8> for GpuMode {
             3 => GpuMode::Egpu,
             4 => GpuMode::Vfio,
             5 => GpuMode::Ultimate,
-            6 => GpuMode::Error,
+            6 | 254 => GpuMode::Error,
             _ => GpuMode::NotSupported,
         }
     }
@@ -228,11 +504,16 @@ impl GpuMode {
         0
     }
 
+    /// For reading the `gpu_mux_mode` attribute. Only 0 and 1 are valid
+    /// modes, 255 is returned by firmware without a MUX and anything else is
+    /// an error.
     pub fn from_mux(num: u8) -> Self {
-        if num == 0 {
-            return Self::Discrete;
+        match num {
+            0 => Self::Discrete,
+            1 => Self::Optimus,
+            255 => Self::NotSupported,
+            _ => Self::Error,
         }
-        Self::Optimus
     }
 
     pub fn from_dgpu(num: u8) -> Self {
@@ -252,6 +533,41 @@ impl GpuMode {
     }
 }
 
+impl FromStr for GpuMode {
+    type Err = PlatformError;
+
+    /// Surrounding whitespace is ignored, such as the trailing newline of a
+    /// value read from sysfs
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "discrete" => Ok(GpuMode::Discrete),
+            "optimus" => Ok(GpuMode::Optimus),
+            "integrated" => Ok(GpuMode::Integrated),
+            "egpu" => Ok(GpuMode::Egpu),
+            "vfio" => Ok(GpuMode::Vfio),
+            "ultimate" => Ok(GpuMode::Ultimate),
+            _ => Err(PlatformError::ParseVendor),
+        }
+    }
+}
+
+/// The lowercase name as accepted by `FromStr`. There is deliberately no
+/// wildcard arm so that new modes must be added here.
+impl From<&GpuMode> for &str {
+    fn from(mode: &GpuMode) -> Self {
+        match mode {
+            GpuMode::Discrete => "discrete",
+            GpuMode::Optimus => "optimus",
+            GpuMode::Integrated => "integrated",
+            GpuMode::Egpu => "egpu",
+            GpuMode::Vfio => "vfio",
+            GpuMode::Ultimate => "ultimate",
+            GpuMode::Error => "error",
+            GpuMode::NotSupported => "not_supported",
+        }
+    }
+}
+
 impl Display for GpuMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -266,3 +582,118 @@ impl Display for GpuMode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{DgpuPower, GpuMode, MiniLedLevel, ThermalPolicy};
+    use crate::error::PlatformError;
+
+    #[test]
+    fn gpu_mode_from_u8() {
+        for mode in [
+            GpuMode::Discrete,
+            GpuMode::Optimus,
+            GpuMode::Integrated,
+            GpuMode::Egpu,
+            GpuMode::Vfio,
+            GpuMode::Ultimate,
+            GpuMode::Error,
+            GpuMode::NotSupported,
+        ] {
+            assert_eq!(GpuMode::from(u8::from(mode)), mode);
+        }
+        assert_eq!(GpuMode::from(254), GpuMode::Error);
+        assert_eq!(GpuMode::from(255), GpuMode::NotSupported);
+    }
+
+    #[test]
+    fn gpu_mode_from_mux() {
+        assert_eq!(GpuMode::from_mux(0), GpuMode::Discrete);
+        assert_eq!(GpuMode::from_mux(1), GpuMode::Optimus);
+        assert_eq!(GpuMode::from_mux(2), GpuMode::Error);
+        assert_eq!(GpuMode::from_mux(254), GpuMode::Error);
+        assert_eq!(GpuMode::from_mux(255), GpuMode::NotSupported);
+    }
+
+    #[test]
+    fn mini_led_level_from_u8() {
+        assert_eq!(MiniLedLevel::from(0), MiniLedLevel::Off);
+        assert_eq!(MiniLedLevel::from(1), MiniLedLevel::MultiZone);
+        assert_eq!(MiniLedLevel::from(2), MiniLedLevel::MultiZoneStrong);
+        for level in [
+            MiniLedLevel::Off,
+            MiniLedLevel::MultiZone,
+            MiniLedLevel::MultiZoneStrong,
+        ] {
+            assert_eq!(MiniLedLevel::from(level as u8), level);
+        }
+    }
+
+    #[test]
+    fn gpu_mode_str_round_trip() {
+        for mode in [
+            GpuMode::Discrete,
+            GpuMode::Optimus,
+            GpuMode::Integrated,
+            GpuMode::Egpu,
+            GpuMode::Vfio,
+            GpuMode::Ultimate,
+        ] {
+            assert_eq!(GpuMode::from_str(<&str>::from(&mode)).unwrap(), mode);
+        }
+        assert_eq!(GpuMode::from_str("vfio").unwrap(), GpuMode::Vfio);
+        assert!(GpuMode::from_str("error").is_err());
+        assert!(GpuMode::from_str("hybrid").is_err());
+    }
+
+    #[test]
+    fn gpu_mode_from_str_trims() {
+        assert_eq!(GpuMode::from_str("vfio\n").unwrap(), GpuMode::Vfio);
+        assert_eq!(GpuMode::from_str("  optimus ").unwrap(), GpuMode::Optimus);
+        assert_eq!(
+            GpuMode::from_str("\tintegrated\r\n").unwrap(),
+            GpuMode::Integrated
+        );
+        assert!(GpuMode::from_str("").is_err());
+        assert!(GpuMode::from_str(" \n").is_err());
+        assert!(GpuMode::from_str("dis crete").is_err());
+    }
+
+    #[test]
+    fn dgpu_power_from_runtime_status() {
+        assert_eq!(DgpuPower::from_str("active\n").unwrap(), DgpuPower::Active);
+        assert_eq!(
+            DgpuPower::from_str("unsupported").unwrap(),
+            DgpuPower::Active
+        );
+        assert_eq!(
+            DgpuPower::from_str("suspended").unwrap(),
+            DgpuPower::Suspended
+        );
+        assert_eq!(
+            DgpuPower::from_str("suspending").unwrap(),
+            DgpuPower::Suspended
+        );
+        assert!(matches!(
+            DgpuPower::from_str("error"),
+            Err(PlatformError::ParsePower(s)) if s == "error"
+        ));
+    }
+
+    #[test]
+    fn thermal_policy_from_u8() {
+        assert_eq!(ThermalPolicy::try_from(0).unwrap(), ThermalPolicy::Default);
+        assert_eq!(
+            ThermalPolicy::try_from(1).unwrap(),
+            ThermalPolicy::Performance
+        );
+        assert_eq!(ThermalPolicy::try_from(2).unwrap(), ThermalPolicy::Silent);
+        assert!(matches!(
+            ThermalPolicy::try_from(3),
+            Err(PlatformError::ParseNum)
+        ));
+        assert_eq!(ThermalPolicy::Silent as u8, 2);
+    }
+}
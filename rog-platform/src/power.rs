@@ -1,9 +1,74 @@
 use std::path::PathBuf;
 
 use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use typeshare::typeshare;
+use zbus::zvariant::Type;
 
+use crate::dry_run::Simulated;
 use crate::error::{PlatformError, Result};
-use crate::{attr_u8, to_device};
+use crate::{attr_u8, has_attr, read_attr_string, read_attr_u8, to_device};
+
+/// Whether the laptop is running from mains power or the battery
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[zvariant(signature = "s")]
+pub enum PowerSource {
+    Ac,
+    #[default]
+    Battery,
+}
+
+impl PowerSource {
+    /// From the mains `online` attribute, `1` is AC
+    pub fn from_online(online: u8) -> Self {
+        if online == 1 {
+            PowerSource::Ac
+        } else {
+            PowerSource::Battery
+        }
+    }
+}
+
+/// Battery wear and charge as read from the `power_supply` battery
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, PartialEq, Eq, Clone)]
+pub struct BatteryHealth {
+    /// Current charge as a percentage
+    pub capacity: u8,
+    /// Charge cycles, `0` if the battery does not report them
+    pub cycle_count: u32,
+    /// Full capacity when new, in µWh or µAh depending on the battery
+    pub design_capacity: u32,
+    /// Full capacity now, in the same unit as `design_capacity`
+    pub full_capacity: u32,
+    /// `full_capacity` as a percentage of `design_capacity`
+    pub health: u8,
+}
+
+impl BatteryHealth {
+    pub fn new(capacity: u8, cycle_count: u32, design_capacity: u32, full_capacity: u32) -> Self {
+        let health = if design_capacity == 0 {
+            0
+        } else {
+            (full_capacity as u64 * 100 / design_capacity as u64).min(100) as u8
+        };
+        Self {
+            capacity,
+            cycle_count,
+            design_capacity,
+            full_capacity,
+            health,
+        }
+    }
+}
+
+fn read_attr_u32(device: &udev::Device, attr_name: &str) -> Result<u32> {
+    read_attr_string(device, attr_name)?
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| PlatformError::ParseNum)
+}
 
 /// The "platform" device provides access to things like:
 /// - `dgpu_disable`
@@ -17,6 +82,8 @@ pub struct AsusPower {
     mains: PathBuf,
     battery: PathBuf,
     usb: Option<PathBuf>,
+    /// Log and simulate every read and write instead of using sysfs
+    simulated: Option<Simulated>,
 }
 
 impl AsusPower {
@@ -24,6 +91,39 @@ impl AsusPower {
 
     attr_u8!("online", mains);
 
+    /// Read the charge, cycle count and wear of the battery found by `new()`.
+    /// Batteries report capacity as either `energy_*` or `charge_*`, both
+    /// are checked.
+    pub fn battery_health(&self) -> Result<BatteryHealth> {
+        if let Some(sim) = &self.simulated {
+            return Ok(BatteryHealth::new(
+                sim.read_u8("capacity")?,
+                100,
+                90_000_000,
+                81_000_000,
+            ));
+        }
+        if !self.battery.exists() {
+            return Err(PlatformError::MissingFunction(
+                "Did not find a battery".to_owned(),
+            ));
+        }
+        let device = to_device(&self.battery)?;
+        let capacity = read_attr_u8(&device, "capacity")?;
+        let cycle_count = read_attr_u32(&device, "cycle_count").unwrap_or(0);
+        let (design, full) = if has_attr(&device, "energy_full_design") {
+            ("energy_full_design", "energy_full")
+        } else {
+            ("charge_full_design", "charge_full")
+        };
+        Ok(BatteryHealth::new(
+            capacity,
+            cycle_count,
+            read_attr_u32(&device, design)?,
+            read_attr_u32(&device, full)?,
+        ))
+    }
+
     /// When checking for battery this will look in order:
     /// - if attr `manufacturer` contains `asus`
     /// - if attr `charge_control_end_threshold` exists and `energy_full_design`
@@ -99,6 +199,7 @@ impl AsusPower {
                 mains,
                 battery,
                 usb,
+                simulated: None,
             });
         }
 
@@ -106,4 +207,39 @@ impl AsusPower {
             "Did not find a battery".to_owned(),
         ))
     }
+
+    /// A simulated battery and mains supply for `--dry-run`. See
+    /// `crate::dry_run::Simulated`.
+    pub fn simulated() -> Self {
+        info!("dry-run: using a simulated battery");
+        Self {
+            mains: PathBuf::from("dry-run-mains"),
+            battery: PathBuf::from("dry-run-battery"),
+            usb: None,
+            simulated: Some(Simulated::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatteryHealth, PowerSource};
+
+    #[test]
+    fn battery_health_percent() {
+        let health = BatteryHealth::new(80, 120, 90_000_000, 81_000_000);
+        assert_eq!(health.health, 90);
+        // New batteries can report more than their design capacity
+        assert_eq!(
+            BatteryHealth::new(80, 0, 90_000_000, 95_000_000).health,
+            100
+        );
+        assert_eq!(BatteryHealth::new(80, 0, 0, 95_000_000).health, 0);
+    }
+
+    #[test]
+    fn power_source_from_online() {
+        assert_eq!(PowerSource::from_online(1), PowerSource::Ac);
+        assert_eq!(PowerSource::from_online(0), PowerSource::Battery);
+    }
 }
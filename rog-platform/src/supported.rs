@@ -8,6 +8,8 @@ use serde_derive::{Deserialize, Serialize};
 use typeshare::typeshare;
 use zbus::zvariant::Type;
 
+use crate::platform::MiniLedLevel;
+
 #[typeshare]
 #[derive(Serialize, Deserialize, Type, Debug, Default, Clone)]
 pub struct SupportedFunctions {
@@ -71,10 +73,14 @@ pub struct LedSupportedFunctions {
 pub struct PlatformSupportedFunctions {
     pub post_animation_sound: bool,
     pub gpu_mux: bool,
+    pub gpu_mux_switchable: bool,
     pub panel_overdrive: bool,
     pub dgpu_disable: bool,
     pub egpu_enable: bool,
     pub mini_led_mode: bool,
+    /// The mini-LED levels available, only `Off` and `MultiZone` if the panel
+    /// is on/off only
+    pub mini_led_levels: Vec<MiniLedLevel>,
 
     pub ppt_pl1_spl: bool,
     pub ppt_pl2_sppt: bool,
@@ -85,6 +91,126 @@ pub struct PlatformSupportedFunctions {
     pub nv_temp_target: bool,
 }
 
+/// Why a feature is not supported. `feature` is the path of the flag in
+/// `SupportedFunctions`, such as `rog_bios_ctrl.panel_overdrive`.
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, Clone, PartialEq, Eq)]
+pub struct UnsupportedReason {
+    pub feature: String,
+    pub reason: String,
+}
+
+impl UnsupportedReason {
+    pub fn new(feature: &str, reason: impl Into<String>) -> Self {
+        Self {
+            feature: feature.to_owned(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// `SupportedFunctions` along with the reason for each feature that is not
+/// supported
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Default, Clone)]
+pub struct SupportedDetails {
+    pub functions: SupportedFunctions,
+    pub reasons: Vec<UnsupportedReason>,
+}
+
+/// Marks a device node in `DeviceNodes` that the daemon did not find
+pub const NODE_NOT_FOUND: &str = "not found";
+
+/// The device nodes the daemon has claimed, for bug reports. Any that were not
+/// found are set to `NODE_NOT_FOUND`.
+#[typeshare]
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+pub struct DeviceNodes {
+    /// The `hidraw` or `asus::kbd_backlight` node used for LED modes
+    pub keyboard_led: String,
+    /// The `asus::kbd_backlight` node used for brightness
+    pub keyboard_brightness: String,
+    /// The syspath of the `asus-nb-wmi` or `asus-armoury` platform device
+    pub platform: String,
+    /// The `hidraw` or USB node of the AniMe Matrix
+    pub anime: String,
+}
+
+impl Default for DeviceNodes {
+    fn default() -> Self {
+        Self {
+            keyboard_led: NODE_NOT_FOUND.to_owned(),
+            keyboard_brightness: NODE_NOT_FOUND.to_owned(),
+            platform: NODE_NOT_FOUND.to_owned(),
+            anime: NODE_NOT_FOUND.to_owned(),
+        }
+    }
+}
+
+impl PlatformSupportedFunctions {
+    /// The reason for each unsupported feature. `driver_found` is if the
+    /// platform device was found at all, without it nothing is supported.
+    pub fn unsupported_reasons(&self, driver_found: bool) -> Vec<UnsupportedReason> {
+        let missing = |attr: &str| {
+            if driver_found {
+                format!(
+                    "`{attr}` is not exposed by the platform driver. It needs a newer kernel or \
+                     is not available on this laptop"
+                )
+            } else {
+                "No ASUS platform driver (asus-nb-wmi or asus-armoury) was found".to_owned()
+            }
+        };
+        let mut reasons = Vec::new();
+        for (feature, supported, attr) in [
+            (
+                "post_animation_sound",
+                self.post_animation_sound,
+                "post_animation_sound",
+            ),
+            ("gpu_mux", self.gpu_mux, "gpu_mux_mode"),
+            ("panel_overdrive", self.panel_overdrive, "panel_od"),
+            ("dgpu_disable", self.dgpu_disable, "dgpu_disable"),
+            ("egpu_enable", self.egpu_enable, "egpu_enable"),
+            ("mini_led_mode", self.mini_led_mode, "mini_led_mode"),
+            ("ppt_pl1_spl", self.ppt_pl1_spl, "ppt_pl1_spl"),
+            ("ppt_pl2_sppt", self.ppt_pl2_sppt, "ppt_pl2_sppt"),
+            ("ppt_fppt", self.ppt_fppt, "ppt_fppt"),
+            ("ppt_apu_sppt", self.ppt_apu_sppt, "ppt_apu_sppt"),
+            (
+                "ppt_platform_sppt",
+                self.ppt_platform_sppt,
+                "ppt_platform_sppt",
+            ),
+            (
+                "nv_dynamic_boost",
+                self.nv_dynamic_boost,
+                "nv_dynamic_boost",
+            ),
+            ("nv_temp_target", self.nv_temp_target, "nv_temp_target"),
+        ] {
+            if !supported {
+                reasons.push(UnsupportedReason::new(
+                    &format!("rog_bios_ctrl.{feature}"),
+                    missing(attr),
+                ));
+            }
+        }
+        if !self.gpu_mux_switchable {
+            let reason = if self.gpu_mux {
+                "`gpu_mux_mode` is read-only on this laptop".to_owned()
+            } else {
+                missing("gpu_mux_mode")
+            };
+            reasons.push(UnsupportedReason::new(
+                "rog_bios_ctrl.gpu_mux_switchable",
+                reason,
+            ));
+        }
+        reasons
+    }
+}
+
 impl fmt::Display for SupportedFunctions {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "\n{}", self.anime_ctrl)?;
@@ -135,9 +261,11 @@ impl fmt::Display for PlatformSupportedFunctions {
         writeln!(f, "\tPOST sound switch: {}", self.post_animation_sound)?;
         writeln!(f, "\tPanel Overdrive: {}", self.panel_overdrive)?;
         writeln!(f, "\tMiniLED backlight: {}", self.mini_led_mode)?;
+        writeln!(f, "\tMiniLED levels: {:?}", self.mini_led_levels)?;
         writeln!(f, "\tdGPU disable switch: {}", self.dgpu_disable)?;
         writeln!(f, "\teGPU enable switch: {}", self.egpu_enable)?;
         writeln!(f, "\tGPU MUX control: {}", self.gpu_mux)?;
+        writeln!(f, "\tGPU MUX switchable: {}", self.gpu_mux_switchable)?;
         writeln!(f, "\tppt_pl1_spl: {}", self.ppt_pl1_spl)?;
         writeln!(f, "\tppt_pl2_sppt: {}", self.ppt_pl2_sppt)?;
         writeln!(f, "\tppt_fppt {}", self.ppt_fppt)?;
@@ -148,3 +276,44 @@ impl fmt::Display for PlatformSupportedFunctions {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PlatformSupportedFunctions, UnsupportedReason};
+
+    #[test]
+    fn platform_unsupported_reasons() {
+        let none = PlatformSupportedFunctions::default();
+        let reasons = none.unsupported_reasons(false);
+        assert_eq!(reasons.len(), 14);
+        assert!(
+            reasons
+                .iter()
+                .all(|r| r.reason
+                    == "No ASUS platform driver (asus-nb-wmi or asus-armoury) was found")
+        );
+
+        let mut some = PlatformSupportedFunctions {
+            panel_overdrive: true,
+            gpu_mux: true,
+            ..Default::default()
+        };
+        let reasons = some.unsupported_reasons(true);
+        assert_eq!(reasons.len(), 12);
+        assert!(!reasons
+            .iter()
+            .any(|r| r.feature == "rog_bios_ctrl.panel_overdrive"));
+        assert!(reasons.contains(&UnsupportedReason::new(
+            "rog_bios_ctrl.gpu_mux_switchable",
+            "`gpu_mux_mode` is read-only on this laptop"
+        )));
+        assert!(reasons.contains(&UnsupportedReason::new(
+            "rog_bios_ctrl.mini_led_mode",
+            "`mini_led_mode` is not exposed by the platform driver. It needs a newer kernel or is \
+             not available on this laptop"
+        )));
+
+        some.gpu_mux_switchable = true;
+        assert_eq!(some.unsupported_reasons(true).len(), 11);
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
 use rusb::{Device, DeviceHandle};
@@ -34,6 +35,16 @@ impl USBRaw {
         Ok(device)
     }
 
+    /// The `/dev/bus/usb` node of the claimed device
+    pub fn dev_path(&self) -> PathBuf {
+        let device = self.0.device();
+        PathBuf::from(format!(
+            "/dev/bus/usb/{:03}/{:03}",
+            device.bus_number(),
+            device.address()
+        ))
+    }
+
     pub fn write_bytes(&self, message: &[u8]) -> Result<usize> {
         self.0
             .write_control(
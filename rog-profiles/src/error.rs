@@ -14,6 +14,10 @@ pub enum ProfileError {
     ParseFanCurvePrevHigher(&'static str, u8, u8),
     ParseFanCurvePercentOver100(u8),
     NotEnoughPoints,
+    TooManyPoints,
+    ParseFanCurveTempOutOfRange(u8),
+    /// (line, reason)
+    ParseFanCurveLine(String, &'static str),
     // Zbus(zbus::Error),
 }
 
@@ -26,6 +30,7 @@ impl fmt::Display for ProfileError {
             ProfileError::Write(path, error) => write!(f, "Write {}: {}", path, error),
             ProfileError::NotSupported => write!(f, "Not supported"),
             ProfileError::NotEnoughPoints => write!(f, "Less than 8 curve points supplied"),
+            ProfileError::TooManyPoints => write!(f, "More than 8 curve points supplied"),
             ProfileError::NotFound(deets) => write!(f, "Not found: {}", deets),
             ProfileError::Io(detail) => write!(f, "std::io error: {}", detail),
             ProfileError::ParseProfileName => write!(f, "Invalid profile name"),
@@ -39,6 +44,15 @@ impl fmt::Display for ProfileError {
             ),
             ProfileError::ParseFanCurvePercentOver100(value) => {
                 write!(f, "Invalid percentage, {} is higher than 100", value)
+            }
+            ProfileError::ParseFanCurveTempOutOfRange(value) => write!(
+                f,
+                "Invalid temperature, {} is higher than {}",
+                value,
+                crate::fan_curve_set::FAN_CURVE_MAX_TEMP
+            ),
+            ProfileError::ParseFanCurveLine(line, reason) => {
+                write!(f, "Invalid fan curve line \"{}\": {}", line, reason)
             } // Error::Zbus(detail) => write!(f, "Zbus error: {}", detail),
         }
     }
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use log::trace;
 use serde_derive::{Deserialize, Serialize};
 use typeshare::typeshare;
@@ -8,6 +10,10 @@ use zbus::zvariant::Type;
 use crate::error::ProfileError;
 use crate::FanCurvePU;
 
+/// Highest temperature point accepted by `import_curves`. Anything above this
+/// is almost certainly a typo rather than a real curve.
+pub const FAN_CURVE_MAX_TEMP: u8 = 120;
+
 fn set_sysfs_name(string: &mut [u8], fan: char, index: usize) {
     string[3] = fan as u8;
     string[15] = char::from_digit(index as u32 + 1, 10).unwrap() as u8;
@@ -142,6 +148,33 @@ impl CurveData {
         self.fan = fan;
     }
 
+    /// Check that the temperature points never decrease. The kernel accepts
+    /// out-of-order points but the resulting curve is not what the user
+    /// intended.
+    pub fn check_temperatures(&self) -> Result<(), ProfileError> {
+        for pair in self.temp.windows(2) {
+            if pair[0] > pair[1] {
+                return Err(ProfileError::ParseFanCurvePrevHigher(
+                    "temperature",
+                    pair[0],
+                    pair[1],
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// A copy of this curve with every point at full fan power, keeping the
+    /// temperatures
+    pub fn full_speed(&self) -> Self {
+        Self {
+            fan: self.fan,
+            pwm: [255; 8],
+            temp: self.temp,
+            enabled: true,
+        }
+    }
+
     fn set_val_from_attr(tmp: &str, device: &Device, buf: &mut [u8; 8]) {
         if let Some(n) = tmp.chars().nth(15) {
             let i = n.to_digit(10).unwrap() as usize;
@@ -187,12 +220,96 @@ impl CurveData {
     }
 }
 
+/// Format the curves of a profile as portable text that can be shared and
+/// given back to `import_curves`. There is one line per fan of the form
+/// `cpu=30:10,40:20,...` where each point is `temp:pwm` with the raw 0-255
+/// fan power value. The enabled state is not included.
+pub fn export_curves(curves: &[CurveData]) -> String {
+    let mut out = String::new();
+    for curve in curves {
+        let fan: &str = curve.fan.into();
+        let points: Vec<String> = curve
+            .temp
+            .iter()
+            .zip(curve.pwm.iter())
+            .map(|(t, p)| format!("{t}:{p}"))
+            .collect();
+        out.push_str(&format!("{fan}={}\n", points.join(",")));
+    }
+    out
+}
+
+/// Parse the text made by `export_curves`. Blank lines and lines starting
+/// with `#` are skipped. Each curve must have 8 points with temperatures and
+/// fan power that never decrease, and temperatures no higher than
+/// `FAN_CURVE_MAX_TEMP`. The returned curves are not enabled.
+pub fn import_curves(data: &str) -> Result<Vec<CurveData>, ProfileError> {
+    let mut curves: Vec<CurveData> = Vec::with_capacity(3);
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (fan, points) = line
+            .split_once('=')
+            .ok_or_else(|| ProfileError::ParseFanCurveLine(line.to_owned(), "missing '='"))?;
+        let fan = FanCurvePU::from_str(fan)
+            .map_err(|_| ProfileError::ParseFanCurveLine(line.to_owned(), "unknown fan"))?;
+        if curves.iter().any(|c| c.fan == fan) {
+            return Err(ProfileError::ParseFanCurveLine(
+                line.to_owned(),
+                "fan is listed more than once",
+            ));
+        }
+        if points.contains('%') || points.contains('c') {
+            return Err(ProfileError::ParseFanCurveLine(
+                line.to_owned(),
+                "points must be raw temp:pwm",
+            ));
+        }
+        let count = points.split(',').count();
+        if count < 8 {
+            return Err(ProfileError::NotEnoughPoints);
+        } else if count > 8 {
+            return Err(ProfileError::TooManyPoints);
+        }
+
+        let mut curve = CurveData::from_str(points)?;
+        if let Some(temp) = curve.temp.iter().find(|t| **t > FAN_CURVE_MAX_TEMP) {
+            return Err(ProfileError::ParseFanCurveTempOutOfRange(*temp));
+        }
+        curve.set_fan(fan);
+        curves.push(curve);
+    }
+
+    if curves.is_empty() {
+        return Err(ProfileError::NotEnoughPoints);
+    }
+    Ok(curves)
+}
+
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     use super::*;
 
+    #[test]
+    fn full_speed_keeps_temperatures() {
+        let curve =
+            CurveData::from_str("30c:1%,49c:2%,59c:3%,69c:4%,79c:31%,89c:49%,99c:56%,109c:58%")
+                .unwrap();
+        let mut gpu = curve.clone();
+        gpu.set_fan(FanCurvePU::GPU);
+
+        for curve in [curve, gpu] {
+            let full = curve.full_speed();
+            assert_eq!(full.fan, curve.fan);
+            assert_eq!(full.temp, curve.temp);
+            assert_eq!(full.pwm, [255; 8]);
+            assert!(full.enabled);
+        }
+    }
+
     #[test]
     fn curve_data_from_str_to_str() {
         let mut curve =
@@ -234,6 +351,101 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn curve_data_check_temperatures() {
+        let mut curve =
+            CurveData::from_str("30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58").unwrap();
+        assert!(curve.check_temperatures().is_ok());
+
+        curve.temp[3] = 40;
+        assert!(matches!(
+            curve.check_temperatures(),
+            Err(ProfileError::ParseFanCurvePrevHigher("temperature", 59, 40))
+        ));
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let mut cpu = CurveData::from_str("30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58").unwrap();
+        cpu.enabled = true;
+        let mut gpu =
+            CurveData::from_str("35:10,45:20,55:30,65:40,75:50,85:60,95:70,105:255").unwrap();
+        gpu.set_fan(FanCurvePU::GPU);
+
+        let text = export_curves(&[cpu.clone(), gpu.clone()]);
+        assert_eq!(
+            text,
+            "cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58\ngpu=35:10,45:20,55:30,65:40,75:50,\
+             85:60,95:70,105:255\n"
+        );
+
+        let curves = import_curves(&text).unwrap();
+        assert_eq!(curves.len(), 2);
+        for (curve, orig) in curves.iter().zip([cpu, gpu].iter()) {
+            assert_eq!(curve.fan, orig.fan);
+            assert_eq!(curve.temp, orig.temp);
+            assert_eq!(curve.pwm, orig.pwm);
+            assert!(!curve.enabled);
+        }
+        assert_eq!(export_curves(&curves), text);
+    }
+
+    #[test]
+    fn import_curves_comments_and_blank_lines() {
+        let curves = import_curves(
+            "# shared curve\n\n  MID=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58  \n",
+        )
+        .unwrap();
+        assert_eq!(curves.len(), 1);
+        assert_eq!(curves[0].fan, FanCurvePU::MID);
+    }
+
+    #[test]
+    fn import_curves_rejects_malformed() {
+        assert!(matches!(
+            import_curves("cpu=30:1,20:2,59:3,69:4,79:31,89:49,99:56,109:58"),
+            Err(ProfileError::ParseFanCurvePrevHigher("temperature", 30, 20))
+        ));
+        assert!(matches!(
+            import_curves("cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56,130:58"),
+            Err(ProfileError::ParseFanCurveTempOutOfRange(130))
+        ));
+        assert!(matches!(
+            import_curves("cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56"),
+            Err(ProfileError::NotEnoughPoints)
+        ));
+        assert!(matches!(
+            import_curves("cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58,110:60"),
+            Err(ProfileError::TooManyPoints)
+        ));
+        assert!(matches!(
+            import_curves("cpu=30:1,49:x,59:3,69:4,79:31,89:49,99:56,109:58"),
+            Err(ProfileError::ParseFanCurveDigit(_))
+        ));
+        assert!(matches!(
+            import_curves("cpu=30c:1%,49c:2%,59c:3%,69c:4%,79c:31%,89c:49%,99c:56%,109c:58%"),
+            Err(ProfileError::ParseFanCurveLine(_, _))
+        ));
+        assert!(matches!(
+            import_curves("fan=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58"),
+            Err(ProfileError::ParseFanCurveLine(_, "unknown fan"))
+        ));
+        assert!(matches!(
+            import_curves("30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58"),
+            Err(ProfileError::ParseFanCurveLine(_, "missing '='"))
+        ));
+        let dup = "cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58\n\
+                   cpu=30:1,49:2,59:3,69:4,79:31,89:49,99:56,109:58";
+        assert!(matches!(
+            import_curves(dup),
+            Err(ProfileError::ParseFanCurveLine(_, _))
+        ));
+        assert!(matches!(
+            import_curves("# nothing\n"),
+            Err(ProfileError::NotEnoughPoints)
+        ));
+    }
+
     #[test]
     fn check_pwm_str() {
         assert_eq!(pwm_str('1', 0), "pwm1_auto_point1_pwm");
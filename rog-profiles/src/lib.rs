@@ -58,7 +58,21 @@ impl Profile {
 
     pub fn get_profile_names() -> Result<Vec<Profile>, ProfileError> {
         let buf = fs::read_to_string(PLATFORM_PROFILES)?;
-        Ok(buf.rsplit(' ').map(|p| p.into()).collect())
+        Ok(Self::parse_profile_names(&buf))
+    }
+
+    /// Parse `platform_profile_choices` in to the supported profiles, ordered
+    /// Quiet, Balanced, Performance. Choices asusd has no profile for, such
+    /// as `low-power`, are skipped rather than read as `Balanced`.
+    pub fn parse_profile_names(choices: &str) -> Vec<Profile> {
+        let found: Vec<Profile> = choices
+            .split_whitespace()
+            .filter_map(|p| p.parse::<Profile>().ok())
+            .collect();
+        [Profile::Quiet, Profile::Balanced, Profile::Performance]
+            .into_iter()
+            .filter(|p| found.contains(p))
+            .collect()
     }
 
     pub fn set_profile(profile: Profile) -> Result<(), ProfileError> {
@@ -83,6 +97,19 @@ impl Profile {
             Profile::Quiet => Profile::Balanced,
         }
     }
+
+    /// The next profile in the order Quiet, Balanced, Performance, skipping
+    /// those not in `available` and wrapping around at the end
+    pub fn next_available(current: Profile, available: &[Profile]) -> Profile {
+        let mut next = Profile::get_next_profile(current);
+        while next != current {
+            if available.contains(&next) {
+                return next;
+            }
+            next = Profile::get_next_profile(next);
+        }
+        current
+    }
 }
 
 impl Default for Profile {
@@ -419,3 +446,52 @@ impl FanCurveProfiles {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Profile;
+
+    #[test]
+    fn parse_profile_names() {
+        assert_eq!(
+            Profile::parse_profile_names("quiet balanced performance\n"),
+            vec![Profile::Quiet, Profile::Balanced, Profile::Performance]
+        );
+        assert_eq!(
+            Profile::parse_profile_names("performance balanced"),
+            vec![Profile::Balanced, Profile::Performance]
+        );
+        assert_eq!(
+            Profile::parse_profile_names("low-power balanced-performance balanced"),
+            vec![Profile::Balanced]
+        );
+        assert!(Profile::parse_profile_names("").is_empty());
+    }
+
+    #[test]
+    fn next_available_profile() {
+        let all = [Profile::Quiet, Profile::Balanced, Profile::Performance];
+        assert_eq!(
+            Profile::next_available(Profile::Quiet, &all),
+            Profile::Balanced
+        );
+        assert_eq!(
+            Profile::next_available(Profile::Balanced, &all),
+            Profile::Performance
+        );
+        assert_eq!(
+            Profile::next_available(Profile::Performance, &all),
+            Profile::Quiet
+        );
+
+        let no_quiet = [Profile::Performance, Profile::Balanced];
+        assert_eq!(
+            Profile::next_available(Profile::Performance, &no_quiet),
+            Profile::Balanced
+        );
+        assert_eq!(
+            Profile::next_available(Profile::Balanced, &[Profile::Balanced]),
+            Profile::Balanced
+        );
+    }
+}